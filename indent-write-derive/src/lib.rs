@@ -0,0 +1,500 @@
+//! Derive macro for indented [`Display`] implementations, for use with
+//! [`indent_write`]. Enable the `derive` feature of `indent_write` to use it,
+//! rather than depending on this crate directly.
+//!
+//! See `indent_write::IndentDisplay` for documentation.
+//!
+//! [`Display`]: core::fmt::Display
+//! [`indent_write`]: https://docs.rs/indent_write
+
+#![forbid(unsafe_code)]
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Derive [`Display`][core::fmt::Display] and `IndentFields` for a struct or
+/// enum, writing its name followed by each of its fields on an indented
+/// line.
+#[proc_macro_derive(IndentDisplay, attributes(indent))]
+pub fn derive_indent_display(input: TokenStream) -> TokenStream {
+    let output = match Input::parse(input) {
+        Ok(input) => input.expand(),
+        Err(message) => format!("::core::compile_error!({:?});", message),
+    };
+
+    output
+        .parse()
+        .expect("indent-write-derive generated invalid code")
+}
+
+/// The options in the `#[indent(...)]` attributes of an item.
+#[derive(Default)]
+struct Options {
+    prefix: Option<String>,
+    skip: bool,
+    flatten: bool,
+}
+
+impl Options {
+    /// Parse the `#[indent(...)]` attributes at `tokens[*pos..]`, skipping
+    /// any other attributes.
+    fn parse(tokens: &[TokenTree], pos: &mut usize) -> Result<Self, String> {
+        let mut options = Options::default();
+
+        while let (Some(TokenTree::Punct(hash)), Some(TokenTree::Group(attr))) =
+            (tokens.get(*pos), tokens.get(*pos + 1))
+        {
+            if hash.as_char() != '#' || attr.delimiter() != Delimiter::Bracket {
+                break;
+            }
+            *pos += 2;
+
+            let attr: Vec<TokenTree> = attr.stream().into_iter().collect();
+            let args = match attr.as_slice() {
+                [TokenTree::Ident(name), TokenTree::Group(args)]
+                    if name.to_string() == "indent"
+                        && args.delimiter() == Delimiter::Parenthesis =>
+                {
+                    args.stream().into_iter().collect::<Vec<_>>()
+                }
+                [TokenTree::Ident(name), ..] if name.to_string() == "indent" => {
+                    return Err("expected `#[indent(...)]`".into())
+                }
+                _ => continue,
+            };
+
+            for arg in split(&args, false) {
+                match arg {
+                    [TokenTree::Ident(name)] if name.to_string() == "skip" => options.skip = true,
+                    [TokenTree::Ident(name)] if name.to_string() == "flatten" => {
+                        options.flatten = true
+                    }
+                    [TokenTree::Ident(name), TokenTree::Punct(eq), TokenTree::Literal(prefix)]
+                        if name.to_string() == "prefix" && eq.as_char() == '=' =>
+                    {
+                        let prefix = prefix.to_string();
+                        if !prefix.starts_with('"') && !prefix.starts_with("r\"") && !prefix.starts_with("r#") {
+                            return Err("the `prefix` must be a string literal".into());
+                        }
+                        options.prefix = Some(prefix);
+                    }
+                    _ => {
+                        return Err(format!(
+                            "unknown `indent` option `{}`; expected `prefix = \"...\"`, `skip`, or `flatten`",
+                            stringify(arg),
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// A field of a struct or enum variant.
+struct Field {
+    /// The field's name, or its index in a tuple struct or variant
+    member: String,
+    options: Options,
+}
+
+impl Field {
+    /// The name written before the field's value.
+    fn label(&self) -> &str {
+        self.member.trim_start_matches("r#")
+    }
+}
+
+/// A variant of an enum, or the body of a struct.
+struct Variant {
+    name: String,
+    path: String,
+    fields: Vec<Field>,
+}
+
+impl Variant {
+    /// Parse the fields in the body of a struct or variant.
+    fn parse(name: String, path: String, body: Option<&TokenTree>) -> Result<Self, String> {
+        let fields = match body {
+            Some(TokenTree::Group(group)) if group.delimiter() != Delimiter::Bracket => {
+                let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+                let named = group.delimiter() == Delimiter::Brace;
+
+                split(&tokens, true)
+                    .filter(|field| !field.is_empty())
+                    .enumerate()
+                    .map(|(idx, field)| {
+                        let mut pos = 0;
+                        let options = Options::parse(field, &mut pos)?;
+                        if options.prefix.is_some() {
+                            return Err("`prefix` can only be used on the type".into());
+                        }
+                        skip_visibility(field, &mut pos);
+
+                        let member = match (named, field.get(pos)) {
+                            (false, _) => idx.to_string(),
+                            (true, Some(TokenTree::Ident(ident))) => ident.to_string(),
+                            (true, _) => return Err("expected a field name".into()),
+                        };
+
+                        Ok(Field { member, options })
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(Variant { name, path, fields })
+    }
+
+    /// The pattern that matches this variant, binding each field that's
+    /// written.
+    fn pattern(&self) -> String {
+        let bindings: String = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| !field.options.skip)
+            .map(|(idx, field)| format!("{}: __indent_field_{}, ", field.member, idx))
+            .collect();
+
+        format!("{} {{ {}.. }}", self.path, bindings)
+    }
+
+    /// The statements that write each field of this variant to `dest`.
+    fn write_fields(&self) -> String {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| !field.options.skip)
+            .map(|(idx, field)| match field.options.flatten {
+                true => format!(
+                    "::indent_write::indentable::IndentFields::fmt_fields(__indent_field_{}, dest)?;",
+                    idx,
+                ),
+                false => format!(
+                    "::core::write!(dest, \"\\n{{}}: {{}}\", {:?}, __indent_field_{})?;",
+                    field.label(),
+                    idx,
+                ),
+            })
+            .collect()
+    }
+}
+
+/// A parsed struct or enum.
+struct Input {
+    name: String,
+    prefix: String,
+    impl_generics: String,
+    ty_generics: String,
+    where_clause: String,
+    is_enum: bool,
+    variants: Vec<Variant>,
+}
+
+impl Input {
+    fn parse(input: TokenStream) -> Result<Self, String> {
+        let tokens: Vec<TokenTree> = input.into_iter().collect();
+        let mut pos = 0;
+
+        let options = Options::parse(&tokens, &mut pos)?;
+        if options.skip || options.flatten {
+            return Err("`skip` and `flatten` can only be used on fields".into());
+        }
+        let prefix = options.prefix.unwrap_or_else(|| "\"  \"".into());
+
+        skip_visibility(&tokens, &mut pos);
+
+        let is_enum = match tokens.get(pos) {
+            Some(TokenTree::Ident(kind)) if kind.to_string() == "struct" => false,
+            Some(TokenTree::Ident(kind)) if kind.to_string() == "enum" => true,
+            _ => return Err("`IndentDisplay` can only be derived for structs and enums".into()),
+        };
+
+        let name = match tokens.get(pos + 1) {
+            Some(TokenTree::Ident(name)) => name.to_string(),
+            _ => return Err("expected a type name".into()),
+        };
+        pos += 2;
+
+        let generics = parse_generics(&tokens, &mut pos);
+
+        // The body of a tuple struct comes right after the generics, followed
+        // by any where clause. Otherwise, the body is the first braced group,
+        // after any where clause, or absent, for unit structs.
+        let body_pos = match tokens.get(pos) {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                Some(pos)
+            }
+            _ => tokens[pos..]
+                .iter()
+                .position(|token| {
+                    matches!(token, TokenTree::Group(group) if group.delimiter() == Delimiter::Brace)
+                })
+                .map(|idx| pos + idx),
+        };
+
+        let where_tokens: Vec<TokenTree> = tokens[pos..]
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| Some(pos + idx) != body_pos)
+            .map(|(_, token)| token.clone())
+            .filter(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ';'))
+            .skip_while(
+                |token| !matches!(token, TokenTree::Ident(ident) if ident.to_string() == "where"),
+            )
+            .skip(1)
+            .collect();
+
+        let where_clause = {
+            let mut predicates = stringify(&where_tokens);
+            for param in &generics.type_params {
+                if !predicates.trim_end().is_empty() && !predicates.trim_end().ends_with(',') {
+                    predicates.push(',');
+                }
+                predicates.push_str(&format!(" {}: ::core::fmt::Display", param));
+            }
+
+            match predicates.trim().is_empty() {
+                true => String::new(),
+                false => format!("where {}", predicates),
+            }
+        };
+
+        let body = body_pos.map(|idx| &tokens[idx]);
+
+        let variants = match is_enum {
+            false => vec![Variant::parse(name.clone(), "Self".into(), body)?],
+            true => {
+                let variants: Vec<TokenTree> = match body {
+                    Some(TokenTree::Group(group)) => group.stream().into_iter().collect(),
+                    _ => return Err("expected the body of an enum".into()),
+                };
+
+                split(&variants, false)
+                    .filter(|variant| !variant.is_empty())
+                    .map(|variant| {
+                        let mut pos = 0;
+                        let options = Options::parse(variant, &mut pos)?;
+                        if options.prefix.is_some() || options.skip || options.flatten {
+                            return Err("`indent` options can't be used on variants".into());
+                        }
+
+                        let name = match variant.get(pos) {
+                            Some(TokenTree::Ident(name)) => name.to_string(),
+                            _ => return Err("expected a variant name".into()),
+                        };
+                        let path = format!("Self::{}", name);
+
+                        Variant::parse(name, path, variant.get(pos + 1))
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+        };
+
+        Ok(Input {
+            name,
+            prefix,
+            impl_generics: generics.impl_generics,
+            ty_generics: generics.ty_generics,
+            where_clause,
+            is_enum,
+            variants,
+        })
+    }
+
+    fn expand(&self) -> String {
+        // An enum with no variants can't be constructed, so its methods are
+        // never called
+        let (name_expr, fields_expr) = match self.is_enum && self.variants.is_empty() {
+            true => ("match *self {}".to_string(), "match *self {}".to_string()),
+            false => {
+                let names: String = self
+                    .variants
+                    .iter()
+                    .map(|variant| format!("{} {{ .. }} => {:?},", variant.path, variant.name))
+                    .collect();
+
+                let fields: String = self
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        format!("{} => {{ {} }}", variant.pattern(), variant.write_fields())
+                    })
+                    .collect();
+
+                (
+                    format!("match self {{ {} }}", names),
+                    format!("match self {{ {} }} ::core::result::Result::Ok(())", fields),
+                )
+            }
+        };
+
+        format!(
+            "
+            impl {impl_generics} ::core::fmt::Display for {name} {ty_generics} {where_clause} {{
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {{
+                    f.write_str({name_expr})?;
+                    let mut writer = ::indent_write::fmt::IndentWriter::new({prefix}, f);
+                    ::indent_write::indentable::IndentFields::fmt_fields(self, &mut writer)
+                }}
+            }}
+
+            impl {impl_generics} ::indent_write::indentable::IndentFields
+                for {name} {ty_generics} {where_clause}
+            {{
+                #[allow(unused_variables)]
+                fn fmt_fields(
+                    &self,
+                    dest: &mut dyn ::core::fmt::Write,
+                ) -> ::core::fmt::Result {{
+                    {fields_expr}
+                }}
+            }}
+            ",
+            impl_generics = self.impl_generics,
+            name = self.name,
+            ty_generics = self.ty_generics,
+            where_clause = self.where_clause,
+            name_expr = name_expr,
+            prefix = self.prefix,
+            fields_expr = fields_expr,
+        )
+    }
+}
+
+/// The generics of a type, as they're written in an impl.
+#[derive(Default)]
+struct Generics {
+    /// The parameters of the impl, such as `<'a, T: Clone, const N: usize>`
+    impl_generics: String,
+
+    /// The arguments of the type, such as `<'a, T, N>`
+    ty_generics: String,
+
+    /// The names of the type parameters, such as `T`
+    type_params: Vec<String>,
+}
+
+/// Parse the generic parameters at `tokens[*pos..]`, if there are any.
+fn parse_generics(tokens: &[TokenTree], pos: &mut usize) -> Generics {
+    match tokens.get(*pos) {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {}
+        _ => return Generics::default(),
+    }
+
+    // Find the matching '>'
+    let mut depth = 0;
+    let mut end = *pos;
+    for (idx, token) in tokens.iter().enumerate().skip(*pos) {
+        depth += angle_depth(tokens, idx, token);
+        if depth == 0 {
+            end = idx;
+            break;
+        }
+    }
+
+    let params = &tokens[*pos + 1..end];
+    *pos = end + 1;
+
+    let mut generics = Generics::default();
+    let mut impl_params = Vec::new();
+    let mut ty_args = Vec::new();
+
+    for param in split(params, true).filter(|param| !param.is_empty()) {
+        // Remove any default
+        let mut depth = 0;
+        let without_default = param
+            .iter()
+            .enumerate()
+            .take_while(|&(idx, token)| {
+                depth += angle_depth(param, idx, token);
+                !(depth == 0 && matches!(token, TokenTree::Punct(punct) if punct.as_char() == '='))
+            })
+            .count();
+        impl_params.push(stringify(&param[..without_default]));
+
+        match param {
+            [TokenTree::Punct(quote), TokenTree::Ident(_), ..] if quote.as_char() == '\'' => {
+                ty_args.push(stringify(&param[..2]));
+            }
+            [TokenTree::Ident(kw), TokenTree::Ident(name), ..] if kw.to_string() == "const" => {
+                ty_args.push(name.to_string());
+            }
+            [TokenTree::Ident(name), ..] => {
+                ty_args.push(name.to_string());
+                generics.type_params.push(name.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    generics.impl_generics = format!("<{}>", impl_params.join(", "));
+    generics.ty_generics = format!("<{}>", ty_args.join(", "));
+    generics
+}
+
+/// How the token at `tokens[idx]` changes the depth of angle brackets. The
+/// `>` of a `->` doesn't close a bracket.
+fn angle_depth(tokens: &[TokenTree], idx: usize, token: &TokenTree) -> i32 {
+    match token {
+        TokenTree::Punct(punct) if punct.as_char() == '<' => 1,
+        TokenTree::Punct(punct) if punct.as_char() == '>' => {
+            match idx.checked_sub(1).map(|idx| &tokens[idx]) {
+                Some(TokenTree::Punct(prev))
+                    if prev.as_char() == '-' && prev.spacing() == Spacing::Joint =>
+                {
+                    0
+                }
+                _ => -1,
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Split `tokens` on commas. If `angles` is true, commas between angle
+/// brackets, such as in `HashMap<K, V>`, don't split.
+fn split(tokens: &[TokenTree], angles: bool) -> impl Iterator<Item = &[TokenTree]> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut pieces = Vec::new();
+
+    for (idx, token) in tokens.iter().enumerate() {
+        if angles {
+            depth += angle_depth(tokens, idx, token);
+        }
+
+        if let TokenTree::Punct(punct) = token {
+            if punct.as_char() == ',' && depth == 0 {
+                pieces.push(&tokens[start..idx]);
+                start = idx + 1;
+            }
+        }
+    }
+
+    pieces.push(&tokens[start..]);
+    pieces.into_iter()
+}
+
+/// Skip a visibility, such as `pub` or `pub(crate)`, at `tokens[*pos..]`.
+fn skip_visibility(tokens: &[TokenTree], pos: &mut usize) {
+    if let Some(TokenTree::Ident(ident)) = tokens.get(*pos) {
+        if ident.to_string() == "pub" {
+            *pos += 1;
+
+            if let Some(TokenTree::Group(group)) = tokens.get(*pos) {
+                if group.delimiter() == Delimiter::Parenthesis {
+                    *pos += 1;
+                }
+            }
+        }
+    }
+}
+
+fn stringify(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
@@ -1,4 +1,208 @@
-use core::fmt;
+use core::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+use columns::Columns;
+use levels::LineIndent;
+
+#[cfg(feature = "alloc")]
+mod align;
+#[cfg(feature = "alloc")]
+mod ansi;
+mod bom;
+mod builder;
+mod columns;
+mod dedent;
+#[cfg(feature = "std")]
+mod error_chain;
+mod gutter;
+mod levels;
+#[cfg(feature = "alloc")]
+mod line_filter;
+#[cfg(feature = "alloc")]
+mod line_map;
+#[cfg(feature = "alloc")]
+mod line_prefix;
+mod list;
+mod nested;
+mod quote;
+mod reserve;
+mod scope;
+#[cfg(feature = "alloc")]
+mod shared;
+mod skip;
+#[cfg(feature = "alloc")]
+mod translate;
+mod tree;
+#[cfg(feature = "alloc")]
+mod truncate;
+#[cfg(feature = "wrap")]
+mod wrap;
+
+#[cfg(feature = "alloc")]
+pub use align::AlignWriter;
+pub use builder::IndentWriterBuilder;
+pub use dedent::DedentWriter;
+#[cfg(feature = "std")]
+pub use error_chain::{write_error_chain, ErrorChain};
+pub use gutter::GutterWriter;
+pub use levels::LevelOverflow;
+#[cfg(feature = "alloc")]
+pub use line_filter::LineFilterWriter;
+#[cfg(feature = "alloc")]
+pub use line_map::LineMapWriter;
+#[cfg(feature = "alloc")]
+pub use line_prefix::LinePrefixWriter;
+pub use list::ListItemWriter;
+pub use nested::NestedIndentWriter;
+pub use quote::QuoteWriter;
+pub use reserve::ReserveHint;
+pub use scope::{IndentGuard, MaxLevelError};
+#[cfg(feature = "alloc")]
+pub use shared::{SharedLevel, SharedLevelGuard};
+#[cfg(feature = "alloc")]
+pub use translate::{IndentStyle, TranslateIndentWriter};
+pub use tree::{TreeStyle, TreeWriter};
+
+const SPACES: &str = "                ";
+const TABS: &str = "\t\t\t\t\t\t\t\t";
+
+/// The maximum number of bytes of leading whitespace held back while waiting
+/// to see if a line is blank
+#[cfg(feature = "alloc")]
+const MAX_PENDING: usize = 4096;
+
+/// An indent to be inserted at the front of each non-empty line.
+///
+/// [`Spaces`][Indent::Spaces] and [`Tabs`][Indent::Tabs] are written without
+/// allocating, regardless of the count. They represent `n` repetitions of a
+/// single character, so repeating such an indent (for instance, to express
+/// several levels of indentation) multiplies the count.
+///
+/// Any `&str` can be converted into an [`Indent::Prefix`], so most functions
+/// that accept an `Indent` can be passed a string directly.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::{Indent, IndentWriter};
+///
+/// let mut writer = IndentWriter::new(Indent::Spaces(4), String::new());
+/// write!(writer, "Line 1\nLine 2\n").unwrap();
+/// assert_eq!(writer.get_ref(), "    Line 1\n    Line 2\n");
+///
+/// assert_eq!(Indent::Tabs(2).to_string(), "\t\t");
+/// assert_eq!(Indent::from("> "), Indent::Prefix("> "));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Indent<'i> {
+    /// Indent with the given number of spaces.
+    Spaces(usize),
+
+    /// Indent with the given number of tabs.
+    Tabs(usize),
+
+    /// Indent with an arbitrary string.
+    ///
+    /// The string may contain newlines. It's always written as-is at the start
+    /// of each non-empty line; newlines inside it don't count as the end of
+    /// the line being indented, but they are included in the writer's
+    /// [`line`][IndentWriter::line] and [`column`][IndentWriter::column].
+    Prefix(&'i str),
+}
+
+impl<'i> Indent<'i> {
+    /// Call `func` with successive chunks of this indent, stopping at the
+    /// first error.
+    fn try_for_each_chunk(self, mut func: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+        let (chunk, mut count) = match self {
+            Indent::Spaces(count) => (SPACES, count),
+            Indent::Tabs(count) => (TABS, count),
+            Indent::Prefix(prefix) => return func(prefix),
+        };
+
+        while count > 0 {
+            let len = count.min(chunk.len());
+            func(&chunk[..len])?;
+            count -= len;
+        }
+
+        Ok(())
+    }
+
+    /// Call `func` with successive chunks of this indent, repeated `count`
+    /// times, stopping at the first error.
+    fn try_for_each_repeated_chunk(
+        self,
+        count: usize,
+        mut func: impl FnMut(&str) -> fmt::Result,
+    ) -> fmt::Result {
+        match self {
+            Indent::Spaces(n) => Indent::Spaces(n * count).try_for_each_chunk(func),
+            Indent::Tabs(n) => Indent::Tabs(n * count).try_for_each_chunk(func),
+            Indent::Prefix(prefix) => (0..count).try_for_each(|_| func(prefix)),
+        }
+    }
+
+    /// The bytes of this indent, repeated `count` times.
+    fn repeated_bytes(self, count: usize) -> impl Iterator<Item = u8> + 'i {
+        let (prefix, len) = match self {
+            Indent::Spaces(n) => (" ", n),
+            Indent::Tabs(n) => ("\t", n),
+            Indent::Prefix(prefix) => (prefix, prefix.len()),
+        };
+
+        prefix.bytes().cycle().take(len * count)
+    }
+
+    /// The number of bytes in this indent.
+    fn byte_len(self) -> usize {
+        match self {
+            Indent::Spaces(count) | Indent::Tabs(count) => count,
+            Indent::Prefix(prefix) => prefix.len(),
+        }
+    }
+
+    /// Check if this indent is empty.
+    fn is_empty(self) -> bool {
+        match self {
+            Indent::Spaces(count) | Indent::Tabs(count) => count == 0,
+            Indent::Prefix(prefix) => prefix.is_empty(),
+        }
+    }
+
+    /// This indent, with trailing whitespace removed.
+    fn trim_end(self) -> Self {
+        match self {
+            Indent::Prefix(prefix) => Indent::Prefix(prefix.trim_end()),
+            Indent::Spaces(_) | Indent::Tabs(_) => Indent::Prefix(""),
+        }
+    }
+}
+
+impl<'i> From<&'i str> for Indent<'i> {
+    #[inline]
+    fn from(prefix: &'i str) -> Self {
+        Indent::Prefix(prefix)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'i> From<&'i String> for Indent<'i> {
+    #[inline]
+    fn from(prefix: &'i String) -> Self {
+        Indent::Prefix(prefix)
+    }
+}
+
+impl Display for Indent<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.try_for_each_chunk(|chunk| f.write_str(chunk))
+    }
+}
+
 /// Adapter for writers to indent each line
 ///
 /// An `IndentWriter` adapts a [`fmt::Write`] object to insert an indent before
@@ -7,6 +211,16 @@ use core::fmt;
 ///
 /// These writers can be nested to provide increasing levels of indentation.
 ///
+/// # Errors
+///
+/// If the inner writer returns an error (or panics), the `IndentWriter`'s
+/// state reflects exactly the writes that the inner writer completed. In
+/// particular, an indent or line suffix that was partially written will be
+/// resumed, rather than repeated, by the next write.
+/// [`last_write_accepted`][Self::last_write_accepted] reports how much of a
+/// failed [`write_str`][fmt::Write::write_str] was handled, so that the
+/// remainder can be retried.
+///
 /// # Example
 ///
 /// ```
@@ -28,18 +242,160 @@ use core::fmt;
 #[derive(Debug, Clone)]
 pub struct IndentWriter<'i, W> {
     writer: W,
-    indent: &'i str,
+    indent: Indent<'i>,
+    level: usize,
     need_indent: bool,
+
+    // The prefix for each level, used in place of repeating `indent`, if any
+    level_prefixes: Option<levels::LevelPrefixes<'i>>,
+
+    // The indent to use in place of `indent` the first time an indent is
+    // inserted, if any
+    first_indent: Option<Indent<'i>>,
+    indent_empty_lines: bool,
+
+    // The number of bytes of the current line's indent that have been
+    // written, or None if we haven't started writing it. This allows an
+    // indent to be resumed, rather than repeated, if the inner writer fails
+    // partway through it.
+    indent_written: Option<usize>,
+
+    // True if the line suffix was written, but the newline after it wasn't
+    suffix_written: bool,
+
+    // The number of bytes of the most recent write that were written or held
+    // back
+    accepted: usize,
+
+    skip_indented: bool,
+
+    // False if indentation is disabled, and content is written unchanged
+    enabled: bool,
+
+    // True until the first character is written, so that a byte order mark
+    // there can be handled
+    stream_start: bool,
+    strip_bom: bool,
+
+    // When skipping lines that are already indented, the number of bytes at
+    // the start of the current line that match the indent, and have been
+    // held back. None if the current line didn't match.
+    indent_matched: Option<usize>,
+    line: usize,
+    // True if the most recent complete line of output was empty
+    prev_line_blank: bool,
+    column: usize,
+
+    // The column where the content of the current line starts, after its
+    // indent
+    indent_end: usize,
+    line_suffix: &'i str,
+    max_level: Option<usize>,
+    max_width: Option<usize>,
+    tab_width: Option<usize>,
+    #[cfg(feature = "alloc")]
+    truncate: Option<truncate::Truncate<'i>>,
+    #[cfg(feature = "wrap")]
+    wrap: Option<wrap::WordWrap>,
+    #[cfg(feature = "alloc")]
+    trim_blank: bool,
+    #[cfg(feature = "alloc")]
+    squeeze_blank: bool,
+    #[cfg(feature = "alloc")]
+    whitespace_blank: bool,
+    #[cfg(feature = "alloc")]
+    drop_blank_whitespace: bool,
+
+    // True if the previous line was blank, when squeezing blank lines
+    #[cfg(feature = "alloc")]
+    blank_run: bool,
+
+    // Leading whitespace of the current line, held back until we know if
+    // the line is blank
+    #[cfg(feature = "alloc")]
+    pending: String,
+
+    #[cfg(feature = "alloc")]
+    shared: Option<SharedLevel>,
+
+    // True if this writer is the one currently doing the indentation for
+    // its shared level
+    #[cfg(feature = "alloc")]
+    owns_shared: bool,
+
+    // Reusable buffer for rendering format arguments
+    #[cfg(feature = "alloc")]
+    fmt_buffer: String,
+
+    // The complete indent for lines at some level, so that it can be written
+    // in one piece, and the indent it was built from
+    #[cfg(feature = "alloc")]
+    indent_cache: String,
+    #[cfg(feature = "alloc")]
+    indent_cache_key: Option<LineIndent<'i>>,
+
+    // If we're tracking ANSI escape sequences, the colors and styles in
+    // effect
+    #[cfg(feature = "alloc")]
+    ansi: Option<ansi::AnsiState>,
 }
 
 impl<'i, W: fmt::Write> IndentWriter<'i, W> {
     /// Create a new [`IndentWriter`].
     #[inline]
-    pub fn new(indent: &'i str, writer: W) -> Self {
+    pub fn new(indent: impl Into<Indent<'i>>, writer: W) -> Self {
         Self {
             writer,
-            indent,
+            indent: indent.into(),
+            level: 1,
             need_indent: true,
+            level_prefixes: None,
+            first_indent: None,
+            indent_empty_lines: false,
+            indent_written: None,
+            suffix_written: false,
+            accepted: 0,
+            skip_indented: false,
+            enabled: true,
+            stream_start: true,
+            strip_bom: false,
+            indent_matched: Some(0),
+            line: 0,
+            prev_line_blank: false,
+            column: 0,
+            indent_end: 0,
+            line_suffix: "",
+            max_level: None,
+            max_width: None,
+            tab_width: None,
+            #[cfg(feature = "alloc")]
+            truncate: None,
+            #[cfg(feature = "wrap")]
+            wrap: None,
+            #[cfg(feature = "alloc")]
+            trim_blank: false,
+            #[cfg(feature = "alloc")]
+            squeeze_blank: false,
+            #[cfg(feature = "alloc")]
+            whitespace_blank: false,
+            #[cfg(feature = "alloc")]
+            drop_blank_whitespace: false,
+            #[cfg(feature = "alloc")]
+            blank_run: false,
+            #[cfg(feature = "alloc")]
+            pending: String::new(),
+            #[cfg(feature = "alloc")]
+            shared: None,
+            #[cfg(feature = "alloc")]
+            owns_shared: false,
+            #[cfg(feature = "alloc")]
+            ansi: None,
+            #[cfg(feature = "alloc")]
+            fmt_buffer: String::new(),
+            #[cfg(feature = "alloc")]
+            indent_cache: String::new(),
+            #[cfg(feature = "alloc")]
+            indent_cache_key: None,
         }
     }
 
@@ -62,11 +418,74 @@ impl<'i, W: fmt::Write> IndentWriter<'i, W> {
     /// assert_eq!(buffer, "Line 1\n    Line 2\n    Line 3\n")
     /// ```
     #[inline]
-    pub fn new_skip_initial(indent: &'i str, writer: W) -> Self {
+    pub fn new_skip_initial(indent: impl Into<Indent<'i>>, writer: W) -> Self {
         Self {
-            writer,
-            indent,
             need_indent: false,
+            ..Self::new(indent, writer)
+        }
+    }
+
+    /// Create a new [`IndentWriter`] which indents each line with `count`
+    /// spaces. See [`Indent::Spaces`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::spaces(4, String::new());
+    /// writeln!(writer, "Line 1").unwrap();
+    /// assert_eq!(writer.get_ref(), "    Line 1\n");
+    /// ```
+    #[inline]
+    pub fn spaces(count: usize, writer: W) -> Self {
+        Self::new(Indent::Spaces(count), writer)
+    }
+
+    /// Create a new [`IndentWriter`] which indents each line with `count`
+    /// tabs. See [`Indent::Tabs`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::tabs(1, String::new());
+    /// writeln!(writer, "Line 1").unwrap();
+    /// assert_eq!(writer.get_ref(), "\tLine 1\n");
+    /// ```
+    #[inline]
+    pub fn tabs(count: usize, writer: W) -> Self {
+        Self::new(Indent::Tabs(count), writer)
+    }
+
+    /// Create a new [`IndentWriter`] which will wrap lines that would exceed
+    /// `width` columns. The indent counts toward the width, and lines are
+    /// broken at `char` boundaries, without regard for words. Newlines in the
+    /// written content reset the width as normal. Each line will contain at
+    /// least one character of content, even if the indent alone meets or
+    /// exceeds the width.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::with_max_width("> ", 6, String::new());
+    ///
+    /// write!(writer, "abcdefg\nhi").unwrap();
+    /// write!(writer, "jklmn\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "> abcd\n> efg\n> hijk\n> lmn\n");
+    /// ```
+    #[inline]
+    pub fn with_max_width(indent: impl Into<Indent<'i>>, width: usize, writer: W) -> Self {
+        Self {
+            max_width: Some(width),
+            ..Self::new(indent, writer)
         }
     }
 
@@ -83,65 +502,1484 @@ impl<'i, W: fmt::Write> IndentWriter<'i, W> {
         &self.writer
     }
 
-    /// Get the string being used as an indent for each line
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the indentation
+    /// logic; use [`set_needs_indent`][Self::set_needs_indent] afterwards if
+    /// it changed whether we're at the start of a line, or use
+    /// [`write_raw`][Self::write_raw] instead.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Get the indent being inserted before each line
     #[inline]
-    pub fn indent(&self) -> &'i str {
+    pub fn indent(&self) -> Indent<'i> {
         self.indent
     }
-}
 
-impl<'i, W: fmt::Write> fmt::Write for IndentWriter<'i, W> {
-    fn write_str(&mut self, mut s: &str) -> fmt::Result {
-        loop {
-            match self.need_indent {
-                // We don't need an indent. Scan for the end of the line
-                false => match s.as_bytes().iter().position(|&b| b == b'\n') {
-                    // No end of line in the input; write the entire string
-                    None => break self.writer.write_str(s),
+    /// Check if an indent will be inserted before the next non-empty line.
+    /// This is true at the start of the stream (unless created with
+    /// [`new_skip_initial`][Self::new_skip_initial]) and after each newline,
+    /// and false while in the middle of a line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    ///
+    /// // Some callback leaves us in the middle of a line
+    /// write!(writer, "partial").unwrap();
+    ///
+    /// // Emit a separating newline only if we're mid-line
+    /// if !writer.needs_indent() {
+    ///     writer.write_char('\n').unwrap();
+    /// }
+    ///
+    /// assert_eq!(writer.get_ref(), "  partial\n");
+    /// assert!(writer.needs_indent());
+    /// ```
+    #[inline]
+    pub fn needs_indent(&self) -> bool {
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(ref shared) = self.shared {
+                return shared.need_indent();
+            }
+        }
 
-                    // We can see the end of the line. Write up to and including
-                    // that newline, then request an indent
-                    Some(len) => {
-                        let (head, tail) = s.split_at(len + 1);
-                        self.writer.write_str(head)?;
-                        self.need_indent = true;
-                        s = tail;
-                    }
-                },
-                // We need an indent. Scan for the beginning of the next
-                // non-empty line.
-                true => match s.as_bytes().iter().position(|&b| b != b'\n') {
-                    // No non-empty lines in input, write the entire string
-                    None => break self.writer.write_str(s),
+        self.need_indent
+    }
 
-                    // We can see the next non-empty line. Write up to the
-                    // beginning of that line, then insert an indent, then
-                    // continue.
-                    Some(len) => {
-                        let (head, tail) = s.split_at(len);
-                        self.writer.write_str(head)?;
-                        self.writer.write_str(self.indent)?;
-                        self.need_indent = false;
-                        s = tail;
-                    }
-                },
+    /// Set whether an indent should be inserted before the next non-empty
+    /// line. This is useful for resynchronizing the writer's state after
+    /// content was written directly through [`get_mut`][Self::get_mut].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    ///
+    /// // Write a complete line out-of-band, bypassing the indent
+    /// writer.get_mut().push_str("raw line\n");
+    /// writer.set_needs_indent(true);
+    ///
+    /// write!(writer, "indented").unwrap();
+    ///
+    /// // Continue an unfinished line out-of-band
+    /// writer.get_mut().push_str("\nraw prefix: ");
+    /// writer.set_needs_indent(false);
+    ///
+    /// write!(writer, "value\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "raw line\n  indented\nraw prefix: value\n");
+    /// ```
+    #[inline]
+    pub fn set_needs_indent(&mut self, needs_indent: bool) {
+        self.need_indent = needs_indent;
+        self.reset_line_progress();
+
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(ref shared) = self.shared {
+                shared.set_need_indent(needs_indent);
             }
         }
     }
 
-    fn write_char(&mut self, c: char) -> fmt::Result {
-        // We need an indent, and this is the start of a non-empty line.
-        // Insert the indent.
-        if self.need_indent && c != '\n' {
-            self.writer.write_str(self.indent)?;
-            self.need_indent = false;
-        }
+    /// Check if indentation is enabled. See [`set_enabled`][Self::set_enabled].
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
 
-        // This is the end of a non-empty line. Request an indent.
-        if !self.need_indent && c == '\n' {
-            self.need_indent = true;
-        }
+    /// Enable or disable indentation. While indentation is disabled, content
+    /// is written to the inner writer unchanged, without any indents or other
+    /// processing, but the writer still tracks where lines begin, so that
+    /// when it's enabled again, it resumes indenting at the start of the next
+    /// line. If it's enabled in the middle of a line, that line isn't
+    /// indented. This is useful for a "flat" output mode that uses the same
+    /// rendering code.
+    ///
+    /// Any content held back by the writer is written, as with
+    /// [`flush`][Self::flush], before content written while indentation is
+    /// disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    /// write!(writer, "Line 1\n").unwrap();
+    ///
+    /// writer.set_enabled(false);
+    /// write!(writer, "Line 2\nLine 3, ").unwrap();
+    ///
+    /// writer.set_enabled(true);
+    /// write!(writer, "continued\nLine 4\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "    Line 1\nLine 2\nLine 3, continued\n    Line 4\n");
+    /// ```
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Write `s` to the inner writer unchanged, without any indents or other
+    /// processing, for verbatim blocks of preformatted content. Unlike
+    /// writing to [`get_mut`][Self::get_mut], this keeps track of whether
+    /// `s` ended at the start of a line, so that the next line written
+    /// through this writer is indented correctly. Any content held back by
+    /// the writer is written, as with [`flush`][Self::flush], before `s`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    /// write!(writer, "payload:\n").unwrap();
+    /// writer.write_raw("aGVsbG8=\nd29ybGQ=\n").unwrap();
+    /// write!(writer, "end\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "    payload:\naGVsbG8=\nd29ybGQ=\n    end\n");
+    /// ```
+    pub fn write_raw(&mut self, s: &str) -> fmt::Result {
+        self.flush()?;
+        self.emit(s)?;
+
+        #[cfg(feature = "alloc")]
+        self.observe_ansi(s);
+
+        self.track_line_start(s);
+        Ok(())
+    }
+
+    /// Get the number of bytes of the most recent
+    /// [`write_str`][fmt::Write::write_str] or
+    /// [`write_char`][fmt::Write::write_char] call that were handled: either
+    /// written to the inner writer, or held back to be written later. After a
+    /// successful write, this is the length of the written string.
+    ///
+    /// If a write fails, retrying with the unhandled remainder of the string
+    /// produces the same output as if the error had never happened. When word
+    /// wrapping, content that was held back and lost to the error can't be
+    /// recovered this way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fmt::{self, Write};
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// // A writer that fails the first time it's written to
+    /// struct Flaky(String, bool);
+    ///
+    /// impl Write for Flaky {
+    ///     fn write_str(&mut self, s: &str) -> fmt::Result {
+    ///         if !self.1 {
+    ///             self.1 = true;
+    ///             return Err(fmt::Error);
+    ///         }
+    ///         self.0.push_str(s);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut writer = IndentWriter::new("  ", Flaky(String::new(), false));
+    /// let mut content = "Line 1\nLine 2\n";
+    ///
+    /// while writer.write_str(content).is_err() {
+    ///     content = &content[writer.last_write_accepted()..];
+    /// }
+    ///
+    /// assert_eq!(writer.get_ref().0, "  Line 1\n  Line 2\n");
+    /// ```
+    #[inline]
+    pub fn last_write_accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Get the zero-based line number of the current output position; that
+    /// is, the number of newlines that have been written so far.
+    ///
+    /// Only content written through this `IndentWriter` is counted; content
+    /// written directly through [`get_mut`][Self::get_mut] is not tracked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    ///
+    /// write!(writer, "Line 1\n\nLine 3").unwrap();
+    /// assert_eq!(writer.line(), 2);
+    /// ```
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
 
-        self.writer.write_char(c)
+    /// Get the zero-based column of the current output position, measured in
+    /// `char`s written on the current line. The column *includes* the indent,
+    /// so it reflects the actual position in the output, rather than in the
+    /// content written to this `IndentWriter`.
+    ///
+    /// No indent is written until the first character of a non-empty line, so
+    /// at the start of a line the column is 0 even if an indent is pending.
+    /// With [`ansi_aware`][Self::ansi_aware], escape sequences aren't counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    ///
+    /// write!(writer, "Line 1\n").unwrap();
+    /// assert_eq!(writer.column(), 0);
+    ///
+    /// write!(writer, "😀 ").unwrap();
+    /// assert_eq!(writer.column(), 4);
+    /// ```
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.column
     }
+
+    /// Configure this writer to strip trailing whitespace from the indent on
+    /// lines that have no visible content. For instance, with an indent of
+    /// `"// "`, a line containing only spaces will be prefixed with `"//"`
+    /// instead of `"// "`. Only the indent is trimmed; the content of the
+    /// line is always written unchanged. Empty lines are never indented, as
+    /// usual.
+    ///
+    /// Because a line can't be known to be blank until its newline is
+    /// written, the leading whitespace of each line is held back until the
+    /// first visible character or newline. Call [`flush`][Self::flush] to
+    /// write any held-back whitespace at the end of the stream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("// ", String::new())
+    ///     .trim_prefix_on_blank_lines(true);
+    ///
+    /// write!(writer, "Line 1\n  \n\nLine 4\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "// Line 1\n//  \n\n// Line 4\n");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn trim_prefix_on_blank_lines(mut self, trim: bool) -> Self {
+        self.trim_blank = trim;
+        self
+    }
+
+    /// Configure this writer to collapse runs of consecutive blank lines
+    /// into a single blank line. Lines containing only whitespace count as
+    /// blank. This isn't applied to content that is word wrapped.
+    ///
+    /// As with
+    /// [`trim_prefix_on_blank_lines`][Self::trim_prefix_on_blank_lines], the
+    /// leading whitespace of each line is held back until the first visible
+    /// character or newline.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new()).squeeze_blank_lines(true);
+    ///
+    /// write!(writer, "Section 1\n\n\n").unwrap();
+    /// write!(writer, "  \n\nSection 2\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  Section 1\n\n  Section 2\n");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn squeeze_blank_lines(mut self, squeeze: bool) -> Self {
+        self.squeeze_blank = squeeze;
+        self
+    }
+
+    /// Configure this writer to treat lines that contain only whitespace
+    /// like empty lines, so they don't get an indent (unless
+    /// [empty lines are indented][IndentWriterBuilder::indent_empty_lines]).
+    /// The whitespace itself is still written; use
+    /// [`drop_whitespace_on_blank_lines`][Self::drop_whitespace_on_blank_lines]
+    /// to remove it. This isn't applied to content that is word wrapped.
+    ///
+    /// Because a line can't be known to be blank until its newline is
+    /// written, the leading whitespace of each line is held back until the
+    /// first visible character or newline. At most 4096 bytes of whitespace
+    /// are held back; if a line starts with more whitespace than that, it's
+    /// indented as though it had visible content. Call
+    /// [`flush`][Self::flush] to write any held-back whitespace at the end of
+    /// the stream.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("> ", String::new()).treat_whitespace_as_blank(true);
+    ///
+    /// write!(writer, "Line 1\n  \n\t").unwrap();
+    /// write!(writer, "\nLine 4\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "> Line 1\n  \n\t\n> Line 4\n");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn treat_whitespace_as_blank(mut self, treat: bool) -> Self {
+        self.whitespace_blank = treat;
+        self
+    }
+
+    /// Configure this writer to remove the whitespace from lines that contain
+    /// only whitespace, writing them as empty lines. This implies
+    /// [`treat_whitespace_as_blank`][Self::treat_whitespace_as_blank], and
+    /// the leading whitespace of each line is held back in the same way,
+    /// except that [`flush`][Self::flush] doesn't write it, since it will be
+    /// removed unless visible content follows it on the same line.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("> ", String::new()).drop_whitespace_on_blank_lines(true);
+    ///
+    /// write!(writer, "Line 1\n  \n\t\nLine 4\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "> Line 1\n\n\n> Line 4\n");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn drop_whitespace_on_blank_lines(mut self, drop: bool) -> Self {
+        self.drop_blank_whitespace = drop;
+        self.whitespace_blank |= drop;
+        self
+    }
+
+    /// True if the leading whitespace of each line should be held back until
+    /// we know whether the line is blank.
+    #[cfg(feature = "alloc")]
+    fn holds_back_whitespace(&self) -> bool {
+        self.trim_blank || self.squeeze_blank || self.whitespace_blank
+    }
+
+    /// Configure this writer to write `suffix` at the end of each non-empty
+    /// line, immediately before the newline. Lines that are empty in the
+    /// output (because nothing, not even an indent, was written to them) don't
+    /// get a suffix. The final line of the output doesn't get a suffix unless
+    /// it ends with a newline; use [`finish`][Self::finish] to end the final
+    /// line without a suffix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new()).line_suffix(" \\");
+    ///
+    /// write!(writer, "#define SWAP(a, b)\n").unwrap();
+    /// write!(writer, "\n").unwrap();
+    /// write!(writer, "do {{ int t = a; a = b; b = t; }} while (0)").unwrap();
+    ///
+    /// let output = writer.finish().unwrap();
+    /// assert_eq!(
+    ///     output,
+    ///     "    #define SWAP(a, b) \\\n\n    do { int t = a; a = b; b = t; } while (0)\n",
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn line_suffix(mut self, suffix: &'i str) -> Self {
+        self.line_suffix = suffix;
+        self
+    }
+
+    /// Finish writing, and extract the inner writer. This writes any
+    /// held-back content (see [`flush`][Self::flush]), and then, if the
+    /// writer isn't at the start of a line (according to
+    /// [`needs_indent`][Self::needs_indent]), ends the line with a newline. No
+    /// [line suffix][Self::line_suffix] is written before this newline.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    /// write!(writer, "Line 1\nLine 2").unwrap();
+    /// assert_eq!(writer.finish().unwrap(), "  Line 1\n  Line 2\n");
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    /// write!(writer, "Line 1\n").unwrap();
+    /// assert_eq!(writer.finish().unwrap(), "  Line 1\n");
+    /// ```
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        self.ensure_newline()?;
+        Ok(self.writer)
+    }
+
+    /// Ensure that the output is at the start of a line. Like
+    /// [`finish`][Self::finish], this writes any held-back content, and then
+    /// writes a newline if the writer isn't at the start of a line. This is
+    /// useful between sections of output, when it isn't known whether the
+    /// previous section ended with a newline.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    ///
+    /// write!(writer, "Section 1").unwrap();
+    /// writer.ensure_newline().unwrap();
+    /// write!(writer, "Section 2\n").unwrap();
+    /// writer.ensure_newline().unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  Section 1\n  Section 2\n");
+    /// ```
+    pub fn ensure_newline(&mut self) -> fmt::Result {
+        self.flush()?;
+
+        if !self.needs_indent() {
+            self.emit("\n")?;
+            self.set_needs_indent(true);
+        }
+
+        Ok(())
+    }
+
+    /// Ensure that the output is separated from what follows by exactly one
+    /// empty line. This is like [`ensure_newline`][Self::ensure_newline],
+    /// but then also writes a newline, unless the previous line was already
+    /// empty. Nothing is written if nothing has been written yet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    ///
+    /// writer.blank_line().unwrap();
+    /// write!(writer, "Section 1").unwrap();
+    /// writer.blank_line().unwrap();
+    /// write!(writer, "Section 2\n\n").unwrap();
+    /// writer.blank_line().unwrap();
+    /// write!(writer, "Section 3\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  Section 1\n\n  Section 2\n\n  Section 3\n");
+    /// ```
+    pub fn blank_line(&mut self) -> fmt::Result {
+        self.flush()?;
+
+        if self.line == 0 && self.column == 0 {
+            return Ok(());
+        }
+
+        self.ensure_newline()?;
+
+        if !self.prev_line_blank {
+            self.emit("\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// Write each item, followed by a newline, stopping at the first error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("- ", String::new());
+    /// writer.write_lines(&[1, 2, 3]).unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "- 1\n- 2\n- 3\n");
+    /// ```
+    pub fn write_lines<I>(&mut self, items: I) -> fmt::Result
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        items
+            .into_iter()
+            .try_for_each(|item| fmt::Write::write_fmt(self, format_args!("{}\n", item)))
+    }
+
+    /// Write each item, with `separator` between each pair of items,
+    /// stopping at the first error. No separator is written after the last
+    /// item.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    /// writer.write_separated(&["a", "b", "c"], ",\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "    a,\n    b,\n    c");
+    /// ```
+    pub fn write_separated<I>(&mut self, items: I, separator: &str) -> fmt::Result
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        let mut items = items.into_iter();
+
+        if let Some(item) = items.next() {
+            fmt::Write::write_fmt(self, format_args!("{}", item))?;
+
+            for item in items {
+                fmt::Write::write_str(self, separator)?;
+                fmt::Write::write_fmt(self, format_args!("{}", item))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write each string, stopping at the first error. This is the fallible
+    /// version of this writer's [`Extend`] implementations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    /// writer.try_extend("a b c".split(' ').flat_map(|s| [s, "\n"])).unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "    a\n    b\n    c\n");
+    /// ```
+    pub fn try_extend<I>(&mut self, iter: I) -> fmt::Result
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        iter.into_iter()
+            .try_for_each(|s| fmt::Write::write_str(self, s.as_ref()))
+    }
+
+    /// Write each character, stopping at the first error. This is the
+    /// fallible version of this writer's [`Extend<char>`] implementation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    /// writer.try_extend_chars("a,b".chars().map(|c| if c == ',' { '\n' } else { c })).unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "    a\n    b");
+    /// ```
+    pub fn try_extend_chars<I>(&mut self, iter: I) -> fmt::Result
+    where
+        I: IntoIterator<Item = char>,
+    {
+        iter.into_iter()
+            .try_for_each(|c| fmt::Write::write_char(self, c))
+    }
+
+    /// Write any content that is being held back by this writer. This is
+    /// only necessary when using
+    /// [`trim_prefix_on_blank_lines`][Self::trim_prefix_on_blank_lines],
+    /// which holds back the leading whitespace of each line (a line that is
+    /// unfinished when it is flushed is treated as blank), or word wrapping,
+    /// which holds back the current word, or
+    /// [`skip_already_indented`][Self::skip_already_indented], which holds
+    /// back the start of a line while it matches the indent, or
+    /// [truncating][Self::with_max_width_truncate] long lines, which holds
+    /// back the end of the line while the marker wouldn't fit after it.
+    pub fn flush(&mut self) -> fmt::Result {
+        self.flush_held_indent()?;
+
+        #[cfg(feature = "alloc")]
+        self.end_truncated_line(false)?;
+
+        #[cfg(feature = "wrap")]
+        {
+            if self.wrap.is_some() {
+                self.flush_wrapped()?;
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            // If we're dropping the whitespace from blank lines, keep holding
+            // it back; it'll be dropped unless visible content follows it.
+            if !self.pending.is_empty() && !self.drop_blank_whitespace {
+                if !self.whitespace_blank || self.indent_empty_lines {
+                    self.insert_indent(true)?;
+                }
+                self.emit_pending()?;
+                self.begin_content();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Configure this writer to replace each tab in the written content with
+    /// spaces, up to the next multiple of `width` columns. The
+    /// [`column`][Self::column] includes the indent, so tab stops are
+    /// aligned relative to the start of the output line. Tabs in the indent
+    /// itself are written unchanged, but will advance the column to the next
+    /// tab stop. With [`with_max_width`][Self::with_max_width], each tab
+    /// counts as the number of columns it's expanded to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new()).expand_tabs(4);
+    ///
+    /// write!(writer, "a\tb\n\tc\tdef\tg\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  a b\n    c   def g\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn expand_tabs(mut self, width: usize) -> Self {
+        assert!(width > 0, "tab width must be nonzero");
+        self.tab_width = Some(width);
+        self
+    }
+
+    /// Write user content to the inner writer, expanding tabs if configured.
+    /// Each successfully written piece is counted towards
+    /// [`last_write_accepted`][Self::last_write_accepted].
+    fn emit_content(&mut self, mut s: &str) -> fmt::Result {
+        if self.tab_width.is_some() {
+            while let Some(idx) = s.find('\t') {
+                self.emit(&s[..idx])?;
+                #[cfg(feature = "alloc")]
+                self.observe_ansi(&s[..idx]);
+                self.accepted += idx;
+                self.emit_tab()?;
+                self.accepted += 1;
+                s = &s[idx + 1..];
+            }
+        }
+
+        self.emit(s)?;
+        #[cfg(feature = "alloc")]
+        self.observe_ansi(s);
+        self.accepted += s.len();
+        Ok(())
+    }
+
+    /// Write a tab, expanding it if configured.
+    fn emit_tab(&mut self) -> fmt::Result {
+        match self.tab_width {
+            None => self.emit("\t"),
+            Some(width) => self.emit_indent(Indent::Spaces(width - self.column % width)),
+        }
+    }
+
+    /// Get the indent for the current line, and the number of times it
+    /// should be repeated.
+    fn line_indent(&self) -> LineIndent<'i> {
+        match self.first_indent {
+            Some(indent) => LineIndent::Repeated(indent, 1),
+            None => self.level_indent(),
+        }
+    }
+
+    /// Insert the indent for the current line. If `trim` is true, trailing
+    /// whitespace is removed from the indent.
+    ///
+    /// If part of the indent was already written, only the remainder is
+    /// written, so this can safely be retried after an error or panic.
+    /// Because the trimmed indent is always a prefix of the full one, this is
+    /// true even if `trim` changes between attempts.
+    fn insert_indent(&mut self, trim: bool) -> fmt::Result {
+        if self.first_indent.is_none() {
+            self.check_level(self.level()).map_err(|_| fmt::Error)?;
+        }
+
+        let indent = self.line_indent();
+        let mut skip = self.indent_written.unwrap_or(0);
+
+        // If colors are in effect, reset them for the indent, then restore
+        // them after it.
+        #[cfg(feature = "alloc")]
+        let open = self.open_sgr();
+        #[cfg(feature = "alloc")]
+        let (reset, restore) = match open.is_empty() {
+            true => ("", ""),
+            false => (ansi::RESET, open.as_str()),
+        };
+        #[cfg(not(feature = "alloc"))]
+        let (reset, restore) = ("", "");
+
+        #[cfg(feature = "alloc")]
+        let cache = match trim {
+            true => String::new(),
+            false => self.take_indent_cache(indent),
+        };
+        #[cfg(feature = "alloc")]
+        let cached: &str = &cache;
+        #[cfg(not(feature = "alloc"))]
+        let cached = "";
+
+        let mut emit_chunk = |chunk: &str| {
+            if skip >= chunk.len() {
+                skip -= chunk.len();
+                return Ok(());
+            }
+
+            let chunk = chunk.get(skip..).unwrap_or(chunk);
+            skip = 0;
+
+            // A newline inside the indent doesn't end the line being
+            // indented, so preserve the line's progress across it.
+            let (first_indent, written) = (self.first_indent, self.indent_written);
+            self.emit(chunk)?;
+            self.first_indent = first_indent;
+            self.indent_written = Some(written.unwrap_or(0) + chunk.len());
+            Ok(())
+        };
+
+        let result = (|| {
+            if trim {
+                let mut started = false;
+                indent.try_for_each_trimmed_chunk(|chunk| {
+                    if !started && !chunk.is_empty() {
+                        started = true;
+                        emit_chunk(reset)?;
+                    }
+                    emit_chunk(chunk)
+                })?;
+                if started {
+                    emit_chunk(restore)?;
+                }
+            } else if !indent.is_empty() {
+                emit_chunk(reset)?;
+                match cached.is_empty() {
+                    true => indent.try_for_each_chunk(&mut emit_chunk)?,
+                    false => emit_chunk(cached)?,
+                }
+                emit_chunk(restore)?;
+            }
+
+            Ok(())
+        })();
+
+        #[cfg(feature = "alloc")]
+        self.restore_indent_cache(cache);
+
+        result?;
+        self.indent_written.get_or_insert(0);
+        self.indent_end = self.column;
+        Ok(())
+    }
+
+    /// The indent for the current line is complete, and content is about to
+    /// be written after it.
+    fn begin_content(&mut self) {
+        self.need_indent = false;
+        self.reset_line_progress();
+
+        #[cfg(feature = "alloc")]
+        {
+            self.blank_run = false;
+        }
+    }
+
+    /// Forget any progress made writing the indent and suffix of the current
+    /// line. This is called whenever a line's indent is completed, or a line
+    /// ends.
+    fn reset_line_progress(&mut self) {
+        if self.indent_written.take().is_some() {
+            self.first_indent = None;
+        }
+
+        self.suffix_written = false;
+        self.indent_matched = Some(0);
+    }
+
+    /// Write the held-back leading whitespace of the current line. Each piece
+    /// is removed from `pending` only after it's been successfully written,
+    /// so that none of it is lost or repeated if the inner writer fails or
+    /// panics.
+    #[cfg(feature = "alloc")]
+    fn emit_pending(&mut self) -> fmt::Result {
+        struct Restore<'a, 'i, W: fmt::Write> {
+            writer: &'a mut IndentWriter<'i, W>,
+            pending: String,
+        }
+
+        impl<W: fmt::Write> Drop for Restore<'_, '_, W> {
+            fn drop(&mut self) {
+                self.writer.pending = core::mem::take(&mut self.pending);
+            }
+        }
+
+        let pending = core::mem::take(&mut self.pending);
+        let mut guard = Restore {
+            writer: self,
+            pending,
+        };
+
+        while !guard.pending.is_empty() {
+            // Write tabs separately, since they might be expanded
+            let len = match guard.pending.find('\t') {
+                Some(0) => {
+                    guard.writer.emit_tab()?;
+                    1
+                }
+                Some(idx) => {
+                    guard.writer.emit(&guard.pending[..idx])?;
+                    idx
+                }
+                None => {
+                    guard.writer.emit(&guard.pending)?;
+                    guard.pending.len()
+                }
+            };
+
+            guard.pending.drain(..len);
+        }
+
+        Ok(())
+    }
+
+    /// Write the line suffix, if any, followed by a newline.
+    fn emit_newline(&mut self) -> fmt::Result {
+        if !self.line_suffix.is_empty() && !self.suffix_written {
+            self.emit(self.line_suffix)?;
+            self.suffix_written = true;
+        }
+
+        self.emit("\n")
+    }
+
+    fn emit_indent(&mut self, indent: Indent<'_>) -> fmt::Result {
+        indent.try_for_each_chunk(|chunk| self.emit(chunk))
+    }
+
+    /// Write a string to the inner writer, updating the tracked output
+    /// position if it succeeds.
+    fn emit(&mut self, s: &str) -> fmt::Result {
+        if s.is_empty() {
+            return Ok(());
+        }
+
+        self.writer.write_str(s)?;
+        self.advance_position(s);
+        Ok(())
+    }
+
+    /// If writing `line` (which contains no newlines) would exceed the max
+    /// width, get the byte index at which it should be broken.
+    fn wrap_point(&self, line: &str) -> Option<usize> {
+        let width = self.max_width?;
+        let mut columns = self.columns();
+
+        line.char_indices().find_map(|(idx, c)| {
+            let column = columns.advance(c);
+
+            // Always write at least one character after the indent, so that
+            // we make progress even if the indent is wider than the max width
+            match column > width && (idx > 0 || self.column > self.indent_end) {
+                true => Some(idx),
+                false => None,
+            }
+        })
+    }
+
+    /// Get a measure of the columns that content will take up when it's
+    /// written at the current position.
+    fn columns(&self) -> Columns {
+        Columns {
+            column: self.column,
+            tab_width: self.tab_width,
+            #[cfg(feature = "alloc")]
+            escape: self.ansi.as_ref().map(|ansi| ansi.escape),
+            #[cfg(not(feature = "alloc"))]
+            escape: None,
+        }
+    }
+
+    fn advance_position(&mut self, s: &str) {
+        let tail = match s.rfind('\n') {
+            None => s,
+            Some(idx) => {
+                self.prev_line_blank = match s[..idx].rfind('\n') {
+                    Some(prev) => prev + 1 == idx,
+                    None => idx == 0 && self.column == 0,
+                };
+                self.line += s.as_bytes().iter().filter(|&&b| b == b'\n').count();
+                self.column = 0;
+                self.indent_end = 0;
+                self.reset_line_progress();
+
+                #[cfg(feature = "alloc")]
+                if let Some(ansi) = &mut self.ansi {
+                    ansi.escape = Default::default();
+                }
+
+                &s[idx + 1..]
+            }
+        };
+
+        let mut columns = self.columns();
+        match columns.escape {
+            None if self.tab_width.is_none() => self.column += tail.chars().count(),
+            _ => {
+                for c in tail.chars() {
+                    columns.advance(c);
+                }
+                self.column = columns.column;
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        if let (Some(ansi), Some(escape)) = (&mut self.ansi, columns.escape) {
+            ansi.escape = escape;
+        }
+    }
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Render `args` into a buffer, then write it with a single pass of
+    /// `write_indented`. This avoids scanning, and calling the inner writer
+    /// for, each formatted fragment separately.
+    #[cfg(feature = "alloc")]
+    fn write_fmt_buffered(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        if let Some(s) = args.as_str() {
+            return fmt::Write::write_str(self, s);
+        }
+
+        let mut buffer = core::mem::take(&mut self.fmt_buffer);
+        buffer.clear();
+
+        let result =
+            fmt::write(&mut buffer, args).and_then(|()| fmt::Write::write_str(self, &buffer));
+
+        self.fmt_buffer = buffer;
+        result
+    }
+
+    /// True if this writer has nothing to add to its input: the indent is
+    /// empty, and no options that need to scan the lines are enabled. In this
+    /// case each write can be forwarded to the inner writer as is.
+    fn is_passthrough(&self) -> bool {
+        #[cfg(feature = "wrap")]
+        {
+            if self.wrap.is_some() {
+                return false;
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            if self.holds_back_whitespace() || self.ansi.is_some() || self.truncate.is_some() {
+                return false;
+            }
+        }
+
+        self.indent.is_empty()
+            && self.level_prefixes.is_none()
+            && self.first_indent.is_none()
+            && self.line_suffix.is_empty()
+            && self.max_width.is_none()
+            && self.tab_width.is_none()
+            && !self.skip_indented
+            && !self.suffix_written
+    }
+
+    /// Write `s` unchanged, keeping track of whether we're at the start of a
+    /// line.
+    fn write_passthrough(&mut self, s: &str) -> fmt::Result {
+        self.emit(s)?;
+        self.accepted += s.len();
+        self.track_line_start(s);
+        Ok(())
+    }
+
+    /// Update whether we're at the start of a line, after `s` was written to
+    /// the inner writer unchanged.
+    fn track_line_start(&mut self, s: &str) {
+        match s.chars().last() {
+            Some('\n') => {
+                self.need_indent = true;
+                self.reset_line_progress();
+            }
+            Some(_) => self.need_indent = false,
+            None => {}
+        }
+    }
+
+    /// The core of `write_str`: write `s`, inserting indents as needed.
+    fn write_indented(&mut self, mut s: &str) -> fmt::Result {
+        if !self.enabled {
+            self.flush()?;
+            return self.write_passthrough(s);
+        }
+
+        #[cfg(feature = "wrap")]
+        {
+            if self.wrap.is_some() {
+                return self.write_str_wrapped(s);
+            }
+        }
+
+        if self.is_passthrough() {
+            return self.write_passthrough(s);
+        }
+
+        loop {
+            match self.need_indent {
+                // We need an indent, but we're trimming the indent on blank
+                // lines, or squeezing them. Hold back leading whitespace
+                // until we know whether this line is blank.
+                #[cfg(feature = "alloc")]
+                true if self.holds_back_whitespace() => {
+                    match s.find(|c: char| c == '\n' || !c.is_whitespace()) {
+                        // The entire input is whitespace; hold it back, unless
+                        // there's too much of it
+                        None if self.pending.len() + s.len() > MAX_PENDING => {
+                            self.insert_indent(false)?;
+                            self.emit_pending()?;
+                            self.begin_content();
+                            break self.emit_content(s);
+                        }
+                        None => {
+                            self.pending.push_str(s);
+                            self.accepted += s.len();
+                            break Ok(());
+                        }
+
+                        // We found the end of the line; it's blank. If
+                        // there's any whitespace, write the trimmed indent
+                        // before it.
+                        Some(len) if s.as_bytes()[len] == b'\n' => {
+                            let (head, tail) = s.split_at(len + 1);
+                            if self.squeeze_blank && self.blank_run {
+                                self.pending.clear();
+                                self.accepted += head.len();
+                            } else if self.pending.is_empty()
+                                && len == 0
+                                && !self.indent_empty_lines
+                            {
+                                self.emit_content(head)?;
+                            } else if self.drop_blank_whitespace {
+                                self.pending.clear();
+                                self.accepted += len;
+
+                                if self.indent_empty_lines {
+                                    self.insert_indent(self.trim_blank)?;
+                                    self.emit_newline()?;
+                                    self.accepted += 1;
+                                } else {
+                                    self.emit_content("\n")?;
+                                }
+                            } else if self.whitespace_blank && !self.indent_empty_lines {
+                                self.emit_pending()?;
+                                self.emit_content(head)?;
+                            } else {
+                                self.insert_indent(self.trim_blank)?;
+                                self.emit_pending()?;
+                                self.emit_content(&head[..len])?;
+                                self.emit_newline()?;
+                                self.accepted += 1;
+                            }
+                            self.blank_run = true;
+                            s = tail;
+                        }
+
+                        // We found visible content. Write the full indent,
+                        // then the held-back whitespace.
+                        Some(len) => {
+                            let (head, tail) = s.split_at(len);
+                            self.insert_indent(false)?;
+                            self.emit_pending()?;
+                            self.begin_content();
+                            self.emit_content(head)?;
+                            s = tail;
+                        }
+                    }
+                }
+
+                // We don't need an indent. Scan for the end of the line
+                false => {
+                    // If we're truncating long lines, write the content of
+                    // the line, then handle its end as usual.
+                    #[cfg(feature = "alloc")]
+                    {
+                        if self.truncate.is_some() {
+                            let len = s.find('\n').unwrap_or(s.len());
+                            let (head, tail) = s.split_at(len);
+                            self.write_truncated(head)?;
+                            s = tail;
+
+                            if s.is_empty() {
+                                break Ok(());
+                            }
+
+                            self.end_truncated_line(true)?;
+                        }
+                    }
+
+                    let line_end = s.as_bytes().iter().position(|&b| b == b'\n');
+
+                    // If the line is too long, write as much of it as will
+                    // fit, then break it and request an indent.
+                    if let Some(len) = self.wrap_point(&s[..line_end.unwrap_or(s.len())]) {
+                        let (head, tail) = s.split_at(len);
+                        self.emit_content(head)?;
+                        self.emit_newline()?;
+                        self.need_indent = true;
+                        s = tail;
+                        continue;
+                    }
+
+                    match line_end {
+                        // No end of line in the input; write the entire string
+                        None => break self.emit_content(s),
+
+                        // We can see the end of the line. Write up to and
+                        // including that newline, then request an indent
+                        Some(len) => {
+                            let (head, tail) = s.split_at(len + 1);
+                            if self.line_suffix.is_empty() {
+                                self.emit_content(head)?;
+                            } else {
+                                self.emit_content(&head[..len])?;
+                                self.emit_newline()?;
+                                self.accepted += 1;
+                            }
+                            self.need_indent = true;
+                            s = tail;
+                        }
+                    }
+                }
+                // We need an indent, and we're indenting empty lines, so
+                // insert it right away.
+                true if self.indent_empty_lines => {
+                    if s.is_empty() {
+                        break Ok(());
+                    }
+
+                    self.insert_indent(false)?;
+                    self.begin_content();
+                }
+
+                // We need an indent. Scan for the beginning of the next
+                // non-empty line.
+                true => match s.as_bytes().iter().position(|&b| b != b'\n') {
+                    // No non-empty lines in input, write the entire string
+                    None => break self.emit_content(s),
+
+                    // We can see the next non-empty line. Write up to the
+                    // beginning of that line, then insert an indent, then
+                    // continue.
+                    Some(len) => {
+                        let (head, tail) = s.split_at(len);
+                        self.emit_content(head)?;
+                        self.insert_indent(false)?;
+                        self.begin_content();
+                        s = tail;
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'i, W: fmt::Write> fmt::Write for IndentWriter<'i, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.accepted = 0;
+        let s = self.write_bom(s)?;
+
+        #[cfg(feature = "alloc")]
+        {
+            if self.shared.is_some() {
+                return self.write_str_shared(s);
+            }
+        }
+
+        self.write_checking_indent(s)
+    }
+
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.accepted = 0;
+
+        if self.stream_start {
+            return self.write_str(c.encode_utf8(&mut [0; 4]));
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            if self.shared.is_some() {
+                return self.write_str(c.encode_utf8(&mut [0; 4]));
+            }
+        }
+
+        if !self.enabled
+            || self.skip_indented
+            || self.max_width.is_some()
+            || (c == '\t' && self.tab_width.is_some())
+        {
+            return self.write_str(c.encode_utf8(&mut [0; 4]));
+        }
+
+        #[cfg(feature = "wrap")]
+        {
+            if self.wrap.is_some() {
+                return self.write_str(c.encode_utf8(&mut [0; 4]));
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            if self.ansi.is_some()
+                || self.truncate.is_some()
+                || (self.need_indent && self.holds_back_whitespace())
+            {
+                return self.write_str(c.encode_utf8(&mut [0; 4]));
+            }
+        }
+
+        // We need an indent, and this is the start of a non-empty line.
+        // Insert the indent.
+        if self.need_indent && (c != '\n' || self.indent_empty_lines) {
+            self.insert_indent(false)?;
+            self.begin_content();
+        }
+
+        // This is the end of a non-empty line. Write the suffix; once the
+        // newline is written, request an indent.
+        let line_end = !self.need_indent && c == '\n';
+        if line_end && !self.line_suffix.is_empty() && !self.suffix_written {
+            self.emit(self.line_suffix)?;
+            self.suffix_written = true;
+        }
+
+        self.writer.write_char(c)?;
+        self.accepted = 1;
+        self.advance_position(c.encode_utf8(&mut [0; 4]));
+        self.need_indent |= line_end;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        if self.shared.is_some() {
+            self.write_fmt_shared(args)
+        } else {
+            self.write_fmt_buffered(args)
+        }
+    }
+}
+
+/// Write each character through the writer, as with
+/// [`try_extend_chars`][IndentWriter::try_extend_chars].
+///
+/// # Panics
+///
+/// [`Extend`] can't report errors, so this panics if the inner writer returns
+/// an error. Writers like [`String`] never do; use `try_extend_chars` for
+/// writers that can fail.
+impl<W: fmt::Write> Extend<char> for IndentWriter<'_, W> {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        self.try_extend_chars(iter)
+            .expect("the inner writer returned an error")
+    }
+}
+
+/// Write each string through the writer, as with
+/// [`try_extend`][IndentWriter::try_extend].
+///
+/// # Panics
+///
+/// [`Extend`] can't report errors, so this panics if the inner writer returns
+/// an error. Writers like [`String`] never do; use `try_extend` for writers
+/// that can fail.
+impl<'a, W: fmt::Write> Extend<&'a str> for IndentWriter<'_, W> {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        self.try_extend(iter)
+            .expect("the inner writer returned an error")
+    }
+}
+
+/// Write each string through the writer, as with
+/// [`try_extend`][IndentWriter::try_extend].
+///
+/// This implementation requires the `alloc` feature.
+///
+/// # Panics
+///
+/// [`Extend`] can't report errors, so this panics if the inner writer returns
+/// an error. Writers like [`String`] never do; use `try_extend` for writers
+/// that can fail.
+#[cfg(feature = "alloc")]
+impl<W: fmt::Write> Extend<String> for IndentWriter<'_, W> {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, iter: I) {
+        self.try_extend(iter)
+            .expect("the inner writer returned an error")
+    }
+}
+
+/// Create an [`IndentWriter`] that writes to a [`Formatter`].
+/// This is useful in [`Display`] and [`Debug`][fmt::Debug] implementations
+/// for writing indented nested content.
+///
+/// # Example
+///
+/// ```
+/// use std::fmt::{self, Write};
+/// use indent_write::fmt::indent_formatter;
+///
+/// struct Block(&'static [&'static str]);
+///
+/// impl fmt::Display for Block {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str("{\n")?;
+///
+///         let mut writer = indent_formatter(f, "    ");
+///         self.0.iter().try_for_each(|line| writeln!(writer, "{}", line))?;
+///
+///         f.write_str("}")
+///     }
+/// }
+///
+/// assert_eq!(Block(&["a;", "b;"]).to_string(), "{\n    a;\n    b;\n}");
+/// ```
+#[inline]
+pub fn indent_formatter<'i, 'a, 'f>(
+    f: &'a mut fmt::Formatter<'f>,
+    indent: impl Into<Indent<'i>>,
+) -> IndentWriter<'i, &'a mut fmt::Formatter<'f>> {
+    IndentWriter::new(indent, f)
+}
+
+/// Create an [`IndentWriter`] that writes to a [`Formatter`],
+/// inserting `indent` only if the alternate flag (`{:#}`) is set. Otherwise,
+/// content is written unchanged, without scanning it for lines. This allows
+/// a type to have a compact format and an expanded, multi-line alternate
+/// format with the same code.
+///
+/// # Example
+///
+/// ```
+/// use std::fmt::{self, Write};
+/// use indent_write::fmt::indent_if_alternate;
+///
+/// struct List(&'static [i32]);
+///
+/// impl fmt::Display for List {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         let (open, sep, close) = match f.alternate() {
+///             true => ("[\n", ",\n", ",\n]"),
+///             false => ("[", ", ", "]"),
+///         };
+///
+///         f.write_str(open)?;
+///         {
+///             let mut writer = indent_if_alternate(f, "  ");
+///             writer.write_separated(self.0, sep)?;
+///         }
+///         f.write_str(close)
+///     }
+/// }
+///
+/// assert_eq!(format!("{}", List(&[1, 2, 3])), "[1, 2, 3]");
+/// assert_eq!(format!("{:#}", List(&[1, 2, 3])), "[\n  1,\n  2,\n  3,\n]");
+/// ```
+#[inline]
+pub fn indent_if_alternate<'i, 'a, 'f>(
+    f: &'a mut fmt::Formatter<'f>,
+    indent: impl Into<Indent<'i>>,
+) -> IndentWriter<'i, &'a mut fmt::Formatter<'f>> {
+    match f.alternate() {
+        true => IndentWriter::new(indent, f),
+        false => IndentWriter::new("", f),
+    }
+}
+
+/// Format `args` into a new [`String`], inserting `indent` before each
+/// non-empty line.
+///
+/// This is a shorthand for writing to an [`IndentWriter`] wrapping a
+/// `String`; see also the [`indent_format!`][crate::indent_format] macro.
+///
+/// This function requires the `alloc` feature.
+///
+/// # Panics
+///
+/// Panics if a formatting trait implementation returns an error, the same way
+/// that [`format!`][alloc::format] does.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::fmt::format_indented;
+///
+/// let output = format_indented("  ", format_args!("{}\n\n{}\n", "a", "b"));
+/// assert_eq!(output, "  a\n\n  b\n");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn format_indented<'i>(indent: impl Into<Indent<'i>>, args: fmt::Arguments<'_>) -> String {
+    let indent = indent.into();
+    let estimate = args.as_str().map_or(0, str::len) + indent.byte_len();
+
+    // Format directly into the output, rather than into the writer's
+    // intermediate buffer
+    let mut writer = IndentWriter::new(indent, String::with_capacity(estimate));
+    fmt::write(&mut writer, args).expect("a formatting trait implementation returned an error");
+    writer.into_inner()
 }
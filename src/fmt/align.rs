@@ -0,0 +1,138 @@
+use core::fmt;
+
+use alloc::string::String;
+
+use super::Indent;
+
+/// Adapter for writers to align lines on a delimiter
+///
+/// An `AlignWriter` adapts a [`fmt::Write`] object to align blocks of lines
+/// on the first occurrence of a delimiter in each line, such as the `=` in
+/// `name = value` lines. Lines are collected into a block until a blank line
+/// (one that's empty or contains only whitespace) is written, or
+/// [`flush_block`][Self::flush_block] is called. Then spaces are inserted
+/// before the delimiter in each line of the block, so that the delimiters
+/// line up, and the block is written to the inner writer. Lines that don't
+/// contain the delimiter are part of the block, and are written unchanged.
+/// The position of each delimiter is measured in `char`s.
+///
+/// Because the padding depends on every line in the block, the whole block
+/// is buffered in memory. Call `flush_block` at the end of the output to
+/// write the last block.
+///
+/// This type requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::AlignWriter;
+///
+/// let mut writer = AlignWriter::new('=', String::new());
+///
+/// write!(writer, "width = 10\nx = 1\n// comment\n\n").unwrap();
+/// write!(writer, "a = 1\nbb = 2").unwrap();
+/// writer.flush_block().unwrap();
+///
+/// assert_eq!(
+///     writer.get_ref(),
+///     "width = 10\nx     = 1\n// comment\n\na  = 1\nbb = 2",
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct AlignWriter<W> {
+    writer: W,
+    delimiter: char,
+    block: String,
+}
+
+impl<W: fmt::Write> AlignWriter<W> {
+    /// Create a new [`AlignWriter`], which aligns lines on `delimiter`.
+    #[inline]
+    pub fn new(delimiter: char, writer: W) -> Self {
+        Self {
+            writer,
+            delimiter,
+            block: String::new(),
+        }
+    }
+
+    /// Extract the writer from the `AlignWriter`, discarding the current
+    /// block. Use [`flush_block`][Self::flush_block] first to write it.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the alignment
+    /// logic, and is written before the current block.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Get the delimiter that lines are aligned on
+    #[inline]
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    /// Align and write the current block, including the current line, if
+    /// it's incomplete. Lines written after this start a new block.
+    pub fn flush_block(&mut self) -> fmt::Result {
+        let delimiter = self.delimiter;
+
+        let column = self
+            .block
+            .split_inclusive('\n')
+            .filter_map(|line| line.find(delimiter).map(|idx| line[..idx].chars().count()))
+            .max();
+
+        let writer = &mut self.writer;
+
+        match column {
+            None => writer.write_str(&self.block)?,
+            Some(column) => {
+                for line in self.block.split_inclusive('\n') {
+                    match line.find(delimiter) {
+                        None => writer.write_str(line)?,
+                        Some(idx) => {
+                            let (head, tail) = line.split_at(idx);
+                            let padding = Indent::Spaces(column - head.chars().count());
+
+                            writer.write_str(head)?;
+                            padding.try_for_each_chunk(|chunk| writer.write_str(chunk))?;
+                            writer.write_str(tail)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.block.clear();
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for AlignWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for piece in s.split_inclusive('\n') {
+            let line_start = self.block.rfind('\n').map_or(0, |idx| idx + 1);
+            self.block.push_str(piece);
+
+            if piece.ends_with('\n') && self.block[line_start..].trim().is_empty() {
+                self.flush_block()?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use core::fmt;
+
+use alloc::string::String;
+
+use super::columns::Escape;
+use super::IndentWriter;
+
+/// The SGR sequence that resets all colors and styles
+pub(super) const RESET: &str = "\x1b[0m";
+
+// Escape sequences longer than this are assumed to be malformed
+const MAX_SEQUENCE_LEN: usize = 64;
+
+/// Tracks the SGR (color and style) escape sequences in effect in the
+/// content written so far.
+#[derive(Debug, Clone, Default)]
+pub(super) struct AnsiState {
+    // The parameter in effect for each kind of style, such as "1" for bold
+    // or "38;5;208" for the foreground color, or empty if it isn't set
+    styles: [String; STYLES],
+
+    // Parameters we don't recognize, which are kept until the next reset
+    other: String,
+
+    // The single SGR sequence that restores everything in effect
+    open: String,
+
+    // An escape sequence that's been partially written
+    partial: String,
+
+    // The progress through an escape sequence in the output, which is
+    // tracked separately from `partial` because it includes the indent
+    pub(super) escape: Escape,
+}
+
+// The kinds of style that are tracked separately, each of which is replaced
+// when it's set again
+const BOLD: usize = 0;
+const DIM: usize = 1;
+const ITALIC: usize = 2;
+const UNDERLINE: usize = 3;
+const BLINK: usize = 4;
+const INVERSE: usize = 5;
+const HIDDEN: usize = 6;
+const STRIKE: usize = 7;
+const OVERLINE: usize = 8;
+const FOREGROUND: usize = 9;
+const BACKGROUND: usize = 10;
+const UNDERLINE_COLOR: usize = 11;
+const STYLES: usize = 12;
+
+/// The effect of one SGR parameter.
+enum Sgr {
+    Reset,
+    Set(usize),
+    Clear(&'static [usize]),
+    Other,
+}
+
+impl Sgr {
+    fn new(code: &str) -> Self {
+        // Parameters like "4:3" or "38:5:208" have sub-parameters
+        let (code, sub) = match code.find(':') {
+            Some(idx) => (&code[..idx], Some(&code[idx + 1..])),
+            None => (code, None),
+        };
+
+        // An empty parameter is the same as 0
+        let code: u16 = match code {
+            "" => 0,
+            code => match code.parse() {
+                Ok(code) => code,
+                Err(_) => return Sgr::Other,
+            },
+        };
+
+        match code {
+            0 => Sgr::Reset,
+            1 => Sgr::Set(BOLD),
+            2 => Sgr::Set(DIM),
+            3 => Sgr::Set(ITALIC),
+            4 if sub == Some("0") => Sgr::Clear(&[UNDERLINE]),
+            4 | 21 => Sgr::Set(UNDERLINE),
+            5 | 6 => Sgr::Set(BLINK),
+            7 => Sgr::Set(INVERSE),
+            8 => Sgr::Set(HIDDEN),
+            9 => Sgr::Set(STRIKE),
+            22 => Sgr::Clear(&[BOLD, DIM]),
+            23 => Sgr::Clear(&[ITALIC]),
+            24 => Sgr::Clear(&[UNDERLINE]),
+            25 => Sgr::Clear(&[BLINK]),
+            27 => Sgr::Clear(&[INVERSE]),
+            28 => Sgr::Clear(&[HIDDEN]),
+            29 => Sgr::Clear(&[STRIKE]),
+            30..=38 | 90..=97 => Sgr::Set(FOREGROUND),
+            39 => Sgr::Clear(&[FOREGROUND]),
+            40..=48 | 100..=107 => Sgr::Set(BACKGROUND),
+            49 => Sgr::Clear(&[BACKGROUND]),
+            53 => Sgr::Set(OVERLINE),
+            55 => Sgr::Clear(&[OVERLINE]),
+            58 => Sgr::Set(UNDERLINE_COLOR),
+            59 => Sgr::Clear(&[UNDERLINE_COLOR]),
+            _ => Sgr::Other,
+        }
+    }
+}
+
+impl AnsiState {
+    /// Scan content for SGR sequences.
+    fn observe(&mut self, mut s: &str) {
+        while !s.is_empty() {
+            if self.partial.is_empty() {
+                match s.find('\x1b') {
+                    None => return,
+                    Some(idx) => {
+                        self.partial.push('\x1b');
+                        s = &s[idx + 1..];
+                    }
+                }
+
+                continue;
+            }
+
+            let c = match s.chars().next() {
+                Some(c) => c,
+                None => return,
+            };
+            s = &s[c.len_utf8()..];
+
+            // The escape is "\x1b[", then any parameter and intermediate
+            // bytes, then a final byte, which is 'm' for SGR.
+            match c {
+                '[' if self.partial.len() == 1 => self.partial.push(c),
+                '\x20'..='\x3f' if self.partial.len() > 1 => self.partial.push(c),
+                '\x40'..='\x7e' if self.partial.len() > 1 => {
+                    self.partial.push(c);
+                    if c == 'm' {
+                        self.apply_sgr();
+                    }
+                    self.partial.clear();
+                }
+
+                // Malformed; forget it. This might be the start of another
+                // escape.
+                _ => {
+                    self.partial.clear();
+                    if c == '\x1b' {
+                        self.partial.push(c);
+                    }
+                }
+            }
+
+            if self.partial.len() > MAX_SEQUENCE_LEN {
+                self.partial.clear();
+            }
+        }
+    }
+
+    /// Apply the complete SGR sequence in `partial`.
+    fn apply_sgr(&mut self) {
+        let params = &self.partial[2..self.partial.len() - 1];
+        let mut params = params.split(';');
+
+        while let Some(param) = params.next() {
+            match Sgr::new(param) {
+                Sgr::Reset => {
+                    self.styles.iter_mut().for_each(String::clear);
+                    self.other.clear();
+                }
+                Sgr::Set(style) => {
+                    let value = &mut self.styles[style];
+                    value.clear();
+                    value.push_str(param);
+
+                    // Extended colors, like "38;5;208" or "38;2;255;128;0",
+                    // take their arguments from the following parameters
+                    if matches!(param, "38" | "48" | "58") {
+                        let count = match params.next() {
+                            Some(kind) => {
+                                value.push(';');
+                                value.push_str(kind);
+                                match kind {
+                                    "5" => 1,
+                                    "2" => 3,
+                                    _ => 0,
+                                }
+                            }
+                            None => 0,
+                        };
+
+                        params.by_ref().take(count).for_each(|arg| {
+                            value.push(';');
+                            value.push_str(arg);
+                        });
+                    }
+                }
+                Sgr::Clear(styles) => {
+                    for &style in styles {
+                        self.styles[style].clear();
+                    }
+                }
+                Sgr::Other => {
+                    if !self.other.split(';').any(|other| other == param) {
+                        if !self.other.is_empty() {
+                            self.other.push(';');
+                        }
+                        self.other.push_str(param);
+                    }
+                }
+            }
+        }
+
+        self.open.clear();
+        for param in self.styles.iter().chain(Some(&self.other)) {
+            if !param.is_empty() {
+                self.open.push_str(match self.open.is_empty() {
+                    true => "\x1b[",
+                    false => ";",
+                });
+                self.open.push_str(param);
+            }
+        }
+        if !self.open.is_empty() {
+            self.open.push('m');
+        }
+    }
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Configure this writer to be aware of ANSI color codes in the content
+    /// written to it. When a line ends while a color or style (set with an
+    /// SGR escape sequence, such as `"\x1b[31m"`) is in effect, the next
+    /// line's indent would normally be written in that color. With this
+    /// option, the indent is preceded by a reset (`"\x1b[0m"`), and followed
+    /// by a single sequence that restores the colors and styles in effect,
+    /// so the indent is uncolored and the content keeps its color. Only the
+    /// current foreground and background colors and the styles that are set
+    /// are restored, not every sequence since the last reset. The content
+    /// itself is never altered, and malformed escape sequences are passed
+    /// through unchanged.
+    ///
+    /// Escape sequences take up no columns in the output, so with this
+    /// option they aren't counted toward the [`column`][Self::column], or
+    /// the width of [`with_max_width`][Self::with_max_width],
+    /// [`with_max_width_truncate`][Self::with_max_width_truncate], or word
+    /// wrapping.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("| ", String::new()).ansi_aware(true);
+    ///
+    /// write!(writer, "\x1b[31mred\nstill red\x1b[0m\nplain\n").unwrap();
+    ///
+    /// assert_eq!(
+    ///     writer.get_ref(),
+    ///     "| \x1b[31mred\n\x1b[0m| \x1b[31mstill red\x1b[0m\n| plain\n",
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn ansi_aware(mut self, ansi_aware: bool) -> Self {
+        self.ansi = match ansi_aware {
+            true => Some(AnsiState::default()),
+            false => None,
+        };
+        self
+    }
+
+    /// Track the escape sequences in content that was just written.
+    pub(super) fn observe_ansi(&mut self, s: &str) {
+        if let Some(ansi) = &mut self.ansi {
+            ansi.observe(s);
+        }
+    }
+
+    /// Get a copy of the SGR sequences in effect, which must be written
+    /// after the indent. It's a copy, rather than taken from the state, so
+    /// that it isn't lost if the inner writer panics while the indent is
+    /// being written.
+    pub(super) fn open_sgr(&self) -> String {
+        match &self.ansi {
+            Some(ansi) => ansi.open.clone(),
+            None => String::new(),
+        }
+    }
+}
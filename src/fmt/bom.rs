@@ -0,0 +1,60 @@
+use core::fmt;
+
+use super::IndentWriter;
+
+const BOM: char = '\u{FEFF}';
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Configure this writer to remove a byte order mark (`'\u{FEFF}'`) that
+    /// appears as the very first character written to it. By default, such a
+    /// byte order mark is kept, and written before the first indent, rather
+    /// than after it, so that it stays at the start of the output. Byte order
+    /// marks anywhere else are treated as ordinary content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    /// write!(writer, "\u{FEFF}Line 1\n").unwrap();
+    /// assert_eq!(writer.get_ref(), "\u{FEFF}    Line 1\n");
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new()).strip_bom(true);
+    /// write!(writer, "\u{FEFF}Line 1\n").unwrap();
+    /// assert_eq!(writer.get_ref(), "    Line 1\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn strip_bom(mut self, strip: bool) -> Self {
+        self.strip_bom = strip;
+        self
+    }
+
+    /// If this is the start of the stream, handle a byte order mark at the
+    /// start of `s`. Returns the rest of `s`, which should be written
+    /// normally.
+    pub(super) fn write_bom<'s>(&mut self, s: &'s str) -> Result<&'s str, fmt::Error> {
+        if !self.stream_start || s.is_empty() {
+            return Ok(s);
+        }
+
+        let rest = match s.strip_prefix(BOM) {
+            None => s,
+            Some(rest) => {
+                // The byte order mark has no width, so it doesn't affect the
+                // column
+                if !self.strip_bom {
+                    self.writer.write_char(BOM)?;
+                }
+
+                self.accepted += BOM.len_utf8();
+                rest
+            }
+        };
+
+        self.stream_start = false;
+        Ok(rest)
+    }
+}
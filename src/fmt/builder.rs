@@ -0,0 +1,349 @@
+use core::fmt;
+
+use super::{Indent, IndentWriter, LevelOverflow};
+
+/// Builder for configuring an [`IndentWriter`].
+///
+/// Created with [`IndentWriter::builder`]. The builder can be reused (or
+/// cloned) to create any number of writers with the same configuration.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::IndentWriter;
+///
+/// let base = IndentWriter::builder("  ").indent_empty_lines(true);
+/// let list = base.clone().first_line_prefix("- ");
+///
+/// let mut writer = base.build(String::new());
+/// write!(writer, "a\n\nb\n").unwrap();
+/// assert_eq!(writer.get_ref(), "  a\n  \n  b\n");
+///
+/// let mut writer = list.build(String::new());
+/// write!(writer, "a\n\nb\n").unwrap();
+/// assert_eq!(writer.get_ref(), "- a\n  \n  b\n");
+/// ```
+#[derive(Debug, Clone)]
+#[must_use = "builders do nothing unless built"]
+pub struct IndentWriterBuilder<'i> {
+    indent: Indent<'i>,
+    level: usize,
+    level_prefixes: Option<(&'i [&'i str], LevelOverflow)>,
+    initial_indent: bool,
+    indent_empty_lines: bool,
+    first_line_prefix: Option<Indent<'i>>,
+    line_suffix: &'i str,
+    max_level: Option<usize>,
+    max_width: Option<usize>,
+    tab_width: Option<usize>,
+    #[cfg(feature = "alloc")]
+    truncate: Option<(usize, &'i str)>,
+    skip_indented: bool,
+    strip_bom: bool,
+    #[cfg(feature = "wrap")]
+    word_wrap: Option<usize>,
+    #[cfg(feature = "alloc")]
+    trim_blank: bool,
+    #[cfg(feature = "alloc")]
+    squeeze_blank: bool,
+    #[cfg(feature = "alloc")]
+    whitespace_blank: bool,
+    #[cfg(feature = "alloc")]
+    drop_blank_whitespace: bool,
+    #[cfg(feature = "alloc")]
+    ansi_aware: bool,
+}
+
+impl<'i> IndentWriter<'i, ()> {
+    /// Create an [`IndentWriterBuilder`], for configuring an `IndentWriter`
+    /// that uses the given indent. The writer type is chosen when the
+    /// builder is [built][IndentWriterBuilder::build].
+    #[inline]
+    pub fn builder(indent: impl Into<Indent<'i>>) -> IndentWriterBuilder<'i> {
+        IndentWriterBuilder::new(indent)
+    }
+}
+
+impl<'i> IndentWriterBuilder<'i> {
+    /// Create a new builder, for configuring an [`IndentWriter`] that uses
+    /// the given indent. By default, the built writer is configured the same
+    /// as one created with [`IndentWriter::new`].
+    #[inline]
+    pub fn new(indent: impl Into<Indent<'i>>) -> Self {
+        Self {
+            indent: indent.into(),
+            level: 1,
+            level_prefixes: None,
+            initial_indent: true,
+            indent_empty_lines: false,
+            first_line_prefix: None,
+            line_suffix: "",
+            max_level: None,
+            max_width: None,
+            tab_width: None,
+            #[cfg(feature = "alloc")]
+            truncate: None,
+            skip_indented: false,
+            strip_bom: false,
+            #[cfg(feature = "wrap")]
+            word_wrap: None,
+            #[cfg(feature = "alloc")]
+            trim_blank: false,
+            #[cfg(feature = "alloc")]
+            squeeze_blank: false,
+            #[cfg(feature = "alloc")]
+            whitespace_blank: false,
+            #[cfg(feature = "alloc")]
+            drop_blank_whitespace: false,
+            #[cfg(feature = "alloc")]
+            ansi_aware: false,
+        }
+    }
+
+    /// Set the initial indentation level. Defaults to 1; see
+    /// [`IndentWriter::set_level`].
+    #[inline]
+    pub fn level(self, level: usize) -> Self {
+        Self { level, ..self }
+    }
+
+    /// Set whether the first line should be indented. Defaults to true; see
+    /// [`IndentWriter::new_skip_initial`].
+    #[inline]
+    pub fn initial_indent(self, initial_indent: bool) -> Self {
+        Self {
+            initial_indent,
+            ..self
+        }
+    }
+
+    /// Set whether empty lines should be indented. Defaults to false, in
+    /// which case only non-empty lines are indented. Lines are only
+    /// indented once at least one character (including a newline) has been
+    /// written to them, so there's never an indent after a trailing newline.
+    #[inline]
+    pub fn indent_empty_lines(self, indent_empty_lines: bool) -> Self {
+        Self {
+            indent_empty_lines,
+            ..self
+        }
+    }
+
+    /// Set an indent to be used in place of the regular indent the first
+    /// time an indent is inserted. This is usually the first line, unless
+    /// [`initial_indent`][Self::initial_indent] is false, in which case it's
+    /// the second.
+    #[inline]
+    pub fn first_line_prefix(self, prefix: impl Into<Indent<'i>>) -> Self {
+        Self {
+            first_line_prefix: Some(prefix.into()),
+            ..self
+        }
+    }
+
+    /// Use a different prefix for each indentation level. See
+    /// [`IndentWriter::level_prefixes`].
+    #[inline]
+    pub fn level_prefixes(self, prefixes: &'i [&'i str], overflow: LevelOverflow) -> Self {
+        Self {
+            level_prefixes: Some((prefixes, overflow)),
+            ..self
+        }
+    }
+
+    /// Set a suffix to be written at the end of each non-empty line. See
+    /// [`IndentWriter::line_suffix`].
+    #[inline]
+    pub fn line_suffix(self, line_suffix: &'i str) -> Self {
+        Self {
+            line_suffix,
+            ..self
+        }
+    }
+
+    /// Set the maximum indentation level. See [`IndentWriter::max_level`].
+    #[inline]
+    pub fn max_level(self, max: usize) -> Self {
+        Self {
+            max_level: Some(max),
+            ..self
+        }
+    }
+
+    /// Hard-wrap lines that would exceed `width` columns. See
+    /// [`IndentWriter::with_max_width`].
+    #[inline]
+    pub fn max_width(self, width: usize) -> Self {
+        Self {
+            max_width: Some(width),
+            ..self
+        }
+    }
+
+    /// Truncate lines that would exceed `width` columns, writing `marker` in
+    /// place of the rest of the line. See
+    /// [`IndentWriter::with_max_width_truncate`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn max_width_truncate(self, width: usize, marker: &'i str) -> Self {
+        Self {
+            truncate: Some((width, marker)),
+            ..self
+        }
+    }
+
+    /// Wrap lines at word boundaries so that they don't exceed `width`
+    /// columns. If both this and [`max_width`][Self::max_width] are set,
+    /// word wrapping takes priority. See [`IndentWriter::with_word_wrap`].
+    ///
+    /// This method requires the `wrap` feature.
+    #[cfg(feature = "wrap")]
+    #[inline]
+    pub fn word_wrap(self, width: usize) -> Self {
+        Self {
+            word_wrap: Some(width),
+            ..self
+        }
+    }
+
+    /// Expand tabs in the written content to the next multiple of `width`
+    /// columns. See [`IndentWriter::expand_tabs`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    #[inline]
+    pub fn expand_tabs(self, width: usize) -> Self {
+        assert!(width > 0, "tab width must be nonzero");
+        Self {
+            tab_width: Some(width),
+            ..self
+        }
+    }
+
+    /// Leave lines alone if they already start with the indent. See
+    /// [`IndentWriter::skip_already_indented`].
+    #[inline]
+    pub fn skip_already_indented(self, skip: bool) -> Self {
+        Self {
+            skip_indented: skip,
+            ..self
+        }
+    }
+
+    /// Remove a byte order mark at the very start of the output. See
+    /// [`IndentWriter::strip_bom`].
+    #[inline]
+    pub fn strip_bom(self, strip: bool) -> Self {
+        Self {
+            strip_bom: strip,
+            ..self
+        }
+    }
+
+    /// Strip trailing whitespace from the indent on lines with no visible
+    /// content. See [`IndentWriter::trim_prefix_on_blank_lines`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn trim_prefix_on_blank_lines(self, trim: bool) -> Self {
+        Self {
+            trim_blank: trim,
+            ..self
+        }
+    }
+
+    /// Collapse runs of consecutive blank lines into a single blank line. See
+    /// [`IndentWriter::squeeze_blank_lines`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn squeeze_blank_lines(self, squeeze: bool) -> Self {
+        Self {
+            squeeze_blank: squeeze,
+            ..self
+        }
+    }
+
+    /// Treat lines that contain only whitespace like empty lines. See
+    /// [`IndentWriter::treat_whitespace_as_blank`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn treat_whitespace_as_blank(self, treat: bool) -> Self {
+        Self {
+            whitespace_blank: treat,
+            ..self
+        }
+    }
+
+    /// Remove the whitespace from lines that contain only whitespace. See
+    /// [`IndentWriter::drop_whitespace_on_blank_lines`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn drop_whitespace_on_blank_lines(self, drop: bool) -> Self {
+        Self {
+            drop_blank_whitespace: drop,
+            ..self
+        }
+    }
+
+    /// Reset colors before each indent, and restore them after it. See
+    /// [`IndentWriter::ansi_aware`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn ansi_aware(self, ansi_aware: bool) -> Self {
+        Self { ansi_aware, ..self }
+    }
+
+    /// Create an [`IndentWriter`] with this configuration, wrapping the
+    /// given writer.
+    pub fn build<W: fmt::Write>(&self, writer: W) -> IndentWriter<'i, W> {
+        let mut writer = IndentWriter::new(self.indent, writer);
+
+        writer.level = self.level;
+        writer.need_indent = self.initial_indent;
+        writer.indent_empty_lines = self.indent_empty_lines;
+        writer.first_indent = self.first_line_prefix;
+        writer.line_suffix = self.line_suffix;
+        writer.max_level = self.max_level;
+        writer.max_width = self.max_width;
+        writer.tab_width = self.tab_width;
+        writer.skip_indented = self.skip_indented;
+        writer.strip_bom = self.strip_bom;
+
+        if let Some((prefixes, overflow)) = self.level_prefixes {
+            writer = writer.level_prefixes(prefixes, overflow);
+        }
+
+        #[cfg(feature = "wrap")]
+        {
+            writer.wrap = self.word_wrap.map(super::wrap::WordWrap::new);
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            writer.trim_blank = self.trim_blank;
+            writer.squeeze_blank = self.squeeze_blank;
+            writer.whitespace_blank = self.whitespace_blank || self.drop_blank_whitespace;
+            writer.drop_blank_whitespace = self.drop_blank_whitespace;
+
+            if let Some((width, marker)) = self.truncate {
+                writer = writer.truncate_at(width, marker);
+            }
+            writer = writer.ansi_aware(self.ansi_aware);
+        }
+
+        writer
+    }
+}
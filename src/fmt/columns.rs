@@ -0,0 +1,75 @@
+/// The progress through an escape sequence, which takes up no space in the
+/// output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Escape {
+    None,
+    Start,
+    Csi,
+}
+
+impl Default for Escape {
+    #[inline]
+    fn default() -> Self {
+        Escape::None
+    }
+}
+
+impl Escape {
+    /// Advance past `c`, and return true if it's part of an escape sequence.
+    pub(super) fn advance(&mut self, c: char) -> bool {
+        let (next, hidden) = match (*self, c) {
+            (_, '\x1b') => (Escape::Start, true),
+            (Escape::Start, '[') => (Escape::Csi, true),
+            (Escape::Csi, '\x20'..='\x3f') => (Escape::Csi, true),
+            (Escape::Csi, '\x40'..='\x7e') => (Escape::None, true),
+            _ => (Escape::None, false),
+        };
+
+        *self = next;
+        hidden
+    }
+}
+
+/// Measures the columns taken up by content on a line, starting from some
+/// position on it.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Columns {
+    pub(super) column: usize,
+    pub(super) tab_width: Option<usize>,
+
+    // The progress through an escape sequence, if escape sequences have no
+    // width
+    pub(super) escape: Option<Escape>,
+}
+
+impl Columns {
+    /// Advance past `c`, which isn't a newline, and get the new column.
+    pub(super) fn advance(&mut self, c: char) -> usize {
+        if let Some(escape) = &mut self.escape {
+            if escape.advance(c) {
+                return self.column;
+            }
+        }
+
+        self.column = match (c, self.tab_width) {
+            ('\t', Some(width)) => (self.column / width + 1) * width,
+            _ => self.column + 1,
+        };
+        self.column
+    }
+
+    /// Get the length of the longest prefix of `line` (which contains no
+    /// newlines) that ends at or before column `width`, and advance past it.
+    #[cfg(feature = "alloc")]
+    pub(super) fn fit(&mut self, line: &str, width: usize) -> usize {
+        for (idx, c) in line.char_indices() {
+            let before = *self;
+            if self.advance(c) > width {
+                *self = before;
+                return idx;
+            }
+        }
+
+        line.len()
+    }
+}
@@ -0,0 +1,158 @@
+use core::fmt;
+
+/// Adapter for writers to remove a prefix from each line
+///
+/// A `DedentWriter` adapts a [`fmt::Write`] object to remove a prefix from
+/// the start of each line written through it. At most one occurrence of the
+/// prefix is removed; lines that don't start with the prefix are forwarded
+/// unchanged. This is the inverse of an
+/// [`IndentWriter`][super::IndentWriter], and is useful for shifting
+/// already-indented content to the left.
+///
+/// Lines may be split across several writes. When the content written so far
+/// on a line is a partial match for the prefix, it is held back until it
+/// either completes the prefix (and is removed) or diverges from it (and is
+/// written). Use [`flush`][Self::flush] to write out any held-back content
+/// at the end of the output.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::DedentWriter;
+///
+/// let mut writer = DedentWriter::new("    ", String::new());
+///
+/// write!(writer, "    Line 1\n  ").unwrap();
+/// write!(writer, "  Line 2\n").unwrap();
+///
+/// // Lines that don't start with the prefix are written unchanged
+/// write!(writer, "\n  Line 3\n").unwrap();
+///
+/// assert_eq!(writer.get_ref(), "Line 1\nLine 2\n\n  Line 3\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct DedentWriter<'p, W> {
+    writer: W,
+    prefix: &'p str,
+
+    // If we're at the start of a line, the number of bytes of the prefix
+    // matched (and held back) so far. None if the rest of the line should
+    // be forwarded unchanged.
+    matched: Option<usize>,
+}
+
+impl<'p, W: fmt::Write> DedentWriter<'p, W> {
+    /// Create a new [`DedentWriter`].
+    #[inline]
+    pub fn new(prefix: &'p str, writer: W) -> Self {
+        Self {
+            writer,
+            prefix,
+            matched: Some(0),
+        }
+    }
+
+    /// Extract the writer from the `DedentWriter`, discarding any held-back
+    /// content. Use [`flush`][Self::flush] first to write it.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the dedent
+    /// logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Get the prefix being removed from each line
+    #[inline]
+    pub fn prefix(&self) -> &'p str {
+        self.prefix
+    }
+
+    /// Write out any content held back because it was a partial match for the
+    /// prefix. The rest of the current line will be forwarded unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::DedentWriter;
+    ///
+    /// let mut writer = DedentWriter::new("    ", String::new());
+    /// write!(writer, "    Line 1\n  ").unwrap();
+    /// assert_eq!(writer.get_ref(), "Line 1\n");
+    ///
+    /// writer.flush().unwrap();
+    /// assert_eq!(writer.get_ref(), "Line 1\n  ");
+    /// ```
+    pub fn flush(&mut self) -> fmt::Result {
+        if let Some(matched) = self.matched {
+            if matched > 0 {
+                self.writer.write_str(&self.prefix[..matched])?;
+                self.matched = None;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for DedentWriter<'_, W> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        while !s.is_empty() {
+            match self.matched {
+                Some(matched) => {
+                    let rest = &self.prefix[matched..];
+
+                    // Match by chars, so that `matched` is always on a char
+                    // boundary
+                    let len = rest
+                        .char_indices()
+                        .zip(s.chars())
+                        .take_while(|&((_, a), b)| a == b)
+                        .last()
+                        .map_or(0, |((i, c), _)| i + c.len_utf8());
+
+                    if len == rest.len() {
+                        // The prefix is complete; drop it.
+                        self.matched = None;
+                        s = &s[len..];
+                    } else if len == s.len() {
+                        // Still a partial match; hold it back.
+                        self.matched = Some(matched + len);
+                        return Ok(());
+                    } else {
+                        // This line doesn't start with the prefix. Write the
+                        // held-back part; the rest of the line is forwarded
+                        // unchanged.
+                        self.flush()?;
+                        self.matched = None;
+                    }
+                }
+                None => match s.find('\n') {
+                    None => return self.writer.write_str(s),
+                    Some(len) => {
+                        let (head, tail) = s.split_at(len + 1);
+                        self.writer.write_str(head)?;
+                        self.matched = Some(0);
+                        s = tail;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
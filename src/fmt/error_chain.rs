@@ -0,0 +1,186 @@
+use core::fmt::{self, Display, Formatter};
+use std::error::Error;
+
+use super::IndentWriter;
+
+/// The default maximum number of sources written by an [`ErrorChain`]
+const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Adapter for displaying an error and its chain of sources
+///
+/// An `ErrorChain` displays an error, followed by each of its
+/// [`source`][Error::source]s on its own line, each indented one more level
+/// than the last. Multi-line error messages are indented as a whole. At most
+/// 32 sources are written, by default, to guard against chains that are
+/// very long or cyclic; if there are more, a final `"..."` line is written
+/// instead. No newline is written after the last source.
+///
+/// This type requires the `std` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::{error::Error, fmt};
+/// use indent_write::fmt::ErrorChain;
+///
+/// #[derive(Debug)]
+/// struct Failed(&'static str, Option<Box<Failed>>);
+///
+/// impl fmt::Display for Failed {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str(self.0)
+///     }
+/// }
+///
+/// impl Error for Failed {
+///     fn source(&self) -> Option<&(dyn Error + 'static)> {
+///         self.1.as_deref().map(|err| err as _)
+///     }
+/// }
+///
+/// let err = Failed("build failed", Some(Box::new(Failed(
+///     "disk full\n(device sda1)",
+///     None,
+/// ))));
+///
+/// assert_eq!(
+///     ErrorChain::new(&err, "  ").to_string(),
+///     "build failed\n  disk full\n  (device sda1)",
+/// );
+/// ```
+#[derive(Clone, Copy)]
+pub struct ErrorChain<'a> {
+    error: &'a (dyn Error + 'a),
+    prefix: &'a str,
+    max_depth: usize,
+}
+
+impl<'a> ErrorChain<'a> {
+    /// Create a new [`ErrorChain`], which indents each source with one more
+    /// copy of `prefix`.
+    #[inline]
+    pub fn new(error: &'a (dyn Error + 'a), prefix: &'a str) -> Self {
+        Self {
+            error,
+            prefix,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Set the maximum number of sources to write. The default is 32.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::{error::Error, fmt};
+    /// use indent_write::fmt::ErrorChain;
+    ///
+    /// // An error that is its own source
+    /// #[derive(Debug)]
+    /// struct Cycle;
+    ///
+    /// impl fmt::Display for Cycle {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         f.write_str("cycle")
+    ///     }
+    /// }
+    ///
+    /// impl Error for Cycle {
+    ///     fn source(&self) -> Option<&(dyn Error + 'static)> {
+    ///         Some(&Cycle)
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(
+    ///     ErrorChain::new(&Cycle, "  ").max_depth(2).to_string(),
+    ///     "cycle\n  cycle\n    cycle\n      ...",
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+}
+
+impl fmt::Debug for ErrorChain<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorChain")
+            .field("error", &self.error)
+            .field("prefix", &self.prefix)
+            .field("max_depth", &self.max_depth)
+            .finish()
+    }
+}
+
+impl Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut writer = IndentWriter::new(self.prefix, f);
+        writer.set_level(0);
+        fmt::Write::write_fmt(&mut writer, format_args!("{}", self.error))?;
+
+        let mut source = self.error.source();
+        let mut depth = 0;
+
+        while let Some(error) = source {
+            depth += 1;
+            writer.set_level(depth);
+
+            if depth > self.max_depth {
+                return fmt::Write::write_str(&mut writer, "\n...");
+            }
+
+            fmt::Write::write_fmt(&mut writer, format_args!("\n{}", error))?;
+            source = error.source();
+        }
+
+        Ok(())
+    }
+}
+
+/// Write `error`, followed by each of its sources, each indented with one
+/// more copy of `prefix` than the last. See [`ErrorChain`] for details.
+///
+/// This function requires the `std` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::{error::Error, fmt};
+/// use indent_write::fmt::write_error_chain;
+///
+/// #[derive(Debug)]
+/// struct Failed(&'static str, Option<Box<Failed>>);
+///
+/// impl fmt::Display for Failed {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str(self.0)
+///     }
+/// }
+///
+/// impl Error for Failed {
+///     fn source(&self) -> Option<&(dyn Error + 'static)> {
+///         self.1.as_deref().map(|err| err as _)
+///     }
+/// }
+///
+/// let err = Failed("couldn't load config", Some(Box::new(Failed(
+///     "couldn't read file",
+///     Some(Box::new(Failed("permission denied", None))),
+/// ))));
+///
+/// let mut output = String::new();
+/// write_error_chain(&mut output, &err, "  ").unwrap();
+///
+/// assert_eq!(
+///     output,
+///     "couldn't load config\n  couldn't read file\n    permission denied",
+/// );
+/// ```
+pub fn write_error_chain(
+    writer: &mut impl fmt::Write,
+    error: &dyn Error,
+    prefix: &str,
+) -> fmt::Result {
+    write!(writer, "{}", ErrorChain::new(error, prefix))
+}
@@ -0,0 +1,135 @@
+use core::fmt;
+
+/// Adapter for writers to number lines in a gutter, diagnostic style
+///
+/// A `GutterWriter` adapts a [`fmt::Write`] object to prefix each line
+/// written through it with its line number, right-aligned in a gutter of a
+/// fixed width and followed by a separator, like the source snippets in
+/// compiler diagnostics: `" 12 | code here"`. Empty lines get an empty
+/// gutter (`"    |"`, with no trailing space), so the separators stay
+/// continuous.
+///
+/// If the gutter is too narrow for a line number, the number is written in
+/// full, which misaligns that line; use [`width_for`][Self::width_for] to
+/// compute a width that fits every line number.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::GutterWriter;
+///
+/// let width = GutterWriter::width_for(10);
+/// let mut writer = GutterWriter::new(9, width, String::new());
+///
+/// write!(writer, "fn main() {{\n\n}}\n").unwrap();
+///
+/// assert_eq!(writer.get_ref(), " 9 | fn main() {\n   |\n11 | }\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct GutterWriter<W> {
+    writer: W,
+    line: usize,
+    width: usize,
+    need_gutter: bool,
+}
+
+impl GutterWriter<()> {
+    /// Get the gutter width needed to fit line numbers up to and including
+    /// `last_line`; that is, the number of decimal digits in `last_line`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::fmt::GutterWriter;
+    ///
+    /// assert_eq!(GutterWriter::width_for(9), 1);
+    /// assert_eq!(GutterWriter::width_for(10), 2);
+    /// assert_eq!(GutterWriter::width_for(999), 3);
+    /// ```
+    pub fn width_for(last_line: usize) -> usize {
+        let mut width = 1;
+        let mut line = last_line;
+
+        while line >= 10 {
+            line /= 10;
+            width += 1;
+        }
+
+        width
+    }
+}
+
+impl<W: fmt::Write> GutterWriter<W> {
+    /// Create a new [`GutterWriter`]. The first line will be numbered
+    /// `start_line`, and line numbers are right-aligned to `gutter_width`
+    /// columns.
+    #[inline]
+    pub fn new(start_line: usize, gutter_width: usize, writer: W) -> Self {
+        Self {
+            writer,
+            line: start_line,
+            width: gutter_width,
+            need_gutter: true,
+        }
+    }
+
+    /// Extract the writer from the `GutterWriter`.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the gutter
+    /// logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Get the number of the current line; that is, the line number that is
+    /// (or will be) written in the gutter of the current line.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get the width of the gutter.
+    #[inline]
+    pub fn gutter_width(&self) -> usize {
+        self.width
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for GutterWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.need_gutter {
+                if line == "\n" {
+                    write!(self.writer, "{:>width$} |", "", width = self.width)?;
+                } else {
+                    write!(self.writer, "{:>width$} | ", self.line, width = self.width)?;
+                }
+
+                self.need_gutter = false;
+            }
+
+            self.writer.write_str(line)?;
+
+            if line.ends_with('\n') {
+                self.need_gutter = true;
+                self.line += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
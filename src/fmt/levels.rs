@@ -0,0 +1,238 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+use super::{Indent, IndentWriter};
+#[cfg(feature = "alloc")]
+use super::{SPACES, TABS};
+
+/// How [`IndentWriter::level_prefixes`] chooses the prefix for levels beyond
+/// the end of the slice of prefixes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LevelOverflow {
+    /// Use the last prefix for every deeper level
+    #[default]
+    RepeatLast,
+
+    /// Start again from the first prefix
+    Cycle,
+}
+
+/// A set of prefixes, one per indentation level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct LevelPrefixes<'i> {
+    prefixes: &'i [&'i str],
+    overflow: LevelOverflow,
+}
+
+impl<'i> LevelPrefixes<'i> {
+    /// The prefix for the (zero-based) `level`.
+    fn get(self, level: usize) -> &'i str {
+        let len = self.prefixes.len();
+
+        match (self.overflow, len) {
+            (_, 0) => "",
+            (_, _) if level < len => self.prefixes[level],
+            (LevelOverflow::RepeatLast, _) => self.prefixes[len - 1],
+            (LevelOverflow::Cycle, _) => self.prefixes[level % len],
+        }
+    }
+}
+
+/// The complete indent for a line: either an [`Indent`] repeated some number
+/// of times, or the concatenated prefixes of each level up to some level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LineIndent<'i> {
+    Repeated(Indent<'i>, usize),
+    Levels(LevelPrefixes<'i>, usize),
+}
+
+impl<'i> LineIndent<'i> {
+    /// The prefix for each level, if this consists of level prefixes.
+    fn level_prefixes(self) -> impl Iterator<Item = &'i str> {
+        let (prefixes, count) = match self {
+            LineIndent::Levels(prefixes, count) => (Some(prefixes), count),
+            LineIndent::Repeated(..) => (None, 0),
+        };
+
+        prefixes
+            .into_iter()
+            .flat_map(move |prefixes| (0..count).map(move |level| prefixes.get(level)))
+    }
+
+    /// Call `func` with successive chunks of this indent, stopping at the
+    /// first error.
+    pub(super) fn try_for_each_chunk(self, func: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+        match self {
+            LineIndent::Repeated(indent, count) => indent.try_for_each_repeated_chunk(count, func),
+            LineIndent::Levels(..) => self.level_prefixes().try_for_each(func),
+        }
+    }
+
+    /// Call `func` with successive chunks of this indent, with trailing
+    /// whitespace removed, stopping at the first error.
+    pub(super) fn try_for_each_trimmed_chunk(
+        self,
+        mut func: impl FnMut(&str) -> fmt::Result,
+    ) -> fmt::Result {
+        match self {
+            LineIndent::Repeated(indent, count) => {
+                let trimmed = indent.trim_end();
+                if !trimmed.is_empty() && count > 0 {
+                    indent.try_for_each_repeated_chunk(count - 1, &mut func)?;
+                    trimmed.try_for_each_chunk(func)?;
+                }
+
+                Ok(())
+            }
+            LineIndent::Levels(..) => {
+                // Only the trailing whitespace of the last prefix that isn't
+                // entirely whitespace is removed; every prefix after it is
+                // dropped.
+                let len = self
+                    .level_prefixes()
+                    .enumerate()
+                    .filter(|(_, prefix)| !prefix.trim_end().is_empty())
+                    .last()
+                    .map_or(0, |(level, _)| level + 1);
+
+                self.level_prefixes()
+                    .take(len)
+                    .enumerate()
+                    .try_for_each(|(level, prefix)| match level + 1 == len {
+                        true => func(prefix.trim_end()),
+                        false => func(prefix),
+                    })
+            }
+        }
+    }
+
+    /// The bytes of this indent.
+    pub(super) fn bytes(self) -> impl Iterator<Item = u8> + 'i {
+        let (indent, count) = match self {
+            LineIndent::Repeated(indent, count) => (indent, count),
+            LineIndent::Levels(..) => (Indent::Prefix(""), 0),
+        };
+
+        indent
+            .repeated_bytes(count)
+            .chain(self.level_prefixes().flat_map(str::bytes))
+    }
+
+    /// The number of bytes in this indent.
+    pub(super) fn byte_len(self) -> usize {
+        match self {
+            LineIndent::Repeated(indent, count) => indent.byte_len() * count,
+            LineIndent::Levels(..) => self.level_prefixes().map(str::len).sum(),
+        }
+    }
+
+    /// Check if this indent is empty.
+    pub(super) fn is_empty(self) -> bool {
+        self.byte_len() == 0
+    }
+
+    /// Check if this indent is written in several chunks, which would be
+    /// faster to write as a single string.
+    #[cfg(feature = "alloc")]
+    fn is_fragmented(self) -> bool {
+        match self {
+            LineIndent::Repeated(Indent::Prefix(prefix), count) => count > 1 && !prefix.is_empty(),
+            LineIndent::Repeated(Indent::Spaces(n), count) => n * count > SPACES.len(),
+            LineIndent::Repeated(Indent::Tabs(n), count) => n * count > TABS.len(),
+            LineIndent::Levels(_, count) => count > 1,
+        }
+    }
+
+    /// The display width of this indent, using `width` to measure each
+    /// piece.
+    #[cfg(feature = "wrap")]
+    pub(super) fn width(self, width: impl Fn(Indent<'_>) -> usize) -> usize {
+        match self {
+            LineIndent::Repeated(indent, count) => width(indent) * count,
+            LineIndent::Levels(..) => self
+                .level_prefixes()
+                .map(|prefix| width(Indent::Prefix(prefix)))
+                .sum(),
+        }
+    }
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Configure this writer to use a different prefix for each indentation
+    /// level, instead of repeating its indent. At level `n`, each line is
+    /// indented with `prefixes[0]`, then `prefixes[1]`, and so on up to
+    /// `prefixes[n - 1]`, the same as nesting an `IndentWriter` for each of
+    /// the prefixes. For levels beyond the end of `prefixes`, `overflow`
+    /// chooses which prefix to use. If `prefixes` is empty, no indent is
+    /// inserted.
+    ///
+    /// A [`first_line_prefix`][super::IndentWriterBuilder::first_line_prefix] is still used
+    /// instead for the first line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::{IndentWriter, LevelOverflow};
+    ///
+    /// let mut writer = IndentWriter::new("", String::new())
+    ///     .level_prefixes(&["  ", "· "], LevelOverflow::Cycle);
+    ///
+    /// for level in 1..=4 {
+    ///     writer.set_level(level);
+    ///     writeln!(writer, "Level {}", level).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     writer.get_ref(),
+    ///     "  Level 1\n  · Level 2\n  ·   Level 3\n  ·   · Level 4\n",
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn level_prefixes(mut self, prefixes: &'i [&'i str], overflow: LevelOverflow) -> Self {
+        self.level_prefixes = Some(LevelPrefixes { prefixes, overflow });
+        self
+    }
+
+    /// Take the cached copy of `indent`, building it if the cache is for a
+    /// different indent. Returns an empty string if `indent` isn't worth
+    /// caching. Restore it with
+    /// [`restore_indent_cache`][Self::restore_indent_cache].
+    #[cfg(feature = "alloc")]
+    pub(super) fn take_indent_cache(&mut self, indent: LineIndent<'i>) -> String {
+        if !indent.is_fragmented() {
+            return String::new();
+        }
+
+        // The cache is empty if the inner writer panicked while it was taken
+        if self.indent_cache_key != Some(indent) || self.indent_cache.is_empty() {
+            self.indent_cache.clear();
+            self.indent_cache.reserve(indent.byte_len());
+            let _ = indent.try_for_each_chunk(|chunk| {
+                self.indent_cache.push_str(chunk);
+                Ok(())
+            });
+            self.indent_cache_key = Some(indent);
+        }
+
+        core::mem::take(&mut self.indent_cache)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(super) fn restore_indent_cache(&mut self, cache: String) {
+        if !cache.is_empty() {
+            self.indent_cache = cache;
+        }
+    }
+
+    /// The indent for lines at the current level.
+    pub(super) fn level_indent(&self) -> LineIndent<'i> {
+        match self.level_prefixes {
+            Some(prefixes) => LineIndent::Levels(prefixes, self.level()),
+            None => LineIndent::Repeated(self.indent, self.level()),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use core::fmt;
+
+use alloc::string::String;
+
+/// Adapter for writers to drop lines that don't match a predicate
+///
+/// A `LineFilterWriter` adapts a [`fmt::Write`] object to forward only some
+/// of the lines written through it. The predicate is given each complete
+/// line, without its newline; if it returns true, the line is written,
+/// followed by its newline. Otherwise, the whole line, including its
+/// newline, is dropped.
+///
+/// When combining a `LineFilterWriter` with an
+/// [`IndentWriter`][super::IndentWriter], put the filter on the outside, so
+/// that lines are filtered before they're indented: each dropped line then
+/// disappears along with its indent. With the filter on the inside, the
+/// predicate sees lines that are already indented.
+///
+/// Because the predicate needs the whole line, each line is buffered in
+/// memory until its newline is written. To bound the memory used for very
+/// long lines, use [`max_line_len`][Self::max_line_len]. Any final line that
+/// doesn't end with a newline is filtered by [`finish`][Self::finish], or
+/// when the writer is dropped, in which case any error is ignored.
+///
+/// This type requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::{IndentWriter, LineFilterWriter};
+///
+/// let mut writer = LineFilterWriter::new(
+///     |line: &str| !line.trim_start().starts_with('#'),
+///     IndentWriter::new("    ", String::new()),
+/// );
+///
+/// write!(writer, "fn main() {{\n  # debug\n").unwrap();
+/// write!(writer, "}}\n# end").unwrap();
+///
+/// let writer = writer.finish().unwrap();
+/// assert_eq!(writer.get_ref(), "    fn main() {\n    }\n");
+/// ```
+pub struct LineFilterWriter<F, W>
+where
+    F: FnMut(&str) -> bool,
+    W: fmt::Write,
+{
+    // Only None after `finish`
+    writer: Option<W>,
+    keep: F,
+
+    line: String,
+    max_line_len: usize,
+
+    // If the current line got too long, and was filtered early, whether it
+    // was kept. The rest of it is forwarded or dropped without buffering.
+    overflowed: Option<bool>,
+}
+
+impl<F, W> LineFilterWriter<F, W>
+where
+    F: FnMut(&str) -> bool,
+    W: fmt::Write,
+{
+    /// Create a new [`LineFilterWriter`], which calls `keep` with each line
+    /// to decide whether to write it.
+    #[inline]
+    pub fn new(keep: F, writer: W) -> Self {
+        Self {
+            writer: Some(writer),
+            keep,
+            line: String::new(),
+            max_line_len: usize::MAX,
+            overflowed: None,
+        }
+    }
+
+    /// Set the maximum number of bytes of a line that will be buffered. If a
+    /// line gets longer than this, the buffered part of it is passed to the
+    /// predicate, and the rest of the line is forwarded or dropped along
+    /// with it, without buffering. By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::LineFilterWriter;
+    ///
+    /// let mut writer = LineFilterWriter::new(|line: &str| line != "abcd", String::new())
+    ///     .max_line_len(4);
+    ///
+    /// write!(writer, "abc\nabcdefg\nabcd\n").unwrap();
+    ///
+    /// assert_eq!(writer.finish().unwrap(), "abc\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_line_len(mut self, max_line_len: usize) -> Self {
+        self.max_line_len = max_line_len;
+        self
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.writer
+            .as_ref()
+            .expect("writer is only taken by finish")
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the line
+    /// filtering logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer
+            .as_mut()
+            .expect("writer is only taken by finish")
+    }
+
+    /// Finish writing, and extract the inner writer. If there is a buffered
+    /// line that doesn't end with a newline, it is passed to the predicate,
+    /// and written, without adding a newline, if it's kept.
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        self.write_line()?;
+        Ok(self.writer.take().expect("writer is only taken by finish"))
+    }
+
+    /// Filter and write the buffered line, if there is one.
+    fn write_line(&mut self) -> fmt::Result {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        self.write_filtered_line().map(drop)
+    }
+
+    /// Filter and write the buffered line, even if it's empty. Returns
+    /// whether it was kept.
+    fn write_filtered_line(&mut self) -> Result<bool, fmt::Error> {
+        let keep = (self.keep)(&self.line);
+
+        if keep {
+            let writer = self
+                .writer
+                .as_mut()
+                .expect("writer is only taken by finish");
+            writer.write_str(&self.line)?;
+        }
+
+        self.line.clear();
+        Ok(keep)
+    }
+}
+
+impl<F, W> fmt::Write for LineFilterWriter<F, W>
+where
+    F: FnMut(&str) -> bool,
+    W: fmt::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for piece in s.split_inclusive('\n') {
+            let (content, newline) = match piece.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (piece, ""),
+            };
+
+            match self.overflowed {
+                Some(true) => self.get_mut().write_str(piece)?,
+                Some(false) => {}
+                None => {
+                    let room = self.max_line_len - self.line.len();
+
+                    if content.len() > room {
+                        let mut split = room;
+                        while !content.is_char_boundary(split) {
+                            split -= 1;
+                        }
+
+                        let (head, tail) = content.split_at(split);
+                        self.line.push_str(head);
+                        let keep = self.write_filtered_line()?;
+                        self.overflowed = Some(keep);
+
+                        if keep {
+                            self.get_mut().write_str(tail)?;
+                            self.get_mut().write_str(newline)?;
+                        }
+                    } else {
+                        self.line.push_str(content);
+
+                        if newline.is_empty() {
+                            continue;
+                        }
+
+                        if self.write_filtered_line()? {
+                            self.get_mut().write_str(newline)?;
+                        }
+                    }
+                }
+            }
+
+            if !newline.is_empty() {
+                self.overflowed = None;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<F, W> fmt::Debug for LineFilterWriter<F, W>
+where
+    F: FnMut(&str) -> bool,
+    W: fmt::Write + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineFilterWriter")
+            .field("writer", &self.writer)
+            .field("line", &self.line)
+            .field("max_line_len", &self.max_line_len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, W> Drop for LineFilterWriter<F, W>
+where
+    F: FnMut(&str) -> bool,
+    W: fmt::Write,
+{
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.write_line();
+        }
+    }
+}
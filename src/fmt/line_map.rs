@@ -0,0 +1,243 @@
+use core::fmt;
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Adapter for writers to transform each line
+///
+/// A `LineMapWriter` adapts a [`fmt::Write`] object to transform each line
+/// written through it with a callback. The callback is given the complete
+/// line, without its line terminator (`"\n"` or `"\r\n"`), and the line it
+/// returns is written, followed by the original terminator. This is useful
+/// for things like redacting secrets, or stripping trailing whitespace. It
+/// can be combined with an [`IndentWriter`][super::IndentWriter] on either
+/// side, to transform lines before or after they're indented.
+///
+/// Because the callback needs the whole line, each line is buffered in
+/// memory until its newline is written. To bound the memory used for very
+/// long lines, use [`max_line_len`][Self::max_line_len]. Any final line that
+/// doesn't end with a newline is written by [`finish`][Self::finish], or
+/// when the writer is dropped, in which case any error is ignored.
+///
+/// This type requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use std::borrow::Cow;
+/// use indent_write::fmt::LineMapWriter;
+///
+/// let mut writer = LineMapWriter::new(
+///     |line: &str| match line.strip_prefix("password: ") {
+///         Some(_) => Cow::Borrowed("password: ****"),
+///         None => Cow::Borrowed(line),
+///     },
+///     String::new(),
+/// );
+///
+/// write!(writer, "user: admin\r\npass").unwrap();
+/// write!(writer, "word: hunter2\r\ndone").unwrap();
+///
+/// assert_eq!(
+///     writer.finish().unwrap(),
+///     "user: admin\r\npassword: ****\r\ndone",
+/// );
+/// ```
+pub struct LineMapWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    // Only None after `finish`
+    writer: Option<W>,
+    map: F,
+
+    line: String,
+    max_line_len: usize,
+
+    // True if the current line got too long, and was written early. The
+    // rest of it is forwarded unchanged.
+    overflowed: bool,
+}
+
+impl<F, W> LineMapWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    /// Create a new [`LineMapWriter`], which calls `map` with each line to
+    /// get the line to write in its place.
+    #[inline]
+    pub fn new(map: F, writer: W) -> Self {
+        Self {
+            writer: Some(writer),
+            map,
+            line: String::new(),
+            max_line_len: usize::MAX,
+            overflowed: false,
+        }
+    }
+
+    /// Set the maximum number of bytes of a line that will be buffered. If a
+    /// line gets longer than this, the buffered part of it is passed to the
+    /// callback and written, and the rest of the line is written unchanged,
+    /// without buffering. By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use std::borrow::Cow;
+    /// use indent_write::fmt::LineMapWriter;
+    ///
+    /// let mut writer = LineMapWriter::new(
+    ///     |line: &str| Cow::Owned(line.to_uppercase()),
+    ///     String::new(),
+    /// )
+    /// .max_line_len(4);
+    ///
+    /// write!(writer, "abc\nabcdefg\n").unwrap();
+    ///
+    /// assert_eq!(writer.finish().unwrap(), "ABC\nABCDefg\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_line_len(mut self, max_line_len: usize) -> Self {
+        self.max_line_len = max_line_len;
+        self
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.writer
+            .as_ref()
+            .expect("writer is only taken by finish")
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the line
+    /// mapping logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer
+            .as_mut()
+            .expect("writer is only taken by finish")
+    }
+
+    /// Finish writing, and extract the inner writer. If there is a buffered
+    /// line that doesn't end with a newline, it is passed to the callback
+    /// and written, without adding a newline.
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        self.write_line()?;
+        Ok(self.writer.take().expect("writer is only taken by finish"))
+    }
+
+    /// Write the buffered line through the callback, if there is one.
+    fn write_line(&mut self) -> fmt::Result {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        self.write_mapped_line(self.line.len())
+    }
+
+    /// Write the first `len` bytes of the buffered line through the
+    /// callback, followed by the rest of it unchanged, even if it's empty.
+    fn write_mapped_line(&mut self, len: usize) -> fmt::Result {
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("writer is only taken by finish");
+        let (content, terminator) = self.line.split_at(len);
+
+        writer.write_str(&(self.map)(content))?;
+        writer.write_str(terminator)?;
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<F, W> fmt::Write for LineMapWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for piece in s.split_inclusive('\n') {
+            let (content, newline) = match piece.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (piece, ""),
+            };
+
+            if self.overflowed {
+                self.get_mut().write_str(piece)?;
+            } else {
+                let room = self.max_line_len - self.line.len();
+
+                if content.len() > room {
+                    let mut split = room;
+                    while !content.is_char_boundary(split) {
+                        split -= 1;
+                    }
+
+                    let (head, tail) = content.split_at(split);
+                    self.line.push_str(head);
+                    self.write_mapped_line(self.line.len())?;
+                    self.overflowed = true;
+                    self.get_mut().write_str(tail)?;
+                    self.get_mut().write_str(newline)?;
+                } else {
+                    self.line.push_str(content);
+
+                    if newline.is_empty() {
+                        continue;
+                    }
+
+                    // A "\r\n" terminator might have been split across
+                    // writes, so check the whole line for it
+                    self.line.push('\n');
+                    let len = match self.line.ends_with("\r\n") {
+                        true => self.line.len() - 2,
+                        false => self.line.len() - 1,
+                    };
+                    self.write_mapped_line(len)?;
+                }
+            }
+
+            if !newline.is_empty() {
+                self.overflowed = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<F, W> fmt::Debug for LineMapWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineMapWriter")
+            .field("writer", &self.writer)
+            .field("line", &self.line)
+            .field("max_line_len", &self.max_line_len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, W> Drop for LineMapWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.write_line();
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use core::fmt;
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Adapter for writers to prefix each line with a prefix chosen from the
+/// line's content
+///
+/// A `LinePrefixWriter` adapts a [`fmt::Write`] object to prefix each line
+/// written through it, like an [`IndentWriter`][super::IndentWriter], but
+/// the prefix for each line is computed by a callback, which is given the
+/// complete line (without its newline). This is useful for output like
+/// diffs, where the marker for a line depends on what the line contains.
+/// The callback is called for every line, including empty ones.
+///
+/// Because the prefix can't be chosen until the whole line is known, each
+/// line is buffered in memory until its newline is written. To bound the
+/// memory used for very long lines, use
+/// [`max_line_len`][Self::max_line_len]. Any final line that doesn't end
+/// with a newline is written by [`finish`][Self::finish], or when the
+/// writer is dropped, in which case any error is ignored.
+///
+/// This type requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use std::borrow::Cow;
+/// use indent_write::fmt::LinePrefixWriter;
+///
+/// let mut writer = LinePrefixWriter::new(
+///     |line: &str| match line.starts_with('+') {
+///         true => Cow::Borrowed("> "),
+///         false => Cow::Borrowed("  "),
+///     },
+///     String::new(),
+/// );
+///
+/// write!(writer, "+ added\n").unwrap();
+/// write!(writer, "unchanged\n+ ").unwrap();
+/// write!(writer, "also added").unwrap();
+///
+/// assert_eq!(writer.finish().unwrap(), "> + added\n  unchanged\n> + also added");
+/// ```
+pub struct LinePrefixWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    // Only None after `finish`
+    writer: Option<W>,
+    prefix: F,
+
+    line: String,
+    max_line_len: usize,
+
+    // True if the current line got too long, and was written early. The
+    // rest of it is forwarded unchanged.
+    overflowed: bool,
+}
+
+impl<F, W> LinePrefixWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    /// Create a new [`LinePrefixWriter`], which calls `prefix` with each line
+    /// to get the prefix for that line.
+    #[inline]
+    pub fn new(prefix: F, writer: W) -> Self {
+        Self {
+            writer: Some(writer),
+            prefix,
+            line: String::new(),
+            max_line_len: usize::MAX,
+            overflowed: false,
+        }
+    }
+
+    /// Set the maximum number of bytes of a line that will be buffered. If a
+    /// line gets longer than this, the buffered part of it is passed to the
+    /// callback and written, and the rest of the line is written unchanged,
+    /// without buffering. By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use std::borrow::Cow;
+    /// use indent_write::fmt::LinePrefixWriter;
+    ///
+    /// let mut writer = LinePrefixWriter::new(
+    ///     |line: &str| Cow::Owned(format!("{}| ", line.len())),
+    ///     String::new(),
+    /// )
+    /// .max_line_len(4);
+    ///
+    /// write!(writer, "abc\nabcdefg\n").unwrap();
+    ///
+    /// assert_eq!(writer.finish().unwrap(), "3| abc\n4| abcdefg\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_line_len(mut self, max_line_len: usize) -> Self {
+        self.max_line_len = max_line_len;
+        self
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.writer
+            .as_ref()
+            .expect("writer is only taken by finish")
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the line
+    /// prefixing logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer
+            .as_mut()
+            .expect("writer is only taken by finish")
+    }
+
+    /// Finish writing, and extract the inner writer. If there is a buffered
+    /// line that doesn't end with a newline, it is written with its prefix,
+    /// without adding a newline.
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        self.write_line()?;
+        Ok(self.writer.take().expect("writer is only taken by finish"))
+    }
+
+    /// Write the buffered line with its prefix, if there is one.
+    fn write_line(&mut self) -> fmt::Result {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        self.write_prefixed_line()
+    }
+
+    /// Write the buffered line with its prefix, even if it's empty.
+    fn write_prefixed_line(&mut self) -> fmt::Result {
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("writer is only taken by finish");
+        let prefix = (self.prefix)(&self.line);
+
+        writer.write_str(&prefix)?;
+        writer.write_str(&self.line)?;
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<F, W> fmt::Write for LinePrefixWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for piece in s.split_inclusive('\n') {
+            let (content, newline) = match piece.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (piece, ""),
+            };
+
+            if self.overflowed {
+                self.get_mut().write_str(piece)?;
+            } else {
+                let room = self.max_line_len - self.line.len();
+
+                if content.len() > room {
+                    let mut split = room;
+                    while !content.is_char_boundary(split) {
+                        split -= 1;
+                    }
+
+                    let (head, tail) = content.split_at(split);
+                    self.line.push_str(head);
+                    self.write_prefixed_line()?;
+                    self.overflowed = true;
+                    self.get_mut().write_str(tail)?;
+                    self.get_mut().write_str(newline)?;
+                } else {
+                    self.line.push_str(content);
+
+                    if newline.is_empty() {
+                        continue;
+                    }
+
+                    self.write_prefixed_line()?;
+                    self.get_mut().write_str(newline)?;
+                }
+            }
+
+            if !newline.is_empty() {
+                self.overflowed = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<F, W> fmt::Debug for LinePrefixWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinePrefixWriter")
+            .field("writer", &self.writer)
+            .field("line", &self.line)
+            .field("max_line_len", &self.max_line_len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, W> Drop for LinePrefixWriter<F, W>
+where
+    F: FnMut(&str) -> Cow<'_, str>,
+    W: fmt::Write,
+{
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.write_line();
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use core::fmt;
+
+const SPACES: &str = "                ";
+
+/// Adapter for writers to format list items
+///
+/// A `ListItemWriter` adapts a [`fmt::Write`] object to prefix the first line
+/// of each list item with a marker, such as `"- "`, and each continuation
+/// line of that item with spaces, one for each character of the marker, so
+/// that multi-line items stay aligned inside the marker. Call
+/// [`next_item`][Self::next_item] to start the next item. As with
+/// [`IndentWriter`][super::IndentWriter], empty lines are never prefixed,
+/// and the marker is written at the start of the first non-empty line of the
+/// item.
+///
+/// For nested lists, write through a `ListItemWriter` that wraps an
+/// `IndentWriter`, or another `ListItemWriter`.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::ListItemWriter;
+///
+/// let mut writer = ListItemWriter::new("- ", String::new());
+///
+/// writeln!(writer, "Item 1\nwith more text").unwrap();
+/// writer.next_item();
+/// writeln!(writer, "Item 2").unwrap();
+///
+/// assert_eq!(writer.get_ref(), "- Item 1\n  with more text\n- Item 2\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ListItemWriter<'m, W> {
+    writer: W,
+    marker: &'m str,
+
+    // The number of chars in the marker, which is the number of spaces
+    // written at the start of each continuation line
+    width: usize,
+
+    // True if the marker hasn't been written yet for the current item
+    need_marker: bool,
+    need_prefix: bool,
+}
+
+impl<'m, W: fmt::Write> ListItemWriter<'m, W> {
+    /// Create a new [`ListItemWriter`].
+    #[inline]
+    pub fn new(marker: &'m str, writer: W) -> Self {
+        Self {
+            writer,
+            marker,
+            width: marker.chars().count(),
+            need_marker: true,
+            need_prefix: true,
+        }
+    }
+
+    /// Extract the writer from the `ListItemWriter`, discarding any in-progress
+    /// line state.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the list item
+    /// logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Get the marker written at the start of each item
+    #[inline]
+    pub fn marker(&self) -> &'m str {
+        self.marker
+    }
+
+    /// Start the next list item. The marker will be written at the start of
+    /// the next non-empty line. If the writer is in the middle of a line, the
+    /// next item will start after the next newline; write a newline first to
+    /// start it right away.
+    #[inline]
+    pub fn next_item(&mut self) {
+        self.need_marker = true;
+    }
+
+    fn write_prefix(&mut self) -> fmt::Result {
+        if self.need_marker {
+            self.writer.write_str(self.marker)?;
+            self.need_marker = false;
+        } else {
+            let mut remaining = self.width;
+
+            while remaining > 0 {
+                let len = remaining.min(SPACES.len());
+                self.writer.write_str(&SPACES[..len])?;
+                remaining -= len;
+            }
+        }
+
+        self.need_prefix = false;
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for ListItemWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.need_prefix && line != "\n" {
+                self.write_prefix()?;
+            }
+
+            self.writer.write_str(line)?;
+
+            if line.ends_with('\n') {
+                self.need_prefix = true;
+            }
+        }
+
+        Ok(())
+    }
+}
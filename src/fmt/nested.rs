@@ -0,0 +1,273 @@
+use core::fmt::{self, Write};
+
+use super::{Indent, IndentWriter};
+
+/// The operations a [`NestedIndentWriter`] needs from the writer it's nested
+/// in. Everything is ultimately written directly to the innermost writer.
+trait Parent {
+    /// Write the pending indents of this writer and all of its ancestors, if
+    /// we're at the start of a line.
+    fn begin_line(&mut self) -> fmt::Result;
+
+    /// Write content directly to the innermost writer.
+    fn write_content(&mut self, s: &str) -> fmt::Result;
+
+    /// Write an indent directly to the innermost writer.
+    fn write_indent(&mut self, indent: Indent<'_>) -> fmt::Result;
+
+    /// A newline was written; request an indent from this writer and all of
+    /// its ancestors.
+    fn end_line(&mut self);
+}
+
+impl<W: fmt::Write> Parent for IndentWriter<'_, W> {
+    fn begin_line(&mut self) -> fmt::Result {
+        if self.need_indent {
+            self.insert_indent(false)?;
+            self.begin_content();
+        }
+
+        Ok(())
+    }
+
+    fn write_content(&mut self, s: &str) -> fmt::Result {
+        self.emit_content(s)
+    }
+
+    fn write_indent(&mut self, indent: Indent<'_>) -> fmt::Result {
+        self.emit_indent(indent)
+    }
+
+    fn end_line(&mut self) {
+        self.need_indent = true;
+    }
+}
+
+/// An additional level of indentation nested inside an [`IndentWriter`].
+///
+/// Created with [`IndentWriter::nested`] or [`NestedIndentWriter::nested`].
+/// The output is identical to nesting an `IndentWriter` inside another, but
+/// a `NestedIndentWriter` writes directly to the innermost writer, rather than
+/// passing each indent and each piece of content through every level of
+/// nesting. This makes deep nesting much cheaper.
+///
+/// Only the indents of the outer writers are used; other configuration, such
+/// as wrapping, is not applied to content written through a
+/// `NestedIndentWriter`. The outer writers' state (such as
+/// [`line`][IndentWriter::line] and [`column`][IndentWriter::column]) is kept
+/// up to date.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::IndentWriter;
+///
+/// let mut writer = IndentWriter::new("\t", String::new());
+/// writeln!(writer, "Level 1").unwrap();
+/// {
+///     let mut nested = writer.nested("  ");
+///     writeln!(nested, "Level 2\n").unwrap();
+///
+///     let mut nested = nested.nested("> ");
+///     writeln!(nested, "Level 3").unwrap();
+/// }
+/// writeln!(writer, "Level 1").unwrap();
+///
+/// assert_eq!(
+///     writer.get_ref(),
+///     "\tLevel 1\n\t  Level 2\n\n\t  > Level 3\n\tLevel 1\n",
+/// );
+/// ```
+pub struct NestedIndentWriter<'a, 'n> {
+    parent: &'a mut (dyn Parent + 'a),
+    indent: Indent<'n>,
+    need_indent: bool,
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Create a [`NestedIndentWriter`], which adds an additional level of
+    /// indentation to everything written through it. See its documentation
+    /// for details.
+    #[inline]
+    pub fn nested<'a, 'n>(&'a mut self, indent: impl Into<Indent<'n>>) -> NestedIndentWriter<'a, 'n>
+    where
+        W: 'a,
+        'i: 'a,
+    {
+        NestedIndentWriter::new(self, indent.into())
+    }
+
+    /// Write a block: a `header` line, followed by a `body` indented one
+    /// level deeper (using this writer's indent), followed by a `footer`
+    /// line. The body is written through a [`NestedIndentWriter`], which has
+    /// its own `block` method, so blocks can be nested recursively.
+    ///
+    /// A newline is written after the header and after the footer. If the
+    /// body returns an error, it's returned immediately, without writing the
+    /// footer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new());
+    ///
+    /// writer.block("fn foo() {", "}", |body| {
+    ///     writeln!(body, "let x = 1;")?;
+    ///     body.block("if x > 0 {", "}", |body| writeln!(body, "bar(x);"))
+    /// }).unwrap();
+    ///
+    /// assert_eq!(
+    ///     writer.get_ref(),
+    ///     "    fn foo() {\n        let x = 1;\n        if x > 0 {\n            bar(x);\n        }\n    }\n",
+    /// );
+    /// ```
+    pub fn block(
+        &mut self,
+        header: &str,
+        footer: &str,
+        body: impl FnOnce(&mut NestedIndentWriter<'_, 'i>) -> fmt::Result,
+    ) -> fmt::Result {
+        self.write_str(header)?;
+        self.write_char('\n')?;
+
+        let indent = self.indent;
+        body(&mut self.nested(indent))?;
+
+        self.write_str(footer)?;
+        self.write_char('\n')
+    }
+}
+
+impl<'a, 'n> NestedIndentWriter<'a, 'n> {
+    #[inline]
+    fn new(parent: &'a mut (dyn Parent + 'a), indent: Indent<'n>) -> Self {
+        Self {
+            parent,
+            indent,
+            need_indent: true,
+        }
+    }
+
+    /// Create a [`NestedIndentWriter`] with an additional level of
+    /// indentation inside this one.
+    #[inline]
+    pub fn nested<'b, 'm>(&'b mut self, indent: impl Into<Indent<'m>>) -> NestedIndentWriter<'b, 'm>
+    where
+        'a: 'b,
+        'n: 'b,
+    {
+        NestedIndentWriter::new(self, indent.into())
+    }
+
+    /// Write a block: a `header` line, followed by a `body` indented one
+    /// level deeper (using this writer's indent), followed by a `footer`
+    /// line. See [`IndentWriter::block`].
+    pub fn block(
+        &mut self,
+        header: &str,
+        footer: &str,
+        body: impl FnOnce(&mut NestedIndentWriter<'_, 'n>) -> fmt::Result,
+    ) -> fmt::Result {
+        self.write_str(header)?;
+        self.write_char('\n')?;
+
+        let indent = self.indent;
+        body(&mut self.nested(indent))?;
+
+        self.write_str(footer)?;
+        self.write_char('\n')
+    }
+
+    /// Get the indent being inserted by this writer before each line
+    #[inline]
+    pub fn indent(&self) -> Indent<'n> {
+        self.indent
+    }
+}
+
+impl Parent for NestedIndentWriter<'_, '_> {
+    fn begin_line(&mut self) -> fmt::Result {
+        if self.need_indent {
+            self.parent.begin_line()?;
+            self.parent.write_indent(self.indent)?;
+            self.need_indent = false;
+        }
+
+        Ok(())
+    }
+
+    fn write_content(&mut self, s: &str) -> fmt::Result {
+        self.parent.write_content(s)
+    }
+
+    fn write_indent(&mut self, indent: Indent<'_>) -> fmt::Result {
+        self.parent.write_indent(indent)
+    }
+
+    fn end_line(&mut self) {
+        self.need_indent = true;
+        self.parent.end_line();
+    }
+}
+
+impl fmt::Write for NestedIndentWriter<'_, '_> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        loop {
+            match self.need_indent {
+                // We don't need an indent. Scan for the end of the line
+                false => match s.as_bytes().iter().position(|&b| b == b'\n') {
+                    // No end of line in the input; write the entire string
+                    None => break self.parent.write_content(s),
+
+                    // We can see the end of the line. Write up to and including
+                    // that newline, then request an indent
+                    Some(len) => {
+                        let (head, tail) = s.split_at(len + 1);
+                        self.parent.write_content(head)?;
+                        self.end_line();
+                        s = tail;
+                    }
+                },
+
+                // We need an indent. Scan for the beginning of the next
+                // non-empty line.
+                true => match s.as_bytes().iter().position(|&b| b != b'\n') {
+                    // No non-empty lines in input, write the entire string
+                    None => {
+                        self.parent.write_content(s)?;
+                        if !s.is_empty() {
+                            self.end_line();
+                        }
+                        break Ok(());
+                    }
+
+                    // We can see the next non-empty line. Write up to the
+                    // beginning of that line, then insert all of the pending
+                    // indents, then continue.
+                    Some(len) => {
+                        let (head, tail) = s.split_at(len);
+                        if !head.is_empty() {
+                            self.parent.write_content(head)?;
+                            self.end_line();
+                        }
+                        self.begin_line()?;
+                        s = tail;
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl fmt::Debug for NestedIndentWriter<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NestedIndentWriter")
+            .field("indent", &self.indent)
+            .field("need_indent", &self.need_indent)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,192 @@
+use core::fmt;
+
+const MARKERS: &str = ">>>>>>>>>>>>>>>>";
+const SPACES: &str = "                ";
+
+/// Write `count` bytes of `chunk`, which must be ASCII, repeating it as needed
+fn write_repeated(writer: &mut impl fmt::Write, chunk: &str, mut count: usize) -> fmt::Result {
+    while count > 0 {
+        let len = count.min(chunk.len());
+        writer.write_str(&chunk[..len])?;
+        count -= len;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LineState {
+    // At the start of a line; nothing has been written
+    Start,
+
+    // The line so far is quote markers, which are being held back. `spaces`
+    // is the number of spaces since the last marker.
+    Markers { count: usize, spaces: usize },
+
+    // The prefix has been written; forward the rest of the line
+    Content,
+}
+
+/// Adapter for writers to quote text, email style
+///
+/// A `QuoteWriter` adapts a [`fmt::Write`] object to quote each line written
+/// through it with `"> "`. Unlike an [`IndentWriter`][super::IndentWriter]
+/// with a `"> "` prefix, it understands text that is already quoted: if a
+/// line already starts with one or more `>` markers, another marker is added
+/// to them, giving `">>> text"` rather than `"> > > text"`. The spacing is
+/// normalized, so markers are never separated by spaces, and there is
+/// exactly one space between the markers and the text (beyond that, any
+/// extra spaces before the text are kept, so indentation inside quotes is
+/// preserved). Empty lines are quoted with a single `>`.
+///
+/// Lines may be split across several writes. While the content written so
+/// far on a line consists only of markers, it is held back until the rest of
+/// the line arrives. Use [`flush`][Self::flush] to write out any held-back
+/// content at the end of the output.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::QuoteWriter;
+///
+/// let mut writer = QuoteWriter::new(String::new());
+///
+/// write!(writer, "Line 1\n> Line 2\n> ").unwrap();
+/// write!(writer, "> Line 3\n\n>> Line 4\n").unwrap();
+///
+/// assert_eq!(writer.get_ref(), "> Line 1\n>> Line 2\n>>> Line 3\n>\n>>> Line 4\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuoteWriter<W> {
+    writer: W,
+    state: LineState,
+}
+
+impl<W: fmt::Write> QuoteWriter<W> {
+    /// Create a new [`QuoteWriter`].
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            state: LineState::Start,
+        }
+    }
+
+    /// Extract the writer from the `QuoteWriter`, discarding any held-back
+    /// content. Use [`flush`][Self::flush] first to write it.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the quoting
+    /// logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Write out any quote markers being held back at the start of the
+    /// current line. The rest of the current line will be forwarded
+    /// unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::QuoteWriter;
+    ///
+    /// let mut writer = QuoteWriter::new(String::new());
+    /// write!(writer, "> Line 1\n> ").unwrap();
+    /// assert_eq!(writer.get_ref(), ">> Line 1\n");
+    ///
+    /// writer.flush().unwrap();
+    /// assert_eq!(writer.get_ref(), ">> Line 1\n>> ");
+    /// ```
+    pub fn flush(&mut self) -> fmt::Result {
+        if let LineState::Markers { count, spaces } = self.state {
+            self.write_markers(count, spaces)?;
+            self.state = LineState::Content;
+        }
+
+        Ok(())
+    }
+
+    /// Write `count` markers, plus one for this writer, followed by a space
+    /// and any spaces after the first.
+    fn write_markers(&mut self, count: usize, spaces: usize) -> fmt::Result {
+        write_repeated(&mut self.writer, MARKERS, count + 1)?;
+        write_repeated(&mut self.writer, SPACES, spaces.max(1))
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for QuoteWriter<W> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        while let Some(c) = s.chars().next() {
+            match self.state {
+                LineState::Content => match s.find('\n') {
+                    None => return self.writer.write_str(s),
+                    Some(len) => {
+                        let (head, tail) = s.split_at(len + 1);
+                        self.writer.write_str(head)?;
+                        self.state = LineState::Start;
+                        s = tail;
+                    }
+                },
+                LineState::Start => match c {
+                    '>' => {
+                        self.state = LineState::Markers {
+                            count: 1,
+                            spaces: 0,
+                        };
+                        s = &s[1..];
+                    }
+                    '\n' => {
+                        self.writer.write_str(">\n")?;
+                        s = &s[1..];
+                    }
+                    _ => {
+                        self.writer.write_str("> ")?;
+                        self.state = LineState::Content;
+                    }
+                },
+                LineState::Markers { count, spaces } => match c {
+                    '>' => {
+                        self.state = LineState::Markers {
+                            count: count + 1,
+                            spaces: 0,
+                        };
+                        s = &s[1..];
+                    }
+                    ' ' => {
+                        self.state = LineState::Markers {
+                            count,
+                            spaces: spaces + 1,
+                        };
+                        s = &s[1..];
+                    }
+                    '\n' => {
+                        // Drop trailing spaces after the markers
+                        write_repeated(&mut self.writer, MARKERS, count + 1)?;
+                        self.state = LineState::Content;
+                    }
+                    _ => {
+                        self.write_markers(count, spaces)?;
+                        self.state = LineState::Content;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
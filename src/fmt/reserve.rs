@@ -0,0 +1,68 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use super::IndentWriter;
+
+/// Writers that can reserve space for content that is about to be written
+///
+/// This is implemented for in-memory writers like [`String`], so that
+/// [`IndentWriter::reserve_hint`] can reserve space for the output in one go,
+/// rather than letting it grow with each small write. Implementations may
+/// ignore the hint.
+pub trait ReserveHint {
+    /// Reserve space for at least `additional` more bytes of content.
+    fn reserve_hint(&mut self, additional: usize);
+}
+
+#[cfg(feature = "alloc")]
+impl ReserveHint for String {
+    #[inline]
+    fn reserve_hint(&mut self, additional: usize) {
+        self.reserve(additional)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ReserveHint for Vec<u8> {
+    #[inline]
+    fn reserve_hint(&mut self, additional: usize) {
+        self.reserve(additional)
+    }
+}
+
+impl<T: ReserveHint + ?Sized> ReserveHint for &mut T {
+    #[inline]
+    fn reserve_hint(&mut self, additional: usize) {
+        T::reserve_hint(self, additional)
+    }
+}
+
+impl<W: ReserveHint> ReserveHint for IndentWriter<'_, W> {
+    #[inline]
+    fn reserve_hint(&mut self, additional: usize) {
+        self.writer.reserve_hint(additional)
+    }
+}
+
+impl<'i, W: fmt::Write + ReserveHint> IndentWriter<'i, W> {
+    /// Reserve space in the inner writer for `expected_content_len` bytes of
+    /// content, which will be written on about `expected_lines` lines. Space
+    /// is reserved for the content, plus an indent and line suffix for each
+    /// line.
+    pub fn reserve_hint(&mut self, expected_content_len: usize, expected_lines: usize) {
+        let per_line = self.line_indent().byte_len() + self.line_suffix.len();
+
+        self.writer
+            .reserve_hint(expected_content_len + expected_lines * per_line)
+    }
+
+    /// Reserve space in the inner writer for writing `content`. This is
+    /// [`reserve_hint`][Self::reserve_hint], with the number of lines
+    /// estimated by counting the newlines in `content`.
+    pub fn reserve_for(&mut self, content: &str) {
+        let lines = content.bytes().filter(|&b| b == b'\n').count() + 1;
+        self.reserve_hint(content.len(), lines)
+    }
+}
@@ -0,0 +1,296 @@
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use super::IndentWriter;
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Get the current indentation level. The indent is repeated this many
+    /// times at the start of each non-empty line. The default level is 1.
+    ///
+    /// If this writer was created with
+    /// [`with_shared_level`][Self::with_shared_level], this is the shared
+    /// level.
+    #[inline]
+    pub fn level(&self) -> usize {
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(ref shared) = self.shared {
+                return shared.get();
+            }
+        }
+
+        self.level
+    }
+
+    /// Set the indentation level. The indent is repeated this many times at
+    /// the start of each non-empty line; a level of 0 disables indentation.
+    /// The new level takes effect at the start of the next line; if an
+    /// indent has already been written for the current line, it isn't
+    /// changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::spaces(2, String::new());
+    /// writeln!(writer, "Level 1").unwrap();
+    ///
+    /// writer.set_level(3);
+    /// writeln!(writer, "Level 3").unwrap();
+    ///
+    /// writer.set_level(0);
+    /// writeln!(writer, "Level 0").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  Level 1\n      Level 3\nLevel 0\n");
+    /// ```
+    ///
+    /// If this writer was created with
+    /// [`with_shared_level`][Self::with_shared_level], this sets the shared
+    /// level.
+    #[inline]
+    pub fn set_level(&mut self, level: usize) {
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(ref shared) = self.shared {
+                return shared.set(level);
+            }
+        }
+
+        self.level = level;
+    }
+
+    /// Set the indentation level, like [`set_level`][Self::set_level], unless
+    /// it's greater than the [maximum level][Self::max_level].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::spaces(2, String::new()).max_level(4);
+    ///
+    /// assert!(writer.try_set_level(4).is_ok());
+    /// assert!(writer.try_set_level(5).is_err());
+    /// assert_eq!(writer.level(), 4);
+    /// ```
+    pub fn try_set_level(&mut self, level: usize) -> Result<(), MaxLevelError> {
+        self.check_level(level)?;
+        self.set_level(level);
+        Ok(())
+    }
+
+    /// Configure the maximum indentation level for this writer. Once the
+    /// [level][Self::level] is greater than this, writes that would insert an
+    /// indent fail with [`fmt::Error`], and
+    /// [`try_set_level`][Self::try_set_level] and
+    /// [`try_scoped_indent`][Self::try_scoped_indent] fail with a
+    /// [`MaxLevelError`]. This is a guard against runaway recursion producing
+    /// huge amounts of indentation.
+    ///
+    /// Only this writer's level counts toward the limit, not the levels of
+    /// any `IndentWriter`s it writes to, or that write to it; when stacking
+    /// writers, give each of them a limit. A
+    /// [first line prefix][super::IndentWriterBuilder::first_line_prefix], which
+    /// is written once instead of the repeated indent, doesn't count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::spaces(2, String::new()).max_level(2);
+    ///
+    /// writer.set_level(2);
+    /// writeln!(writer, "Level 2").unwrap();
+    ///
+    /// writer.set_level(3);
+    /// assert!(writeln!(writer, "Level 3").is_err());
+    ///
+    /// assert_eq!(writer.get_ref(), "    Level 2\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn max_level(mut self, max: usize) -> Self {
+        self.max_level = Some(max);
+        self
+    }
+
+    /// Check that `level` doesn't exceed the maximum level.
+    pub(super) fn check_level(&self, level: usize) -> Result<(), MaxLevelError> {
+        match self.max_level {
+            Some(max) if level > max => Err(MaxLevelError { level, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Increase the indentation level by one, returning a guard which
+    /// restores the previous level when it's dropped. The guard mutably
+    /// borrows this writer and dereferences to it, so all writes made while
+    /// the level is increased go through the guard.
+    ///
+    /// Because the level is restored when the guard is dropped, it's restored
+    /// even in the event of an early return, or a panic.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::{self, Write};
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// fn write_items(writer: &mut IndentWriter<'_, String>) -> fmt::Result {
+    ///     writeln!(writer, "items:")?;
+    ///     let mut writer = writer.scoped_indent();
+    ///     writeln!(writer, "item 1")?;
+    ///     writeln!(writer, "item 2")
+    /// }
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    /// write_items(&mut writer).unwrap();
+    /// writeln!(writer, "done").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  items:\n    item 1\n    item 2\n  done\n");
+    /// ```
+    #[inline]
+    pub fn scoped_indent(&mut self) -> IndentGuard<'_, 'i, W> {
+        let previous = self.level();
+        self.set_level(previous + 1);
+
+        IndentGuard {
+            writer: self,
+            previous,
+        }
+    }
+
+    /// Increase the indentation level by one, like
+    /// [`scoped_indent`][Self::scoped_indent], unless that would exceed the
+    /// [maximum level][Self::max_level].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::{self, Write};
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// fn write_nested(writer: &mut IndentWriter<'_, String>, depth: usize) -> fmt::Result {
+    ///     writeln!(writer, "depth {}", depth)?;
+    ///     let mut writer = writer.try_scoped_indent().map_err(|_| fmt::Error)?;
+    ///     write_nested(&mut writer, depth + 1)
+    /// }
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new()).max_level(3);
+    /// assert!(write_nested(&mut writer, 1).is_err());
+    ///
+    /// assert_eq!(writer.get_ref(), "  depth 1\n    depth 2\n      depth 3\n");
+    /// ```
+    pub fn try_scoped_indent(&mut self) -> Result<IndentGuard<'_, 'i, W>, MaxLevelError> {
+        self.check_level(self.level() + 1)?;
+        Ok(self.scoped_indent())
+    }
+
+    /// Increase the indentation level by one for the duration of `body`.
+    /// The previous level is restored afterwards, even if `body` returns an
+    /// error or panics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", String::new());
+    /// writeln!(writer, "items:").unwrap();
+    /// writer.with_indent(|writer| writeln!(writer, "item")).unwrap();
+    /// writeln!(writer, "done").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "  items:\n    item\n  done\n");
+    /// ```
+    #[inline]
+    pub fn with_indent<T>(&mut self, body: impl FnOnce(&mut Self) -> T) -> T {
+        body(&mut self.scoped_indent())
+    }
+}
+
+/// Guard for a temporarily increased indentation level.
+///
+/// Created with [`IndentWriter::scoped_indent`]; see its documentation for
+/// an example. It dereferences to the [`IndentWriter`], and restores its
+/// previous level when dropped.
+#[derive(Debug)]
+#[must_use = "the indentation level is restored when the guard is dropped"]
+pub struct IndentGuard<'a, 'i, W: fmt::Write> {
+    writer: &'a mut IndentWriter<'i, W>,
+    previous: usize,
+}
+
+impl<'i, W: fmt::Write> Deref for IndentGuard<'_, 'i, W> {
+    type Target = IndentWriter<'i, W>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.writer
+    }
+}
+
+impl<W: fmt::Write> DerefMut for IndentGuard<'_, '_, W> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.writer
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for IndentGuard<'_, '_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.writer.write_char(c)
+    }
+}
+
+impl<W: fmt::Write> Drop for IndentGuard<'_, '_, W> {
+    #[inline]
+    fn drop(&mut self) {
+        self.writer.set_level(self.previous);
+    }
+}
+
+/// Error returned when the indentation level of an [`IndentWriter`] would
+/// exceed its [maximum level][IndentWriter::max_level].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxLevelError {
+    level: usize,
+    max: usize,
+}
+
+impl MaxLevelError {
+    /// Get the level that was requested.
+    #[inline]
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Get the maximum level of the writer.
+    #[inline]
+    pub fn max_level(&self) -> usize {
+        self.max
+    }
+}
+
+impl fmt::Display for MaxLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "indentation level {} exceeds the maximum of {}",
+            self.level, self.max
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MaxLevelError {}
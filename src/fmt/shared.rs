@@ -0,0 +1,217 @@
+use core::cell::Cell;
+use core::fmt;
+
+use alloc::rc::Rc;
+
+use super::{Indent, IndentWriter};
+
+#[derive(Debug, Default)]
+struct SharedState {
+    level: Cell<usize>,
+    need_indent: Cell<bool>,
+
+    // True while a writer using this level is formatting, during which, any
+    // other writer using this level is writing to it (through a `Formatter`)
+    // and should pass its content through unchanged
+    busy: Cell<bool>,
+}
+
+/// An indentation level shared between several [`IndentWriter`]s.
+///
+/// This is useful for recursive [`Display`][fmt::Display] implementations,
+/// where each level of recursion only has access to a [`Formatter`][fmt::Formatter],
+/// so there's no way to pass an `IndentWriter` along. Instead, each level can
+/// create its own writer with [`IndentWriter::with_shared_level`]; all of
+/// those writers share the same level, and the same line state,
+/// so they agree on the current indentation and whether we're at the
+/// start of a line.
+///
+/// If one of these writers is used to format a value that itself creates a
+/// writer with the same `SharedLevel` (for instance, with
+/// `write!(writer, "{}", child)`), the inner writer passes its content
+/// through unchanged and lets the outer writer do the indenting, so lines are
+/// never indented twice.
+///
+/// `SharedLevel` is a reference-counted handle; cloning it creates another
+/// handle to the same level. The level starts at 0.
+///
+/// This type requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::fmt::{self, Write};
+/// use indent_write::fmt::{IndentWriter, SharedLevel};
+///
+/// struct Tree {
+///     name: &'static str,
+///     children: Vec<Tree>,
+/// }
+///
+/// thread_local! {
+///     static LEVEL: SharedLevel = SharedLevel::new();
+/// }
+///
+/// impl fmt::Display for Tree {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         let level = LEVEL.with(SharedLevel::clone);
+///         let mut writer = IndentWriter::with_shared_level("  ", level.clone(), f);
+///
+///         writeln!(writer, "{}:", self.name)?;
+///
+///         let _guard = level.indent();
+///         for child in &self.children {
+///             write!(writer, "{}", child)?;
+///         }
+///
+///         Ok(())
+///     }
+/// }
+///
+/// let tree = Tree {
+///     name: "root",
+///     children: vec![
+///         Tree { name: "a", children: vec![Tree { name: "b", children: vec![] }] },
+///         Tree { name: "c", children: vec![] },
+///     ],
+/// };
+///
+/// assert_eq!(tree.to_string(), "root:\n  a:\n    b:\n  c:\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedLevel(Rc<SharedState>);
+
+impl SharedLevel {
+    /// Create a new `SharedLevel`, starting at level 0.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_level(0)
+    }
+
+    /// Create a new `SharedLevel`, starting at the given level.
+    #[inline]
+    pub fn with_level(level: usize) -> Self {
+        Self(Rc::new(SharedState {
+            level: Cell::new(level),
+            need_indent: Cell::new(true),
+            busy: Cell::new(false),
+        }))
+    }
+
+    /// Get the current level.
+    #[inline]
+    pub fn get(&self) -> usize {
+        self.0.level.get()
+    }
+
+    /// Set the current level. Like [`IndentWriter::set_level`], the new
+    /// level takes effect at the start of the next line.
+    #[inline]
+    pub fn set(&self, level: usize) {
+        self.0.level.set(level)
+    }
+
+    pub(super) fn need_indent(&self) -> bool {
+        self.0.need_indent.get()
+    }
+
+    pub(super) fn set_need_indent(&self, need_indent: bool) {
+        self.0.need_indent.set(need_indent)
+    }
+
+    /// Increase the level by one, returning a guard which restores the
+    /// previous level when it's dropped.
+    #[inline]
+    pub fn indent(&self) -> SharedLevelGuard {
+        let previous = self.get();
+        self.set(previous + 1);
+
+        SharedLevelGuard {
+            level: self.clone(),
+            previous,
+        }
+    }
+}
+
+impl Default for SharedLevel {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Guard for a temporarily increased [`SharedLevel`].
+///
+/// Created with [`SharedLevel::indent`]. It restores the previous level when
+/// dropped.
+#[derive(Debug)]
+#[must_use = "the level is restored when the guard is dropped"]
+pub struct SharedLevelGuard {
+    level: SharedLevel,
+    previous: usize,
+}
+
+impl Drop for SharedLevelGuard {
+    #[inline]
+    fn drop(&mut self) {
+        self.level.set(self.previous)
+    }
+}
+
+/// Clears the busy flag of a shared level when dropped, even on panic
+struct BusyGuard<'a>(&'a SharedState);
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        self.0.busy.set(false);
+    }
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Create a new [`IndentWriter`] which uses a [`SharedLevel`] instead of
+    /// its own indentation level. See [`SharedLevel`] for details and an
+    /// example.
+    ///
+    /// This function requires the `alloc` feature.
+    #[inline]
+    pub fn with_shared_level(indent: impl Into<Indent<'i>>, level: SharedLevel, writer: W) -> Self {
+        Self {
+            shared: Some(level),
+            ..Self::new(indent, writer)
+        }
+    }
+
+    pub(super) fn write_str_shared(&mut self, s: &str) -> fmt::Result {
+        let shared = self.shared.clone().expect("level isn't shared");
+
+        // Another writer is formatting into this one; let it do the
+        // indenting.
+        if shared.0.busy.get() && !self.owns_shared {
+            self.emit(s)?;
+            self.accepted += s.len();
+            return Ok(());
+        }
+
+        self.need_indent = shared.0.need_indent.get();
+        let result = self.write_checking_indent(s);
+        shared.0.need_indent.set(self.need_indent);
+        result
+    }
+
+    pub(super) fn write_fmt_shared(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        let shared = self.shared.clone().expect("level isn't shared");
+
+        if shared.0.busy.get() {
+            return fmt::write(self, args);
+        }
+
+        shared.0.busy.set(true);
+        let _busy = BusyGuard(&shared.0);
+
+        self.owns_shared = true;
+        let result = fmt::write(self, args);
+        self.owns_shared = false;
+
+        result
+    }
+}
@@ -0,0 +1,133 @@
+use core::fmt;
+
+use super::{IndentWriter, LineIndent};
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Configure this writer to leave lines alone if they already start with
+    /// the indent, rather than indenting them again. This is useful when
+    /// content may or may not have been indented by an earlier layer. The
+    /// indent that's compared is the complete indent for the line, including
+    /// every [level][Self::level]. Empty lines are never indented, as usual.
+    ///
+    /// Because a line can't be known to start with the indent until enough of
+    /// it has been written, the start of each line is held back while it
+    /// matches the indent. Call [`flush`][Self::flush] to write any
+    /// held-back content at the end of the stream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("    ", String::new()).skip_already_indented(true);
+    ///
+    /// write!(writer, "Line 1\n    Line 2\n\n  Line 3\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "    Line 1\n    Line 2\n\n      Line 3\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn skip_already_indented(mut self, skip: bool) -> Self {
+        self.skip_indented = skip;
+        self
+    }
+
+    /// Enable or disable leaving lines alone if they already start with the
+    /// indent. See [`skip_already_indented`][Self::skip_already_indented].
+    /// If it's disabled while the start of a line is being held back, that
+    /// content is written before the next content written to this writer.
+    #[inline]
+    pub fn set_skip_already_indented(&mut self, skip: bool) {
+        self.skip_indented = skip;
+    }
+
+    /// Write `s`, checking if each line already starts with the indent.
+    pub(super) fn write_checking_indent(&mut self, s: &str) -> fmt::Result {
+        if !self.skip_indented || !self.enabled {
+            self.flush_held_indent()?;
+            return self.write_indented(s);
+        }
+
+        for line in s.split_inclusive('\n') {
+            if let Some(line) = self.check_indent(line)? {
+                self.write_indented(line)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compare the start of `line` to the indent, if we're at the start of a
+    /// line. Get the part of the line that still needs to be written, or
+    /// None if it's being held back.
+    fn check_indent<'s>(&mut self, line: &'s str) -> Result<Option<&'s str>, fmt::Error> {
+        let held = match self.indent_matched {
+            Some(held) if self.need_indent => held,
+            _ => return Ok(Some(line)),
+        };
+
+        let indent = self.line_indent();
+        let total = indent.byte_len();
+
+        if total == 0 {
+            return Ok(Some(line));
+        }
+
+        let matched = indent
+            .bytes()
+            .skip(held)
+            .zip(line.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if held + matched == total {
+            // The line is already indented, so write it as plain content.
+            self.first_indent = None;
+            self.begin_content();
+        } else if matched == line.len() {
+            // Still a partial match; hold it back.
+            self.indent_matched = Some(held + matched);
+            self.accepted += matched;
+            return Ok(None);
+        }
+
+        self.indent_matched = None;
+        self.write_held_indent(indent, held)?;
+        Ok(Some(line))
+    }
+
+    /// If the start of the line is being held back because it matches the
+    /// indent, write it.
+    pub(super) fn flush_held_indent(&mut self) -> fmt::Result {
+        if let Some(held) = self.indent_matched {
+            if held > 0 && self.need_indent {
+                let indent = self.line_indent();
+                self.indent_matched = None;
+                self.write_held_indent(indent, held)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the first `held` bytes of the indent as content.
+    fn write_held_indent(&mut self, indent: LineIndent<'_>, held: usize) -> fmt::Result {
+        if held == 0 {
+            return Ok(());
+        }
+
+        // This content was accepted by an earlier write.
+        let accepted = self.accepted;
+        let mut remaining = held;
+
+        indent.try_for_each_chunk(|chunk| {
+            let len = remaining.min(chunk.len());
+            remaining -= len;
+            self.write_indented(&chunk[..len])
+        })?;
+
+        self.accepted = accepted;
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use core::fmt;
+
+use alloc::string::String;
+
+use super::Indent;
+
+/// A unit of indentation, for [`TranslateIndentWriter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// Each tab is one level of indentation
+    Tabs,
+
+    /// Each run of this many spaces is one level of indentation
+    Spaces(usize),
+}
+
+/// Adapter for writers to convert the leading whitespace of each line from
+/// one style of indentation to another
+///
+/// A `TranslateIndentWriter` adapts a [`fmt::Write`] object to rewrite the
+/// leading whitespace of each line written through it, converting
+/// indentation in one [`IndentStyle`] to another, such as from tabs to four
+/// spaces. Only whole levels of indentation at the very start of the line are
+/// converted: with [`IndentStyle::Spaces`], any leftover spaces are kept,
+/// and any other whitespace after the indentation (such as a space after a
+/// run of tabs) is written unchanged, as is the rest of the line. Lines that contain only whitespace are written
+/// unchanged.
+///
+/// To translate the indentation of content before indenting it, wrap an
+/// [`IndentWriter`][super::IndentWriter] in a `TranslateIndentWriter`.
+///
+/// Lines may be split across several writes. The leading whitespace of each
+/// line is held back until the first visible character or newline. Use
+/// [`flush`][Self::flush] to write out any held-back whitespace at the end of
+/// the output.
+///
+/// This type requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::{IndentStyle, TranslateIndentWriter};
+///
+/// let mut writer = TranslateIndentWriter::new(
+///     IndentStyle::Tabs,
+///     IndentStyle::Spaces(4),
+///     String::new(),
+/// );
+///
+/// write!(writer, "fn main() {{\n\t").unwrap();
+/// write!(writer, "if true {{\n\t\t\tx\t= 1;\n\t}}\n}}\n").unwrap();
+///
+/// assert_eq!(
+///     writer.get_ref(),
+///     "fn main() {\n    if true {\n            x\t= 1;\n    }\n}\n",
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TranslateIndentWriter<W> {
+    writer: W,
+    from: IndentStyle,
+    to: IndentStyle,
+
+    // True if we're at the start of a line, holding back leading whitespace
+    at_line_start: bool,
+    pending: String,
+}
+
+impl<W: fmt::Write> TranslateIndentWriter<W> {
+    /// Create a new [`TranslateIndentWriter`], which converts indentation in
+    /// the `from` style to the `to` style.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` is [`IndentStyle::Spaces(0)`][IndentStyle::Spaces].
+    #[inline]
+    pub fn new(from: IndentStyle, to: IndentStyle, writer: W) -> Self {
+        assert!(
+            from != IndentStyle::Spaces(0),
+            "indentation can't be translated from zero-width levels"
+        );
+
+        Self {
+            writer,
+            from,
+            to,
+            at_line_start: true,
+            pending: String::new(),
+        }
+    }
+
+    /// Extract the writer from the `TranslateIndentWriter`, discarding any
+    /// held-back whitespace. Use [`flush`][Self::flush] first to write it.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the translation
+    /// logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Write out any whitespace held back at the start of the current line,
+    /// unchanged, since the line might be blank. The rest of the current line
+    /// will be forwarded unchanged.
+    pub fn flush(&mut self) -> fmt::Result {
+        if self.at_line_start && !self.pending.is_empty() {
+            self.writer.write_str(&self.pending)?;
+            self.pending.clear();
+            self.at_line_start = false;
+        }
+
+        Ok(())
+    }
+
+    /// Write the held-back leading whitespace, translated.
+    fn write_translated(&mut self) -> fmt::Result {
+        let (levels, len) = match self.from {
+            IndentStyle::Tabs => {
+                let levels = self.pending.bytes().take_while(|&b| b == b'\t').count();
+                (levels, levels)
+            }
+            IndentStyle::Spaces(width) => {
+                let spaces = self.pending.bytes().take_while(|&b| b == b' ').count();
+                let levels = spaces / width;
+                (levels, levels * width)
+            }
+        };
+
+        let indent = match self.to {
+            IndentStyle::Tabs => Indent::Tabs(levels),
+            IndentStyle::Spaces(width) => Indent::Spaces(levels * width),
+        };
+
+        indent.try_for_each_chunk(|chunk| self.writer.write_str(chunk))?;
+        self.writer.write_str(&self.pending[len..])?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for TranslateIndentWriter<W> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        while !s.is_empty() {
+            if !self.at_line_start {
+                match s.find('\n') {
+                    None => return self.writer.write_str(s),
+                    Some(len) => {
+                        let (head, tail) = s.split_at(len + 1);
+                        self.writer.write_str(head)?;
+                        self.at_line_start = true;
+                        s = tail;
+                    }
+                }
+
+                continue;
+            }
+
+            match s.find(|c| c != ' ' && c != '\t') {
+                // The entire input is whitespace; hold it back
+                None => {
+                    self.pending.push_str(s);
+                    return Ok(());
+                }
+                Some(len) => {
+                    let (head, tail) = s.split_at(len);
+                    self.pending.push_str(head);
+
+                    if let Some(rest) = tail.strip_prefix('\n') {
+                        // The line is blank; write it unchanged
+                        self.writer.write_str(&self.pending)?;
+                        self.pending.clear();
+                        self.writer.write_str("\n")?;
+                        s = rest;
+                    } else {
+                        self.write_translated()?;
+                        self.at_line_start = false;
+                        s = tail;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use core::fmt;
+
+use super::IndentWriter;
+
+/// The characters used to draw the branches of a [`TreeWriter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeStyle {
+    /// Box-drawing characters: `"├── "`, `"└── "`, and `"│   "`
+    #[default]
+    Unicode,
+
+    /// ASCII characters: ``"|-- "``, ``"`-- "``, and ``"|   "``
+    Ascii,
+}
+
+impl TreeStyle {
+    /// The prefixes for a child: the branch for its first line, and the
+    /// continuation for the rest.
+    fn prefixes(self, last: bool) -> (&'static str, &'static str) {
+        match (self, last) {
+            (TreeStyle::Unicode, false) => ("├── ", "│   "),
+            (TreeStyle::Unicode, true) => ("└── ", "    "),
+            (TreeStyle::Ascii, false) => ("|-- ", "|   "),
+            (TreeStyle::Ascii, true) => ("`-- ", "    "),
+        }
+    }
+}
+
+/// Adapter for writers to draw trees
+///
+/// A `TreeWriter` writes a node of a tree, such as a directory listing.
+/// Content written to it is written unchanged; use [`child`][Self::child]
+/// to get a writer for each of the node's children. The first line written
+/// to a child is prefixed with a branch (`"├── "`, or `"└── "` for the last
+/// child), and each following line with a continuation (`"│   "`, or
+/// `"    "` for the last child), so that multi-line labels, and the child's
+/// own children, line up under the child's label. Empty lines aren't
+/// prefixed.
+///
+/// # Example
+///
+/// ```
+/// # use std::fmt::Write;
+/// use indent_write::fmt::TreeWriter;
+///
+/// let mut root = TreeWriter::new(String::new());
+/// writeln!(root, "src").unwrap();
+/// {
+///     let mut fmt = root.child(false);
+///     writeln!(fmt, "fmt\n(directory)").unwrap();
+///     writeln!(fmt.child(true), "tree.rs").unwrap();
+/// }
+/// writeln!(root.child(true), "lib.rs").unwrap();
+///
+/// assert_eq!(
+///     root.get_ref(),
+///     "src\n├── fmt\n│   (directory)\n│   └── tree.rs\n└── lib.rs\n",
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreeWriter<W> {
+    writer: IndentWriter<'static, W>,
+    style: TreeStyle,
+}
+
+impl<W: fmt::Write> TreeWriter<W> {
+    /// Create a new [`TreeWriter`] for the root of a tree, which draws
+    /// branches with box-drawing characters.
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        Self::with_style(TreeStyle::Unicode, writer)
+    }
+
+    /// Create a new [`TreeWriter`] for the root of a tree, which draws
+    /// branches in the given style. Children use the same style.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::{TreeStyle, TreeWriter};
+    ///
+    /// let mut root = TreeWriter::with_style(TreeStyle::Ascii, String::new());
+    /// writeln!(root, "root").unwrap();
+    /// {
+    ///     let mut a = root.child(false);
+    ///     writeln!(a, "a").unwrap();
+    ///     writeln!(a.child(true), "b").unwrap();
+    /// }
+    /// writeln!(root.child(true), "c").unwrap();
+    ///
+    /// assert_eq!(root.get_ref(), "root\n|-- a\n|   `-- b\n`-- c\n");
+    /// ```
+    #[inline]
+    pub fn with_style(style: TreeStyle, writer: W) -> Self {
+        Self {
+            writer: IndentWriter::new("", writer),
+            style,
+        }
+    }
+
+    /// Get a writer for a child of this node. Set `last` if this is the last
+    /// child, so that its branch is drawn with a corner, and no continuation
+    /// is drawn next to its own children.
+    #[inline]
+    pub fn child(&mut self, last: bool) -> TreeWriter<&mut Self> {
+        let style = self.style;
+        let (branch, continuation) = style.prefixes(last);
+
+        TreeWriter {
+            writer: IndentWriter::builder(continuation)
+                .first_line_prefix(branch)
+                .build(self),
+            style,
+        }
+    }
+
+    /// Get the style used to draw branches
+    #[inline]
+    pub fn style(&self) -> TreeStyle {
+        self.style
+    }
+
+    /// Extract the writer from the `TreeWriter`
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the tree
+    /// drawing logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.get_mut()
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for TreeWriter<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.writer.write_char(c)
+    }
+}
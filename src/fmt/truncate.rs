@@ -0,0 +1,194 @@
+use core::fmt;
+
+use alloc::string::String;
+
+use super::{Indent, IndentWriter};
+
+/// The state of a writer that truncates long lines.
+#[derive(Debug, Clone)]
+pub(super) struct Truncate<'i> {
+    width: usize,
+    marker: &'i str,
+    marker_width: usize,
+
+    // Content at the end of the current line that would fit within the
+    // width, but not if the marker were written after it. It's held back
+    // until we know whether the line needs to be truncated.
+    held: String,
+
+    // True if the current line was truncated, and the rest of it is being
+    // discarded
+    discarding: bool,
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Create a new [`IndentWriter`] which will truncate lines that would
+    /// exceed `width` columns, writing `marker` in place of the rest of the
+    /// line. The indent and the marker count toward the width, so no line is
+    /// longer than `width`, and lines are truncated at `char` boundaries.
+    /// Everything after the truncation point is discarded, up to the next
+    /// newline, except that escape sequences are still written if the writer
+    /// is [`ansi_aware`][Self::ansi_aware]. Lines that fit within the width,
+    /// including lines that would exactly fit without a marker, are written
+    /// unchanged. With [`expand_tabs`][Self::expand_tabs], each tab counts
+    /// as the number of columns it's expanded to.
+    ///
+    /// Because a line can't be known to fit until its newline is written,
+    /// the last `marker.chars().count()` columns of each line are held back.
+    /// Call [`flush`][Self::flush] to write any held-back content at the end
+    /// of the stream. If more content is written to the same line after
+    /// that, and the line then exceeds the width, it's truncated without a
+    /// marker if there's no room left for one.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::with_max_width_truncate("> ", 10, "…", String::new());
+    ///
+    /// write!(writer, "short\nexactly8\nmuch too ").unwrap();
+    /// write!(writer, "long\n\nabc").unwrap();
+    /// writer.flush().unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), "> short\n> exactly8\n> much to…\n\n> abc");
+    /// ```
+    #[inline]
+    pub fn with_max_width_truncate(
+        indent: impl Into<Indent<'i>>,
+        width: usize,
+        marker: &'i str,
+        writer: W,
+    ) -> Self {
+        Self::new(indent, writer).truncate_at(width, marker)
+    }
+
+    pub(super) fn truncate_at(mut self, width: usize, marker: &'i str) -> Self {
+        self.truncate = Some(Truncate {
+            width,
+            marker,
+            marker_width: marker.chars().count(),
+            held: String::new(),
+            discarding: false,
+        });
+        self
+    }
+
+    /// Write part of a line, which contains no newlines, truncating it if
+    /// it's too long.
+    pub(super) fn write_truncated(&mut self, line: &str) -> fmt::Result {
+        let truncate = self.truncate.as_ref().expect("not truncating");
+
+        if truncate.discarding {
+            self.emit_discarded(line)?;
+            self.accepted += line.len();
+            return Ok(());
+        }
+
+        // Write directly as much as will fit along with the marker
+        let mut line = line;
+        if truncate.held.is_empty() {
+            let width = truncate.width.saturating_sub(truncate.marker_width);
+            let len = self.columns().fit(line, width);
+            let (head, tail) = line.split_at(len);
+
+            self.emit_content(head)?;
+            line = tail;
+        }
+
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        // Measure the line from the end of the held-back content, which is
+        // never more than the width of the marker
+        let mut columns = self.columns();
+        let truncate = self.truncate.as_mut().expect("not truncating");
+        columns.fit(&truncate.held, usize::MAX);
+
+        match columns.fit(line, truncate.width) == line.len() {
+            // The rest of the line fits, so far; hold it back
+            true => truncate.held.push_str(line),
+
+            // The line is too long. Drop the held-back content, then write
+            // the marker in its place, if there's room.
+            false => {
+                let marker = truncate.marker;
+
+                if self.column + truncate.marker_width <= truncate.width {
+                    self.emit(marker)?;
+                }
+
+                let truncate = self.truncate.as_mut().expect("not truncating");
+                let held = core::mem::take(&mut truncate.held);
+                truncate.discarding = true;
+
+                self.emit_discarded(&held)?;
+                self.emit_discarded(line)?;
+            }
+        }
+
+        self.accepted += line.len();
+        Ok(())
+    }
+
+    /// If we're aware of ANSI escape sequences, write the ones in content
+    /// that's being discarded, so that the colors and styles they set (or
+    /// reset) still take effect.
+    fn emit_discarded(&mut self, s: &str) -> fmt::Result {
+        let mut escape = match &self.ansi {
+            Some(ansi) => ansi.escape,
+            None => return Ok(()),
+        };
+
+        let mut start = None;
+        for (idx, c) in s.char_indices() {
+            match (escape.advance(c), start) {
+                (true, None) => start = Some(idx),
+                (false, Some(run)) => {
+                    start = None;
+                    self.emit(&s[run..idx])?;
+                    self.observe_ansi(&s[run..idx]);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(run) = start {
+            self.emit(&s[run..])?;
+            self.observe_ansi(&s[run..]);
+        }
+
+        Ok(())
+    }
+
+    /// The current line is ending, or being flushed. Write any content
+    /// that was held back because the line might have been truncated.
+    pub(super) fn end_truncated_line(&mut self, line_end: bool) -> fmt::Result {
+        let held = match self.truncate {
+            Some(ref mut truncate) => {
+                if line_end {
+                    truncate.discarding = false;
+                }
+                core::mem::take(&mut truncate.held)
+            }
+            None => return Ok(()),
+        };
+
+        // The held-back content was already accepted by an earlier write
+        let accepted = self.accepted;
+        let result = self.emit_content(&held);
+        self.accepted = accepted;
+
+        if result.is_err() {
+            if let Some(ref mut truncate) = self.truncate {
+                truncate.held = held;
+            }
+        }
+
+        result
+    }
+}
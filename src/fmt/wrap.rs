@@ -0,0 +1,322 @@
+//! Word wrapping support for [`IndentWriter`], enabled by the `wrap` feature.
+
+use core::fmt;
+
+use alloc::string::String;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::columns::Escape;
+use super::{Indent, IndentWriter};
+
+/// State for word wrapping. The current word, and the whitespace preceding
+/// it, are held back until we know whether they fit on the current line.
+#[derive(Debug, Clone)]
+pub(super) struct WordWrap {
+    width: usize,
+
+    // The display width of the output written so far on the current line,
+    // including the indent
+    line_width: usize,
+
+    // True if no content has been written on the current line
+    at_line_start: bool,
+
+    space: String,
+    space_width: usize,
+    word: String,
+    word_width: usize,
+
+    // The escape sequence being written, if we're aware of ANSI escapes
+    escape: Escape,
+}
+
+/// The display width of an indent. Like other control characters, tabs have
+/// a width of zero.
+fn indent_width(indent: Indent<'_>) -> usize {
+    match indent {
+        Indent::Spaces(count) => count,
+        Indent::Tabs(_) => 0,
+        Indent::Prefix(prefix) => prefix.width(),
+    }
+}
+
+/// The display width of some content. If `ansi` is true, escape sequences
+/// have no width.
+fn content_width(content: &str, ansi: bool) -> usize {
+    if !ansi {
+        return content.width();
+    }
+
+    let mut escape = Escape::None;
+    content
+        .chars()
+        .filter(|&c| !escape.advance(c))
+        .map(|c| c.width().unwrap_or(0))
+        .sum()
+}
+
+impl WordWrap {
+    pub(super) fn new(width: usize) -> Self {
+        Self {
+            width,
+            line_width: 0,
+            at_line_start: true,
+            space: String::new(),
+            space_width: 0,
+            word: String::new(),
+            word_width: 0,
+            escape: Escape::None,
+        }
+    }
+}
+
+impl<'i, W: fmt::Write> IndentWriter<'i, W> {
+    /// Create a new [`IndentWriter`] which will wrap lines at word boundaries
+    /// so that they don't exceed `width` columns. Widths are measured with
+    /// [`unicode-width`](https://docs.rs/unicode-width), so wide characters
+    /// (such as CJK text and emoji) are accounted for; note that control
+    /// characters, including tabs, have a width of zero. The indent counts
+    /// toward the width, and is written at the start of each continuation
+    /// line.
+    ///
+    /// Lines are broken at the last whitespace that fits; the whitespace at
+    /// the break is discarded. Words that are too long to fit on a line by
+    /// themselves are broken at the width. Newlines in the written content
+    /// reset the width as normal.
+    ///
+    /// Because a word can't be known to fit until it's complete, the current
+    /// word is held back until the following whitespace or newline. Call
+    /// [`flush`][Self::flush] to write any held-back content at the end of
+    /// the stream.
+    ///
+    /// This function requires the `wrap` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::with_word_wrap("> ", 12, String::new());
+    ///
+    /// write!(writer, "The quick brown fox jumps\n😀😀😀😀😀😀").unwrap();
+    /// writer.flush().unwrap();
+    ///
+    /// assert_eq!(
+    ///     writer.get_ref(),
+    ///     "> The quick\n> brown fox\n> jumps\n> 😀😀😀😀😀\n> 😀",
+    /// );
+    /// ```
+    #[inline]
+    pub fn with_word_wrap(indent: impl Into<Indent<'i>>, width: usize, writer: W) -> Self {
+        Self {
+            wrap: Some(WordWrap::new(width)),
+            ..Self::new(indent, writer)
+        }
+    }
+
+    /// Create a new [`IndentWriter`] for writing wrapped comments, such as
+    /// doc comments. Like [`with_word_wrap`][Self::with_word_wrap], lines
+    /// are wrapped so that the prefix and the content together don't exceed
+    /// `width` columns, and newlines in the written content start a new
+    /// line. Empty lines are prefixed too, with any trailing whitespace
+    /// trimmed from the prefix, so that a paragraph break in a `"/// "`
+    /// comment is written as a bare `"///"`.
+    ///
+    /// This is the same as `with_word_wrap`, with
+    /// [`indent_empty_lines`][super::IndentWriterBuilder::indent_empty_lines]
+    /// and [`trim_prefix_on_blank_lines`][Self::trim_prefix_on_blank_lines]
+    /// enabled.
+    ///
+    /// This function requires the `wrap` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::fmt::Write;
+    /// use indent_write::fmt::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::with_comment_wrap("/// ", 24, String::new());
+    ///
+    /// write!(writer, "The quick brown fox jumps over the lazy dog.\n\n").unwrap();
+    /// write!(writer, "Second paragraph.\n").unwrap();
+    ///
+    /// assert_eq!(
+    ///     writer.get_ref(),
+    ///     "/// The quick brown fox\n/// jumps over the lazy\n/// dog.\n///\n/// Second paragraph.\n",
+    /// );
+    /// ```
+    #[inline]
+    pub fn with_comment_wrap(prefix: &'i str, width: usize, writer: W) -> Self {
+        Self {
+            indent_empty_lines: true,
+            trim_blank: true,
+            ..Self::with_word_wrap(prefix, width, writer)
+        }
+    }
+
+    /// Write content through the word wrapper.
+    pub(super) fn write_str_wrapped(&mut self, s: &str) -> fmt::Result {
+        // Content is counted as accepted when it's buffered, not when it's
+        // eventually written.
+        let accepted = self.accepted;
+
+        for (idx, c) in s.char_indices() {
+            if let Err(err) = self.write_char_wrapped(c) {
+                self.accepted = accepted + idx;
+                return Err(err);
+            }
+        }
+
+        self.accepted = accepted + s.len();
+        Ok(())
+    }
+
+    fn write_char_wrapped(&mut self, c: char) -> fmt::Result {
+        let ansi = self.ansi.is_some();
+        let wrap = self.wrap.as_mut().expect("not word wrapping");
+
+        // Escape sequences are part of the current word, with no width
+        if ansi && wrap.escape.advance(c) {
+            wrap.word.push(c);
+            return Ok(());
+        }
+
+        match c {
+            '\n' => {
+                self.flush_wrapped()?;
+
+                if self.need_indent && self.indent_empty_lines {
+                    self.insert_indent(self.trim_blank)?;
+                    self.begin_content();
+                }
+
+                if self.need_indent {
+                    self.emit("\n")?;
+                } else {
+                    self.emit_newline()?;
+                }
+                self.need_indent = true;
+
+                let wrap = self.wrap.as_mut().expect("not word wrapping");
+                wrap.line_width = 0;
+                wrap.at_line_start = true;
+            }
+            c if c.is_whitespace() => {
+                if !wrap.word.is_empty() {
+                    self.flush_wrapped()?;
+                }
+
+                let wrap = self.wrap.as_mut().expect("not word wrapping");
+                wrap.space.push(c);
+                wrap.space_width += c.width().unwrap_or(0);
+            }
+            c => {
+                wrap.word.push(c);
+                wrap.word_width += c.width().unwrap_or(0);
+                self.fit_word()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The display width of the indent for the current line.
+    fn line_indent_width(&self) -> usize {
+        self.line_indent().width(indent_width)
+    }
+
+    /// Ensure that the held-back word fits on the current line, breaking the
+    /// line if it doesn't.
+    fn fit_word(&mut self) -> fmt::Result {
+        let indent_width = self.line_indent_width();
+        let ansi = self.ansi.is_some();
+
+        loop {
+            let wrap = self.wrap.as_mut().expect("not word wrapping");
+
+            let pending_indent = if self.need_indent { indent_width } else { 0 };
+            let total = wrap.line_width + pending_indent + wrap.space_width + wrap.word_width;
+
+            if total <= wrap.width {
+                break Ok(());
+            }
+
+            if !wrap.at_line_start {
+                // There's already content on this line. Discard the
+                // whitespace and move the word to the next line.
+                wrap.space.clear();
+                wrap.space_width = 0;
+                self.break_line()?;
+            } else {
+                // The word doesn't fit on a line by itself. Write all but its
+                // last character, which is the one that caused it to
+                // overflow.
+                let last = match wrap.word.char_indices().next_back() {
+                    Some((0, _)) | None => break Ok(()),
+                    Some((idx, _)) => idx,
+                };
+
+                let tail = wrap.word.split_off(last);
+                let head = core::mem::replace(&mut wrap.word, tail);
+                wrap.word_width = content_width(&wrap.word, ansi);
+
+                let mut content = core::mem::take(&mut wrap.space);
+                wrap.space_width = 0;
+                content.push_str(&head);
+
+                self.write_wrapped_content(&content)?;
+                self.break_line()?;
+            }
+        }
+    }
+
+    /// Write out the held-back whitespace and word.
+    pub(super) fn flush_wrapped(&mut self) -> fmt::Result {
+        let wrap = self.wrap.as_mut().expect("not word wrapping");
+        let mut content = core::mem::take(&mut wrap.space);
+        content.push_str(&wrap.word);
+        wrap.word.clear();
+        wrap.space_width = 0;
+        wrap.word_width = 0;
+
+        self.write_wrapped_content(&content)
+    }
+
+    /// Write content known to contain no newlines, inserting an indent first
+    /// if necessary.
+    fn write_wrapped_content(&mut self, content: &str) -> fmt::Result {
+        if content.is_empty() {
+            return Ok(());
+        }
+
+        if self.need_indent {
+            let width = self.line_indent_width();
+            self.insert_indent(false)?;
+            self.begin_content();
+
+            let wrap = self.wrap.as_mut().expect("not word wrapping");
+            wrap.line_width += width;
+        }
+
+        self.emit_content(content)?;
+
+        let width = content_width(content, self.ansi.is_some());
+        let wrap = self.wrap.as_mut().expect("not word wrapping");
+        wrap.line_width += width;
+        wrap.at_line_start = false;
+
+        Ok(())
+    }
+
+    fn break_line(&mut self) -> fmt::Result {
+        self.emit_newline()?;
+        self.need_indent = true;
+
+        let wrap = self.wrap.as_mut().expect("not word wrapping");
+        wrap.line_width = 0;
+        wrap.at_line_start = true;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
-use core::fmt::{self, Display, Formatter, Write};
+use core::fmt::{self, Debug, Display, Formatter, Write};
 
-use crate::fmt::IndentWriter;
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "alloc")]
+use core::cell::OnceCell;
+
+use crate::fmt::{Indent, IndentWriter};
 
 /// Methods for adapting [`Display`] objects to indent themselves when printed.
 pub trait Indentable: Sized + Display {
@@ -19,6 +24,9 @@ pub trait Indentable: Sized + Display {
     ///
     /// assert_eq!(result, "    Line 1\n    Line 2\n\n    Line 3\n");
     /// ```
+    ///
+    /// The width, fill, alignment, and precision of the [`Formatter`] are
+    /// ignored, unless the wrapper is [`padded`][Indented::padded].
     #[must_use = "Indentables do nothing unless used"]
     fn indented(self, indent: &str) -> Indented<'_, Self> {
         Indented { item: self, indent }
@@ -39,19 +47,205 @@ pub trait Indentable: Sized + Display {
     ///
     /// assert_eq!(result, "Line 1\n    Line 2\n\n    Line 3\n");
     /// ```
+    ///
+    /// This is useful when the first line continues a line that's already
+    /// been started:
+    ///
+    /// ```
+    /// use indent_write::indentable::Indentable;
+    ///
+    /// let details = "first\nsecond";
+    /// let result = format!("error: {}", details.indented_skip_initial("    "));
+    ///
+    /// assert_eq!(result, "error: first\n    second");
+    /// ```
+    #[must_use = "Indentables do nothing unless used"]
+    fn indented_skip_initial(self, indent: &str) -> IndentedSkipInitial<'_, Self> {
+        IndentedSkipInitial { item: self, indent }
+    }
+
+    /// Wrap this object so that its [`Display`] representation is indented
+    /// by the width of the [`Formatter`]: `format!("{:4}", indented)` inserts
+    /// four spaces before each non-empty line. With no width, no indent is
+    /// inserted. This lets the caller choose the depth of the indentation
+    /// in the format string.
+    ///
+    /// The width is only used as the depth of the indentation, so it's never
+    /// used to pad the output. The precision, fill, and alignment are
+    /// ignored.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use indent_write::indentable::Indentable;
+    ///
+    /// let content = "Line 1\n\nLine 2";
+    ///
+    /// assert_eq!(format!("{:4}", content.indented_by_width()), "    Line 1\n\n    Line 2");
+    /// assert_eq!(format!("{:>1$}", content.indented_by_width(), 2), "  Line 1\n\n  Line 2");
+    /// assert_eq!(format!("{}", content.indented_by_width()), content);
+    /// ```
+    #[must_use = "Indentables do nothing unless used"]
+    fn indented_by_width(self) -> IndentedByWidth<'static, Self> {
+        IndentedByWidth {
+            item: self,
+            indent: Indent::Spaces(1),
+        }
+    }
+
+    /// Wrap this object so that its [`Display`] representation is indented
+    /// with `indent`, repeated as many times as the width of the
+    /// [`Formatter`]. This is the same as
+    /// [`indented_by_width`][Self::indented_by_width], but with `indent`
+    /// instead of a single space as the unit of indentation.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use indent_write::indentable::Indentable;
+    ///
+    /// let content = "Line 1\nLine 2";
+    ///
+    /// assert_eq!(
+    ///     format!("{:2}", content.indented_by_width_with("| ")),
+    ///     "| | Line 1\n| | Line 2",
+    /// );
+    /// ```
+    #[must_use = "Indentables do nothing unless used"]
+    fn indented_by_width_with(self, indent: &str) -> IndentedByWidth<'_, Self> {
+        IndentedByWidth {
+            item: self,
+            indent: Indent::Prefix(indent),
+        }
+    }
+
+    /// Format this object, indented with the given `indent`, into a
+    /// [`String`]. This is the same as `self.indented(indent).to_string()`.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use indent_write::indentable::Indentable;
+    ///
+    /// let result = "Line 1\n\nLine 2".indented_to_string("  ");
+    ///
+    /// assert_eq!(result, "  Line 1\n\n  Line 2");
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    fn indented_to_string(self, indent: &str) -> String {
+        self.indented(indent).to_string()
+    }
+
+    /// Wrap this object so that its [`Display`] representation is indented
+    /// with the given `indent`, like [`indented`][Self::indented], but only
+    /// formatted once. The first time the wrapper is printed, the indented
+    /// output is rendered into a [`String`]; after that, the rendered output
+    /// is written without formatting the object again. This is useful when
+    /// the object is expensive to format, and is printed several times.
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::fmt;
+    /// use indent_write::indentable::Indentable;
+    ///
+    /// struct Expensive<'a>(&'a Cell<u32>);
+    ///
+    /// impl fmt::Display for Expensive<'_> {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         self.0.set(self.0.get() + 1);
+    ///         write!(f, "tree\nof\nnodes")
+    ///     }
+    /// }
+    ///
+    /// let count = Cell::new(0);
+    /// let indented = Expensive(&count).indented_once("  ");
+    ///
+    /// assert_eq!(indented.to_string(), "  tree\n  of\n  nodes");
+    /// assert_eq!(format!("[\n{}\n]", indented), "[\n  tree\n  of\n  nodes\n]");
+    /// assert_eq!(count.get(), 1);
+    /// ```
+    #[cfg(feature = "alloc")]
     #[must_use = "Indentables do nothing unless used"]
-    fn indented_skip_initial(self, indent: &str) -> IndentedSkipIntial<'_, Self> {
-        IndentedSkipIntial { item: self, indent }
+    fn indented_once(self, indent: &str) -> IndentedOnce<'_, Self> {
+        IndentedOnce {
+            item: self,
+            indent,
+            rendered: OnceCell::new(),
+        }
     }
 }
 
 impl<T: Display> Indentable for T {}
 
+/// Methods for adapting [`Debug`] objects to indent their pretty-printed
+/// representation.
+pub trait IndentableDebug: Sized + Debug {
+    /// Wrap this object so that, when printed with [`Display`], it writes its
+    /// alternate [`Debug`] representation (as with `{:#?}`), indented with the
+    /// given `indent`. Each non-empty line of the formatted output will be
+    /// prefixed with the indent.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use indent_write::indentable::IndentableDebug;
+    ///
+    /// #[derive(Debug)]
+    /// struct Point {
+    ///     x: i32,
+    ///     y: i32,
+    /// }
+    ///
+    /// #[derive(Debug)]
+    /// struct Line {
+    ///     start: Point,
+    ///     end: Point,
+    /// }
+    ///
+    /// let line = Line {
+    ///     start: Point { x: 1, y: 2 },
+    ///     end: Point { x: 3, y: 4 },
+    /// };
+    ///
+    /// let result = format!("line:\n{}\n", line.indented_debug("    "));
+    ///
+    /// assert_eq!(result, "\
+    /// line:
+    ///     Line {
+    ///         start: Point {
+    ///             x: 1,
+    ///             y: 2,
+    ///         },
+    ///         end: Point {
+    ///             x: 3,
+    ///             y: 4,
+    ///         },
+    ///     }
+    /// ");
+    /// ```
+    #[must_use = "Indentables do nothing unless used"]
+    fn indented_debug(self, indent: &str) -> IndentedDebug<'_, Self> {
+        IndentedDebug { item: self, indent }
+    }
+}
+
+impl<T: Debug> IndentableDebug for T {}
+
 /// Wrapper struct that indents the [`Display`] representation of an item. When
 /// printed with [`Display`], it will insert [`indent`][Self::indent] before
 /// each non-empty line of the underlying [`item`][Self::item]'s [`Display`]
 /// output.
 ///
+/// The [`Formatter`]'s width, fill, alignment, and precision are ignored;
+/// use [`padded`][Self::padded] to apply them to each line.
+///
 /// Created with [`Indentable::indented`]; see its documentation for an example.
 #[derive(Debug, Clone, Copy)]
 pub struct Indented<'i, T: Display> {
@@ -62,6 +256,35 @@ pub struct Indented<'i, T: Display> {
     pub indent: &'i str,
 }
 
+impl<'i, T: Display> Indented<'i, T> {
+    /// Wrap this object so that the width, fill, alignment, and precision of
+    /// the [`Formatter`] are applied to each non-empty line of the indented
+    /// output, including its indent. For instance,
+    /// `format!("{:>12.10}", indented.padded())` right-aligns each line,
+    /// truncated to 10 characters, in 12 columns. See [`Padded`].
+    ///
+    /// This method requires the `alloc` feature.
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use indent_write::indentable::Indentable;
+    ///
+    /// let indented = "Line 1\nLong line 2\n\nLine 3".indented("  ");
+    ///
+    /// assert_eq!(
+    ///     format!("{:->12.10}", indented.padded()),
+    ///     "----  Line 1\n--  Long lin\n\n----  Line 3",
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use = "Indentables do nothing unless used"]
+    pub fn padded(self) -> Padded<Self> {
+        Padded { item: self }
+    }
+}
+
 impl<T: Display> Display for Indented<'_, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(IndentWriter::new(self.indent, f), "{}", self.item)
@@ -73,9 +296,13 @@ impl<T: Display> Display for Indented<'_, T> {
 /// each non-empty line _after the first_ of the underlying
 /// [`item`][Self::item]'s [`Display`] output.
 ///
-/// Created with [`Indentable::indented`]; see its documentation for an example.
+/// Formatting options are handled the same way as for [`Indented`]; use
+/// [`padded`][Self::padded] to apply them to each line.
+///
+/// Created with [`Indentable::indented_skip_initial`]; see its documentation
+/// for an example.
 #[derive(Debug, Clone, Copy)]
-pub struct IndentedSkipIntial<'i, T: Display> {
+pub struct IndentedSkipInitial<'i, T: Display> {
     /// The item to indent.
     pub item: T,
 
@@ -83,7 +310,25 @@ pub struct IndentedSkipIntial<'i, T: Display> {
     pub indent: &'i str,
 }
 
-impl<T: Display> Display for IndentedSkipIntial<'_, T> {
+/// The old, misspelled name of [`IndentedSkipInitial`].
+#[deprecated(note = "renamed to `IndentedSkipInitial`")]
+pub type IndentedSkipIntial<'i, T> = IndentedSkipInitial<'i, T>;
+
+impl<'i, T: Display> IndentedSkipInitial<'i, T> {
+    /// Wrap this object so that the formatting options of the [`Formatter`]
+    /// are applied to each non-empty line of the output, as with
+    /// [`Indented::padded`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use = "Indentables do nothing unless used"]
+    pub fn padded(self) -> Padded<Self> {
+        Padded { item: self }
+    }
+}
+
+impl<T: Display> Display for IndentedSkipInitial<'_, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             IndentWriter::new_skip_initial(self.indent, f),
@@ -92,3 +337,335 @@ impl<T: Display> Display for IndentedSkipIntial<'_, T> {
         )
     }
 }
+
+/// Wrapper struct that indents the [`Display`] representation of an item by
+/// the width of the [`Formatter`]. When printed with [`Display`], it will
+/// insert [`indent`][Self::indent], repeated as many times as the width,
+/// before each non-empty line of the underlying [`item`][Self::item]'s
+/// [`Display`] output.
+///
+/// The precision, fill, and alignment of the [`Formatter`] are ignored.
+///
+/// Created with [`Indentable::indented_by_width`] or
+/// [`Indentable::indented_by_width_with`]; see their documentation for
+/// examples.
+#[derive(Debug, Clone, Copy)]
+pub struct IndentedByWidth<'i, T: Display> {
+    /// The item to indent.
+    pub item: T,
+
+    /// The unit of indentation, which is repeated as many times as the width.
+    pub indent: Indent<'i>,
+}
+
+impl<T: Display> Display for IndentedByWidth<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let level = f.width().unwrap_or(0);
+        let mut writer = IndentWriter::builder(self.indent).level(level).build(f);
+        write!(writer, "{}", self.item)
+    }
+}
+
+/// Trait for types that write a list of fields as part of their [`Display`]
+/// representation. It's implemented by
+/// [`#[derive(IndentDisplay)]`][crate::IndentDisplay], and used to write the
+/// fields of a `#[indent(flatten)]` field as if they were fields of the
+/// containing type.
+pub trait IndentFields {
+    /// Write each field of this object to `dest`, each on its own line: a
+    /// newline, followed by the name of the field, `": "`, and its value.
+    fn fmt_fields(&self, dest: &mut dyn Write) -> fmt::Result;
+}
+
+/// Create a [`Display`] adapter that writes each item of `items`, with
+/// `separator` between them, inserting `indent` before each non-empty line
+/// of the items. An item's output may span several lines. The separator is
+/// written without indentation; use
+/// [`indent_separator`][JoinIndented::indent_separator] to indent it as well.
+/// Nothing is written if there are no items.
+///
+/// If an item's output ends with a newline, and the separator starts with
+/// one, the separator's newline is omitted, so that no blank line is written
+/// between the items.
+///
+/// The items are written directly through a single
+/// [`IndentWriter`], without allocating. `items` is
+/// cloned each time the adapter is printed, so it's usually a reference to a
+/// collection, or a cheaply cloneable iterator. Formatting options are
+/// ignored.
+///
+/// # Example:
+///
+/// ```
+/// use indent_write::indentable::join_indented;
+///
+/// let items = ["first", "multi\nline\n", "last"];
+///
+/// assert_eq!(
+///     format!("[\n{}\n]", join_indented(&items, ",\n", "  ")),
+///     "[\n  first,\n  multi\n  line\n,\n  last\n]",
+/// );
+/// assert_eq!(
+///     format!("{}", join_indented(&items, "\n---\n", "  ").indent_separator(true)),
+///     "  first\n  ---\n  multi\n  line\n  ---\n  last",
+/// );
+/// ```
+#[must_use = "Indentables do nothing unless used"]
+pub fn join_indented<'i, I>(items: I, separator: &'i str, indent: &'i str) -> JoinIndented<'i, I>
+where
+    I: IntoIterator + Clone,
+    I::Item: Display,
+{
+    JoinIndented {
+        items,
+        separator,
+        indent,
+        indent_separator: false,
+    }
+}
+
+/// Wrapper struct that writes each item of an iterator, separated, with each
+/// non-empty line of the items indented.
+///
+/// Created with [`join_indented`]; see its documentation for an example.
+#[derive(Debug, Clone, Copy)]
+pub struct JoinIndented<'i, I> {
+    /// The items to write.
+    pub items: I,
+
+    /// The separator to write between items.
+    pub separator: &'i str,
+
+    /// The indentation to insert before each non-empty line.
+    pub indent: &'i str,
+
+    /// If true, the separator is indented along with the items.
+    pub indent_separator: bool,
+}
+
+impl<I> JoinIndented<'_, I> {
+    /// Set whether the separator is indented along with the items. By
+    /// default, it isn't.
+    #[inline]
+    #[must_use]
+    pub fn indent_separator(self, indent_separator: bool) -> Self {
+        Self {
+            indent_separator,
+            ..self
+        }
+    }
+}
+
+impl<I> Display for JoinIndented<'_, I>
+where
+    I: IntoIterator + Clone,
+    I::Item: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut writer = IndentWriter::new(self.indent, f);
+
+        for (idx, item) in self.items.clone().into_iter().enumerate() {
+            if idx > 0 {
+                let mut separator = self.separator;
+
+                if writer.line() > 0 && writer.column() == 0 {
+                    separator = separator.strip_prefix('\n').unwrap_or(separator);
+                }
+
+                match self.indent_separator {
+                    true => writer.write_str(separator)?,
+                    false => writer.write_raw(separator)?,
+                }
+            }
+
+            write!(writer, "{}", item)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Wrapper struct that indents the [`Display`] representation of an item,
+/// formatting the item only once. The first time it's printed, the output of
+/// [`Indented`] is rendered into a [`String`], which is written again each
+/// time it's printed after that.
+///
+/// Formatting options are handled the same way as for [`Indented`]; use
+/// [`padded`][Self::padded] to apply them to each line. They aren't used
+/// when the output is rendered, so they may differ each time it's printed.
+///
+/// If the item returns an error while it's being rendered, nothing is
+/// written, and it will be rendered again the next time it's printed.
+///
+/// This type requires the `alloc` feature.
+///
+/// Created with [`Indentable::indented_once`]; see its documentation for an
+/// example.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct IndentedOnce<'i, T: Display> {
+    item: T,
+    indent: &'i str,
+    rendered: OnceCell<String>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'i, T: Display> IndentedOnce<'i, T> {
+    /// Get a reference to the item.
+    #[inline]
+    pub fn item(&self) -> &T {
+        &self.item
+    }
+
+    /// Get the indentation inserted before each non-empty line.
+    #[inline]
+    pub fn indent(&self) -> &'i str {
+        self.indent
+    }
+
+    /// Wrap this object so that the formatting options of the [`Formatter`]
+    /// are applied to each non-empty line of the output, as with
+    /// [`Indented::padded`].
+    #[inline]
+    #[must_use = "Indentables do nothing unless used"]
+    pub fn padded(self) -> Padded<Self> {
+        Padded { item: self }
+    }
+
+    /// Get the indented output, rendering it if it hasn't been rendered
+    /// yet. Formatting options aren't applied.
+    pub fn rendered(&self) -> Result<&str, fmt::Error> {
+        if let Some(rendered) = self.rendered.get() {
+            return Ok(rendered);
+        }
+
+        let mut rendered = String::new();
+        write!(
+            IndentWriter::new(self.indent, &mut rendered),
+            "{}",
+            self.item
+        )?;
+        Ok(self.rendered.get_or_init(|| rendered))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Display> Display for IndentedOnce<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.rendered()?)
+    }
+}
+
+/// Wrapper struct that indents the alternate [`Debug`] representation of an
+/// item. When printed with [`Display`] or [`Debug`], it will write the
+/// underlying [`item`][Self::item] with `{:#?}`, inserting
+/// [`indent`][Self::indent] before each non-empty line.
+///
+/// Formatting options are handled the same way as for [`Indented`]; use
+/// [`padded`][Self::padded] to apply them to each line.
+///
+/// Created with [`IndentableDebug::indented_debug`]; see its documentation
+/// for an example.
+#[derive(Clone, Copy)]
+pub struct IndentedDebug<'i, T: Debug> {
+    /// The item to indent.
+    pub item: T,
+
+    /// The indentation to insert before each non-empty line.
+    pub indent: &'i str,
+}
+
+impl<'i, T: Debug> IndentedDebug<'i, T> {
+    /// Wrap this object so that the formatting options of the [`Formatter`]
+    /// are applied to each non-empty line of the output, as with
+    /// [`Indented::padded`].
+    ///
+    /// This method requires the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use = "Indentables do nothing unless used"]
+    pub fn padded(self) -> Padded<Self> {
+        Padded { item: self }
+    }
+}
+
+impl<T: Debug> Display for IndentedDebug<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(IndentWriter::new(self.indent, f), "{:#?}", self.item)
+    }
+}
+
+impl<T: Debug> Debug for IndentedDebug<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// Wrapper struct that applies the [`Formatter`]'s width, fill, alignment,
+/// and precision to each non-empty line of the [`Display`] representation of
+/// an item, rather than to the output as a whole. Each line is padded or
+/// truncated as if it were written with [`Formatter::pad`]; newlines are
+/// written unchanged.
+///
+/// This type requires the `alloc` feature, since each line is buffered
+/// before it's written.
+///
+/// Created with `padded` on [`Indented`], [`IndentedSkipInitial`],
+/// [`IndentedOnce`], or [`IndentedDebug`]; see [`Indented::padded`] for an
+/// example.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy)]
+pub struct Padded<T: Display> {
+    /// The item to pad.
+    pub item: T,
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Display> Display for Padded<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.width().is_none() && f.precision().is_none() {
+            return write!(f, "{}", self.item);
+        }
+
+        let mut lines = PadLines {
+            f,
+            line: String::new(),
+        };
+        write!(lines, "{}", self.item)?;
+        lines.end_line()
+    }
+}
+
+/// Writer that buffers each line, then writes it with [`Formatter::pad`].
+#[cfg(feature = "alloc")]
+struct PadLines<'a, 'f> {
+    f: &'a mut Formatter<'f>,
+    line: String,
+}
+
+#[cfg(feature = "alloc")]
+impl PadLines<'_, '_> {
+    fn end_line(&mut self) -> fmt::Result {
+        if !self.line.is_empty() {
+            self.f.pad(&self.line)?;
+            self.line.clear();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for PadLines<'_, '_> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        while let Some(idx) = s.find('\n') {
+            self.line.push_str(&s[..idx]);
+            self.end_line()?;
+            self.f.write_char('\n')?;
+            s = &s[idx + 1..];
+        }
+
+        self.line.push_str(s);
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
-use std::io;
-
-use super::Inspect;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write as _};
+use std::str;
 
 #[derive(Debug, Copy, Clone)]
-enum IndentState<'a> {
+enum IndentState {
     // We are currently writing a line. Forward writes until the end of the
     // line.
     MidLine,
@@ -12,20 +14,67 @@ enum IndentState<'a> {
     // before the next non empty line.
     NeedIndent,
 
-    // We are currently writing an indent.
-    WritingIndent(&'a [u8]),
+    // We are currently writing an indent. The first `n` bytes of the indent
+    // have already been written.
+    WritingIndent(usize),
 }
 
 use IndentState::*;
 
+mod context;
+mod copy;
+mod dynamic;
+mod fmt_write;
+mod line_buffer;
+mod line_ending;
+mod lossy;
+mod numbered;
+mod reader;
+
+pub use context::IndentError;
+pub use copy::{copy_indented, copy_indented_buf};
+pub use dynamic::DynamicIndentWriter;
+pub use line_ending::LineEnding;
+pub use numbered::NumberedWriter;
+pub use reader::IndentReader;
+
 /// Adapter for writers to indent each line
 ///
 /// An `IndentWriter` adapts an [`io::Write`] object to insert an indent before
 /// each non-empty line. Specifically, this means it will insert an indent
 /// between each newline when followed by a non-newline.
 ///
+/// Written content is treated purely as bytes: it isn't required to be valid
+/// UTF-8, and it's never buffered, so arbitrary binary data is written
+/// unchanged, apart from the indent inserted after each `b'\n'`. Use
+/// [`lossy_utf8`][Self::lossy_utf8] to replace invalid UTF-8 instead.
+///
+/// Lines ending with `\r\n` are supported: a line containing only `\r\n` is
+/// empty, and isn't indented. Any `\r` at the start of a line is written
+/// before the indent.
+///
 /// These writers can be nested to provide increasing levels of indentation.
 ///
+/// If the inner writer returns an error or panics, the `IndentWriter` is left
+/// in a consistent state. Content is only counted as written, and the
+/// writer's state is only updated, once the inner writer has returned, so
+/// retrying a write after an error, or after catching a panic, doesn't
+/// duplicate or lose any content or any part of an indent. Anything the
+/// inner writer did before it panicked is unknown, so it's treated as if it
+/// wrote nothing.
+///
+/// This includes [`WouldBlock`][io::ErrorKind::WouldBlock] errors from
+/// non-blocking writers. When [`write`][io::Write::write] returns an error,
+/// none of the buffer was written, though part of an indent, or output for
+/// content accepted by an earlier write, may have been; the write should be
+/// retried with the same buffer. When it returns `Ok(n)`, it should be
+/// retried with the rest of the buffer after the first `n` bytes. The same
+/// goes for [`write_vectored`][io::Write::write_vectored] and
+/// [`flush`][io::Write::flush]. As with any writer,
+/// [`write_all`][io::Write::write_all] and [`write!`] don't report how much
+/// was written if they fail, so they aren't suitable for non-blocking
+/// writers.
+///
 /// # Example
 ///
 /// ```
@@ -46,18 +95,96 @@ use IndentState::*;
 /// ```
 #[derive(Debug, Clone)]
 pub struct IndentWriter<'i, W> {
-    writer: W,
-    indent: &'i str,
-    state: IndentState<'i>,
+    writer: line_buffer::Output<W>,
+    // The indent isn't necessarily valid UTF-8, if it was created with
+    // `new_bytes`
+    indent: Cow<'i, [u8]>,
+    state: IndentState,
+
+    // An indent passed to `set_indent` while the current indent was only
+    // partly written. It replaces the current indent once that's finished.
+    next_indent: Option<Cow<'i, [u8]>>,
+
+    // If we're replacing invalid UTF-8, the state of the replacement
+    lossy: Option<lossy::Lossy>,
+
+    // The indent for the first line of each record, if it's different
+    first_line_prefix: Option<Cow<'i, [u8]>>,
+
+    // True if the next line is the first line of a record, and true if the
+    // indent being written is the first line prefix
+    first_line_next: bool,
+    writing_first_line: bool,
+
+    // The line ending to write for each newline, and the state of the
+    // translation
+    line_ending: line_ending::LineEndingState,
+
+    // A buffer for rendering `write!` arguments, which is kept to be reused
+    fmt_buffer: Vec<u8>,
+
+    // The last error from the inner writer returned by `fmt::Write`
+    error: fmt_write::StashedError,
+
+    // True if errors from the inner writer should have the output position
+    // attached
+    error_context: bool,
 }
 
 impl<'i, W: io::Write> IndentWriter<'i, W> {
-    /// Create a new [`IndentWriter`].
-    pub fn new(indent: &'i str, writer: W) -> Self {
+    /// Create a new [`IndentWriter`]. The indent can be borrowed, or owned,
+    /// such as a `String` built at runtime.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// fn logger(name: &str) -> IndentWriter<'static, Vec<u8>> {
+    ///     IndentWriter::new(format!("{}> ", name), Vec::new())
+    /// }
+    ///
+    /// let mut writer = logger("db");
+    /// writeln!(writer, "connected\nready").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"db> connected\ndb> ready\n");
+    /// ```
+    #[inline]
+    pub fn new(indent: impl Into<Cow<'i, str>>, writer: W) -> Self {
+        Self::new_bytes(str_bytes(indent.into()), writer)
+    }
+
+    /// Create a new [`IndentWriter`] with an indent made of arbitrary bytes,
+    /// which don't need to be valid UTF-8, such as raw terminal escape
+    /// sequences.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new_bytes(b"\xFF| ", Vec::new());
+    /// writeln!(writer, "Line 1\nLine 2").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"\xFF| Line 1\n\xFF| Line 2\n");
+    /// ```
+    #[inline]
+    pub fn new_bytes(indent: impl Into<Cow<'i, [u8]>>, writer: W) -> Self {
         Self {
-            writer,
-            indent,
+            writer: line_buffer::Output::new(writer),
+            indent: indent.into(),
             state: NeedIndent,
+            next_indent: None,
+            lossy: None,
+            first_line_prefix: None,
+            first_line_next: false,
+            writing_first_line: false,
+            line_ending: Default::default(),
+            fmt_buffer: Vec::new(),
+            error: Default::default(),
+            error_context: false,
         }
     }
 
@@ -80,42 +207,594 @@ impl<'i, W: io::Write> IndentWriter<'i, W> {
     /// assert_eq!(buffer, b"Line 1\n    Line 2\n    Line 3\n")
     /// ```
     #[inline]
-    pub fn new_skip_initial(indent: &'i str, writer: W) -> Self {
+    pub fn new_skip_initial(indent: impl Into<Cow<'i, str>>, writer: W) -> Self {
         Self {
-            writer,
-            indent,
             state: MidLine,
+            ..Self::new(indent, writer)
         }
     }
 
-    /// Extract the writer from the [`IndentWriter`], discarding any in-progress
-    /// indent state.
+    /// Create a new [`IndentWriter`] which writes `first_line_prefix` before
+    /// the first non-empty line of each record, and `indent` before every
+    /// other non-empty line, for a hanging indent. The output starts with a
+    /// new record; use [`start_record`][Self::start_record] to start another
+    /// one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::with_first_line_prefix("* ", "  ", Vec::new());
+    ///
+    /// write!(writer, "first\nmessage\n").unwrap();
+    /// writer.start_record();
+    /// write!(writer, "\nsecond\nmessage\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"* first\n  message\n\n* second\n  message\n");
+    /// ```
     #[inline]
-    pub fn into_inner(self) -> W {
-        self.writer
+    pub fn with_first_line_prefix(
+        first_line_prefix: impl Into<Cow<'i, str>>,
+        indent: impl Into<Cow<'i, str>>,
+        writer: W,
+    ) -> Self {
+        Self {
+            first_line_prefix: Some(str_bytes(first_line_prefix.into())),
+            first_line_next: true,
+            ..Self::new(indent, writer)
+        }
+    }
+
+    /// Create a new [`IndentWriter`] which doesn't indent the first line of
+    /// each record, and indents every other non-empty line. This is like
+    /// [`new_skip_initial`][Self::new_skip_initial], but the first line of
+    /// each new record started with [`start_record`][Self::start_record] is
+    /// also skipped. It's the same as a
+    /// [`with_first_line_prefix`][Self::with_first_line_prefix] writer with
+    /// an empty first line prefix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new_hanging("  ", Vec::new());
+    ///
+    /// write!(writer, "HEADER: one\ntwo\n").unwrap();
+    /// writer.start_record();
+    /// write!(writer, "HEADER: three\nfour\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"HEADER: one\n  two\nHEADER: three\n  four\n");
+    /// ```
+    #[inline]
+    pub fn new_hanging(indent: impl Into<Cow<'i, str>>, writer: W) -> Self {
+        Self::with_first_line_prefix("", indent, writer)
+    }
+
+    /// Start a new record, so that the next non-empty line is written with
+    /// the first line prefix from
+    /// [`with_first_line_prefix`][Self::with_first_line_prefix], instead of
+    /// the indent. If the current line hasn't been started yet, it's the
+    /// first line of the new record. If an indent was only partly written,
+    /// the rest of it is still written, and the next line starts the record.
+    /// This has no effect if there is no first line prefix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::with_first_line_prefix("", "    ", Vec::new());
+    ///
+    /// // Each record's first line continues a header written elsewhere
+    /// for (header, message) in [("E1: ", "bad\ninput\n"), ("E2: ", "no\nfile\n")] {
+    ///     writer.get_mut().extend_from_slice(header.as_bytes());
+    ///     writer.start_record();
+    ///     write!(writer, "{}", message).unwrap();
+    /// }
+    ///
+    /// assert_eq!(writer.get_ref(), b"E1: bad\n    input\nE2: no\n    file\n");
+    /// ```
+    pub fn start_record(&mut self) {
+        if self.first_line_prefix.is_none() {
+            return;
+        }
+
+        match self.state {
+            WritingIndent(0) => self.writing_first_line = true,
+            _ => self.first_line_next = true,
+        }
+    }
+
+    /// Extract the writer from the [`IndentWriter`]. If an indent was only
+    /// partly written, the rest of it is written first, the same as with
+    /// [`flush`][io::Write::flush], though the inner writer itself isn't
+    /// flushed. If that fails, the error is returned along with this
+    /// `IndentWriter`, so that no part of the indent is lost.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new());
+    /// writeln!(writer, "Line 1").unwrap();
+    ///
+    /// let buffer = writer.into_inner().unwrap();
+    /// assert_eq!(buffer, b"  Line 1\n");
+    /// ```
+    // The error carries the writer back, the same as `BufWriter::into_inner`
+    #[allow(clippy::result_large_err)]
+    pub fn into_inner(mut self) -> Result<W, IntoInnerError<Self>> {
+        match self.finish_pending() {
+            Ok(()) => Ok(self.writer.writer),
+            Err(error) => Err(IntoInnerError {
+                writer: self,
+                error,
+            }),
+        }
+    }
+
+    /// Finish writing to the [`IndentWriter`], and extract the writer. This
+    /// is the same as [`into_inner`][Self::into_inner], but the inner
+    /// writer is also flushed. If any of that fails, the error is returned
+    /// along with this `IndentWriter`, so that it can be retried.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", std::io::BufWriter::new(Vec::new()));
+    /// writeln!(writer, "Line 1").unwrap();
+    ///
+    /// let buffer = writer.finish().unwrap();
+    /// assert_eq!(buffer.get_ref(), b"  Line 1\n");
+    /// ```
+    // The error carries the writer back, the same as `BufWriter::into_inner`
+    #[allow(clippy::result_large_err)]
+    pub fn finish(mut self) -> Result<W, IntoInnerError<Self>> {
+        match self.finish_pending().and_then(|()| self.writer.flush()) {
+            Ok(()) => Ok(self.writer.writer),
+            Err(error) => Err(IntoInnerError {
+                writer: self,
+                error,
+            }),
+        }
+    }
+
+    /// Extract the writer from the [`IndentWriter`], discarding any
+    /// in-progress indent state, including any part of an indent that
+    /// hasn't been written yet.
+    #[inline]
+    pub fn into_inner_unflushed(self) -> W {
+        self.writer.writer
+    }
+
+    /// Extract the writer from the [`IndentWriter`] without writing
+    /// anything to it, along with the output that
+    /// [`into_inner`][Self::into_inner] would have written. This includes
+    /// the rest of any partly written indent, and any content that was
+    /// accepted but not yet written, such as the `\n` of a translated line
+    /// ending, or an incomplete UTF-8 sequence in lossy mode (which is
+    /// replaced).
+    ///
+    /// This is useful if the inner writer is failing, so that the pending
+    /// output can be saved or logged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new()).lossy_utf8(true);
+    /// writer.write_all(b"a\n\xF0\x9F").unwrap();
+    ///
+    /// let (buffer, pending) = writer.into_parts();
+    /// assert_eq!(buffer, b"  a\n");
+    /// assert_eq!(String::from_utf8(pending).unwrap(), "  \u{FFFD}");
+    /// ```
+    pub fn into_parts(mut self) -> (W, Vec<u8>) {
+        let pending = self.clear_pending();
+        (self.writer.writer, pending)
+    }
+
+    /// Discard the output that this writer is holding, which hasn't been
+    /// written to the inner writer yet, and return it. This is the same
+    /// output as for [`into_parts`][Self::into_parts]. The writer's state
+    /// is updated as if the output was written, so if it's written to the
+    /// inner writer before writing anything else, the result is the same as
+    /// if it was never cleared.
+    ///
+    /// This is useful to recover from an error in the inner writer, such as
+    /// by replacing it with [`replace_writer`][Self::replace_writer]. To
+    /// start again from a new line instead, use [`reset`][Self::reset].
+    pub fn clear_pending(&mut self) -> Vec<u8> {
+        let mut pending = IndentWriter {
+            writer: self.writer.take_buffer(Vec::new()),
+            indent: core::mem::take(&mut self.indent),
+            state: self.state,
+            next_indent: self.next_indent.take(),
+            lossy: self.lossy.take(),
+            first_line_prefix: self.first_line_prefix.take(),
+            first_line_next: self.first_line_next,
+            writing_first_line: self.writing_first_line,
+            line_ending: self.line_ending,
+            fmt_buffer: Vec::new(),
+            error: Default::default(),
+            error_context: false,
+        };
+
+        pending
+            .finish_pending()
+            .expect("writing to a Vec can't fail");
+
+        self.indent = pending.indent;
+        self.state = pending.state;
+        self.next_indent = pending.next_indent;
+        self.lossy = pending.lossy;
+        self.first_line_prefix = pending.first_line_prefix;
+        self.first_line_next = pending.first_line_next;
+        self.writing_first_line = pending.writing_first_line;
+        self.line_ending = pending.line_ending;
+
+        pending.writer.writer
+    }
+
+    /// Discard the output that this writer is holding, and start again as
+    /// if it were a new writer, so that the next non-empty line is indented
+    /// (and starts a new record, if there's a
+    /// [first line prefix][Self::with_first_line_prefix]). If this is called
+    /// in the middle of a line, the rest of the line is indented as if it
+    /// were a new line.
+    ///
+    /// This is useful to recover from an error in the inner writer, such as
+    /// by replacing it with [`replace_writer`][Self::replace_writer]. Use
+    /// [`clear_pending`][Self::clear_pending] to get the discarded output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new());
+    /// write!(writer, "Line 1\nLine").unwrap();
+    ///
+    /// writer.reset();
+    /// let old = writer.replace_writer(Vec::new());
+    /// write!(writer, "Line 2").unwrap();
+    ///
+    /// assert_eq!(old, b"  Line 1\n  Line");
+    /// assert_eq!(writer.get_ref(), b"  Line 2");
+    /// ```
+    pub fn reset(&mut self) {
+        let _ = self.clear_pending();
+
+        self.state = NeedIndent;
+        self.writing_first_line = false;
+        self.first_line_next = self.first_line_prefix.is_some();
+        self.line_ending.reset();
+        self.writer.position = Default::default();
+    }
+
+    /// Replace the inner writer, and return the old one. Any output that
+    /// this writer is holding is written to the new writer; use
+    /// [`reset`][Self::reset] or [`clear_pending`][Self::clear_pending]
+    /// first to discard it.
+    #[inline]
+    pub fn replace_writer(&mut self, writer: W) -> W {
+        core::mem::replace(&mut self.writer.writer, writer)
     }
 
     /// Get a reference to the wrapped writer
     #[inline]
     pub fn get_ref(&self) -> &W {
-        &self.writer
+        &self.writer.writer
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the indentation
+    /// logic. If an indent was only partly written, the rest of it will be
+    /// written before the next content written through the `IndentWriter`.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer.writer
+    }
+
+    /// Get the string being used as an indent for each line, or `None` if it
+    /// isn't valid UTF-8. If the indent was changed with
+    /// [`set_indent`][Self::set_indent], this is the new indent, even if the
+    /// old one is still being written.
+    #[inline]
+    pub fn indent(&self) -> Option<&str> {
+        str::from_utf8(self.indent_bytes()).ok()
+    }
+
+    /// Get the bytes being used as an indent for each line. If the indent
+    /// was changed with [`set_indent`][Self::set_indent], this is the new
+    /// indent, even if the old one is still being written.
+    #[inline]
+    pub fn indent_bytes(&self) -> &[u8] {
+        self.next_indent.as_deref().unwrap_or(&self.indent)
+    }
+
+    /// Change the string used as an indent for each line. The new indent is
+    /// used starting with the next line that's indented. If an indent was
+    /// only partly written, because the inner writer accepted only some of
+    /// it, the rest of the old indent is still written, so that no line gets
+    /// a mix of the two.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new());
+    /// write!(writer, "Line 1\nLine ").unwrap();
+    ///
+    /// writer.set_indent("> ");
+    /// write!(writer, "2\nLine 3\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"  Line 1\n  Line 2\n> Line 3\n");
+    /// ```
+    #[inline]
+    pub fn set_indent(&mut self, indent: impl Into<Cow<'i, str>>) {
+        self.set_indent_bytes(str_bytes(indent.into()))
+    }
+
+    /// Change the bytes used as an indent for each line, which don't need to
+    /// be valid UTF-8. See [`set_indent`][Self::set_indent] and
+    /// [`new_bytes`][Self::new_bytes].
+    pub fn set_indent_bytes(&mut self, indent: impl Into<Cow<'i, [u8]>>) {
+        match self.state {
+            WritingIndent(written) if written > 0 => self.next_indent = Some(indent.into()),
+            _ => {
+                self.indent = indent.into();
+                self.next_indent = None;
+            }
+        }
+    }
+
+    /// Check if this writer is holding any output that hasn't been written
+    /// to the inner writer yet; that is, if
+    /// [`pending_bytes`][Self::pending_bytes] is more than 0. If it isn't,
+    /// it's safe to write to the inner writer directly.
+    #[inline]
+    pub fn has_pending(&self) -> bool {
+        self.pending_bytes() > 0
+    }
+
+    /// Get the number of bytes that this writer is holding, which haven't
+    /// been written to the inner writer yet. This is the sum of:
+    ///
+    /// - The rest of an indent that was only partly written.
+    /// - The `\n` of a translated line ending, if only the `\r` was
+    ///   written. See [`line_ending`][Self::line_ending].
+    /// - Any content that was accepted but not yet written in lossy mode,
+    ///   including an incomplete UTF-8 sequence. See
+    ///   [`lossy_utf8`][Self::lossy_utf8].
+    ///
+    /// It doesn't include an indent that hasn't been started yet; see
+    /// [`needs_indent`][Self::needs_indent].
+    pub fn pending_bytes(&self) -> usize {
+        let indent = match self.state {
+            WritingIndent(written) => self.current_indent().len() - written,
+            _ => 0,
+        };
+
+        let lossy = self.lossy.as_ref().map_or(0, |lossy| lossy.pending_len());
+
+        indent + self.line_ending.pending_len() + lossy + self.writer.pending_len()
+    }
+
+    /// Check if an indent will be written before the next non-empty line,
+    /// because the last content written ended with a newline, or because
+    /// an indent was partly written.
+    #[inline]
+    pub fn needs_indent(&self) -> bool {
+        !matches!(self.state, MidLine)
+    }
+
+    /// Write any output that this writer is holding, without flushing the
+    /// inner writer. This writes the rest of any partly written indent, and
+    /// anything accepted but not written yet, except for an incomplete
+    /// UTF-8 sequence in lossy mode, which is held until it's completed;
+    /// see [`pending_bytes`][Self::pending_bytes].
+    /// [`flush`][io::Write::flush] is the same, but also flushes the inner
+    /// writer.
+    pub fn flush_pending(&mut self) -> io::Result<()> {
+        self.write_pending_lf()?;
+        self.write_lossy_pending()?;
+        self.write_unwritten_indent()?;
+        self.writer.write_buffer()
+    }
+}
+
+impl<'i> IndentWriter<'i, io::StdoutLock<'static>> {
+    /// Create a new [`IndentWriter`] that writes to a lock of the standard
+    /// output. The lock is held until the writer is dropped, and it isn't
+    /// tied to a borrow of [`io::stdout`], so the writer can be stored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut out = IndentWriter::stdout("  ");
+    /// writeln!(out, "Section 1:").unwrap();
+    ///
+    /// let mut section = IndentWriter::new("- ", &mut out);
+    /// writeln!(section, "Item 1\nItem 2").unwrap();
+    /// ```
+    #[inline]
+    pub fn stdout(indent: impl Into<Cow<'i, str>>) -> Self {
+        Self::new(indent, io::stdout().lock())
     }
+}
 
-    /// Get the string being used as an indent for each line
+impl<'i> IndentWriter<'i, io::StderrLock<'static>> {
+    /// Create a new [`IndentWriter`] that writes to a lock of the standard
+    /// error. The lock is held until the writer is dropped, and it isn't
+    /// tied to a borrow of [`io::stderr`], so the writer can be stored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// struct Logger {
+    ///     errors: IndentWriter<'static, std::io::StderrLock<'static>>,
+    /// }
+    ///
+    /// let mut logger = Logger {
+    ///     errors: IndentWriter::stderr("error: "),
+    /// };
+    /// writeln!(logger.errors, "something went wrong").unwrap();
+    /// ```
     #[inline]
-    pub fn indent(&self) -> &'i str {
-        self.indent
+    pub fn stderr(indent: impl Into<Cow<'i, str>>) -> Self {
+        Self::new(indent, io::stderr().lock())
     }
 }
 
 impl<'i, W: io::Write> io::Write for IndentWriter<'i, W> {
+    /// Write some of `buf`, inserting indents as needed. If the inner writer
+    /// can't write anything, this returns a
+    /// [`WriteZero`][io::ErrorKind::WriteZero] error, rather than `Ok(0)`.
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = match self.lossy {
+            Some(_) => self.write_lossy(buf),
+            None => self.write_indented(buf),
+        };
+
+        self.with_context(result.and_then(|n| check_write_zero(n, buf)))
+    }
+
+    /// Write the first non-empty buffer in `bufs`. If an indent is about to
+    /// be written, it's written along with the rest of the line in a single
+    /// vectored write to the inner writer.
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let buf = bufs
+            .iter()
+            .find(|buf| !buf.is_empty())
+            .map_or(&[][..], |buf| &buf[..]);
+
+        match (&self.lossy, self.line_ending.line_ending) {
+            (None, LineEnding::Lf) => {
+                let result = self.write_vectored_indented(buf);
+                self.with_context(result.and_then(|n| check_write_zero(n, buf)))
+            }
+            _ => self.write(buf),
+        }
+    }
+
+    /// Write all of `buf`. Each run of content between indents is written
+    /// with a single call to the inner writer's `write_all`.
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let result = self.write_all_indented(buf);
+        self.with_context(result)
+    }
+
+    /// Render `args` into an internal buffer, and then write it all at once
+    /// with [`write_all`][io::Write::write_all]. If formatting fails,
+    /// nothing is written.
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        if let Some(s) = args.as_str() {
+            return self.write_all(s.as_bytes());
+        }
+
+        let mut buffer = core::mem::take(&mut self.fmt_buffer);
+        buffer.clear();
+
+        let result = buffer
+            .write_fmt(args)
+            .and_then(|()| self.write_all(&buffer));
+
+        self.fmt_buffer = buffer;
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.flush_pending().and_then(|()| self.writer.flush());
+        self.with_context(result)
+    }
+}
+
+impl<'i, W: io::Write> IndentWriter<'i, W> {
+    /// Write all of `buf`, for [`write_all`][io::Write::write_all].
+    fn write_all_indented(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        if self.lossy.is_some() || self.line_ending.line_ending != LineEnding::Lf {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                    Ok(n) => buf = &buf[n..],
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            return Ok(());
+        }
+
+        while !buf.is_empty() {
+            self.write_unwritten_indent()?;
+
+            // Scan for the start of the next non-empty line
+            let mut need_indent = matches!(self.state, NeedIndent);
+            let next_line = buf.iter().position(|&b| {
+                if b == b'\n' {
+                    need_indent = true;
+                    false
+                } else {
+                    need_indent && b != b'\r'
+                }
+            });
+
+            match next_line {
+                // Write the rest of the buffer
+                None => {
+                    self.writer.write_all(buf)?;
+                    self.state = match need_indent {
+                        true => NeedIndent,
+                        false => MidLine,
+                    };
+                    return Ok(());
+                }
+
+                // Write the content before the next non-empty line, then its
+                // indent
+                Some(len) => {
+                    self.writer.write_all(&buf[..len])?;
+                    self.begin_indent();
+                    buf = &buf[len..];
+                }
+            }
+        }
+
+        Ok(())
+    }
+    /// Write some of `buf`, inserting indents as needed.
+    fn write_indented(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pending_lf()?;
+
         loop {
             match self.state {
                 // We're currently writing a line. Scan for the end of the line.
                 IndentState::MidLine => match buf.iter().position(|&b| b == b'\n') {
                     // No newlines in the input buffer, so write the entire thing.
-                    None => break self.writer.write(buf),
+                    None => break self.write_content(buf),
 
                     // We are at a newline presently. Request an indent be
                     // written at the front of the next non-empty line, then
@@ -128,7 +807,7 @@ impl<'i, W: io::Write> io::Write for IndentWriter<'i, W> {
                     // written, request an indent on the subsequent call to
                     // write.
                     Some(len) => {
-                        break self.writer.write(&buf[..len + 1]).inspect(|&n| {
+                        break self.write_content(&buf[..len + 1]).inspect(|&n| {
                             if n >= len {
                                 self.state = NeedIndent;
                             }
@@ -136,24 +815,27 @@ impl<'i, W: io::Write> io::Write for IndentWriter<'i, W> {
                     }
                 },
 
-                // We need an indent. Scan for the next non-empty line.
-                IndentState::NeedIndent => match buf.iter().position(|&b| b != b'\n') {
+                // We need an indent. Scan for the next non-empty line. A
+                // `\r` at the start of a line is written before the indent,
+                // so that a `\r\n` line is empty, even if the `\r` and the
+                // `\n` are written separately.
+                IndentState::NeedIndent => match buf.iter().position(|&b| !is_line_break(b)) {
                     // No non-empty lines in the input buffer, so write the entire thing
-                    None => break self.writer.write(buf),
+                    None => break self.write_content(buf),
 
                     // We are at the beginning of a non-empty line presently.
                     // Begin inserting an indent now, then continue looping
                     // (since we haven't yet attempted to write user data)
-                    Some(0) => self.state = WritingIndent(self.indent.as_bytes()),
+                    Some(0) => self.begin_indent(),
 
                     // There's an upcoming non-empty line. Write out the
                     // remainder of the empty lines. If all the empty lines
                     // were written, force an indent on the subsequent call to
                     // write.
                     Some(len) => {
-                        break self.writer.write(&buf[..len]).inspect(|&n| {
+                        break self.write_content(&buf[..len]).inspect(|&n| {
                             if n >= len {
-                                self.state = IndentState::WritingIndent(self.indent.as_bytes())
+                                self.begin_indent()
                             }
                         })
                     }
@@ -162,39 +844,236 @@ impl<'i, W: io::Write> io::Write for IndentWriter<'i, W> {
                 // We are writing an indent unconditionally. If we're in this
                 // state, the input buffer is known to be the start of a non-
                 // empty line.
-                IndentState::WritingIndent(indent) => match self.writer.write(indent)? {
+                IndentState::WritingIndent(written) => match self.write_indent(written)? {
                     // We successfully wrote the entire indent. Continue with
                     // writing the input buffer.
-                    n if n >= indent.len() => self.state = MidLine,
+                    None => self.end_indent(),
 
                     // Eof; stop work immediately
-                    0 => break Ok(0),
+                    Some(0) => break Ok(0),
 
                     // Only a part of the indent was written. Continue
                     // trying to write the rest of it, but update our state
                     // to keep it consistent in case the next write is an
                     // error
-                    n => self.state = WritingIndent(&indent[n..]),
+                    Some(n) => self.state = WritingIndent(written + n),
                 },
             }
         }
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        // If we're currently in the middle of writing an indent, flush it
-        while let WritingIndent(ref mut indent) = self.state {
-            match self.writer.write(*indent)? {
-                // We wrote the entire indent. Proceed with the flush
-                len if len >= indent.len() => self.state = MidLine,
+    /// Write some of `buf`, inserting indents as needed. The indent is
+    /// written along with the rest of its line with a vectored write.
+    fn write_vectored_indented(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pending_lf()?;
+
+        loop {
+            let written = match self.state {
+                WritingIndent(written) if !buf.is_empty() => written,
+                NeedIndent if self.at_indent_start(buf) => {
+                    self.begin_indent();
+                    continue;
+                }
+                _ => return self.write_indented(buf),
+            };
+
+            // The rest of the line, including its newline
+            let newline = buf.iter().position(|&b| b == b'\n');
+            let line = match newline {
+                Some(len) => &buf[..len + 1],
+                None => buf,
+            };
+
+            let indent = match self.writing_first_line {
+                true => self.first_line_prefix.as_deref().unwrap_or(&self.indent),
+                false => &self.indent,
+            };
+            let indent = &indent[written..];
+
+            let n = self
+                .writer
+                .write_vectored(&[io::IoSlice::new(indent), io::IoSlice::new(line)])?;
+
+            match n.checked_sub(indent.len()) {
+                // Eof; stop work immediately
+                None if n == 0 => return Ok(0),
+
+                // Only a part of the indent was written. Continue trying to
+                // write the rest of it.
+                None => self.state = WritingIndent(written + n),
+
+                // The indent was entirely written, but none of the line was.
+                // Continue with writing the line.
+                Some(0) => self.end_indent(),
+
+                // Some of the line was written. If the entire line was
+                // written, request an indent on the subsequent call to write.
+                Some(n) => {
+                    self.end_indent();
+                    if newline.is_some_and(|len| n >= len) {
+                        self.state = NeedIndent;
+                    }
+                    return Ok(n);
+                }
+            }
+        }
+    }
+
+    /// Check if the next write will start writing a new indent, before
+    /// writing any of `buf`.
+    pub(super) fn at_indent_start(&self, buf: &[u8]) -> bool {
+        match self.state {
+            WritingIndent(written) => written == 0,
+            NeedIndent => buf.first().is_some_and(|&b| !is_line_break(b)),
+            MidLine => false,
+        }
+    }
+
+    /// Get the indent for the current line.
+    fn current_indent(&self) -> &[u8] {
+        match self.writing_first_line {
+            true => self.first_line_prefix.as_deref().unwrap_or(&self.indent),
+            false => &self.indent,
+        }
+    }
+
+    /// Write everything that's been accepted but not yet written, replacing
+    /// any incomplete UTF-8 sequence in lossy mode, and finish writing any
+    /// partly written indent.
+    fn finish_pending(&mut self) -> io::Result<()> {
+        self.write_pending_lf()?;
+        self.finish_lossy()?;
+        self.write_unwritten_indent()?;
+        self.writer.write_buffer()
+    }
+
+    /// If we're currently in the middle of writing an indent, finish writing
+    /// it.
+    fn write_unwritten_indent(&mut self) -> io::Result<()> {
+        while let WritingIndent(written) = self.state {
+            match self.write_indent(written)? {
+                // We wrote the entire indent
+                None => self.end_indent(),
 
                 // EoF; return an error
-                0 => return Err(io::ErrorKind::WriteZero.into()),
+                Some(0) => return Err(io::ErrorKind::WriteZero.into()),
 
                 // Partial write, continue writing.
-                len => *indent = &indent[len..],
+                Some(n) => self.state = WritingIndent(written + n),
             }
         }
 
-        self.writer.flush()
+        Ok(())
+    }
+
+    /// Write the rest of the indent, after the first `written` bytes. Returns
+    /// `None` if it was entirely written, or the number of bytes written if
+    /// it was only partly written.
+    fn write_indent(&mut self, written: usize) -> io::Result<Option<usize>> {
+        let indent = match self.writing_first_line {
+            true => self.first_line_prefix.as_deref().unwrap_or(&self.indent),
+            false => &self.indent,
+        };
+        let indent = &indent[written..];
+
+        self.writer
+            .write(indent)
+            .map(|n| if n >= indent.len() { None } else { Some(n) })
+    }
+
+    /// Start writing the indent for a new line.
+    fn begin_indent(&mut self) {
+        self.state = WritingIndent(0);
+        self.writing_first_line = core::mem::take(&mut self.first_line_next);
+    }
+
+    /// The indent has been entirely written.
+    fn end_indent(&mut self) {
+        self.state = MidLine;
+        self.writing_first_line = false;
+
+        if let Some(indent) = self.next_indent.take() {
+            self.indent = indent;
+        }
+    }
+}
+
+/// Return a [`WriteZero`][io::ErrorKind::WriteZero] error if none of a
+/// non-empty `buf` was written.
+#[inline]
+fn check_write_zero(written: usize, buf: &[u8]) -> io::Result<usize> {
+    match written {
+        0 if !buf.is_empty() => Err(io::ErrorKind::WriteZero.into()),
+        n => Ok(n),
+    }
+}
+
+/// Check if `b` is part of a line ending, rather than the content of a line
+#[inline]
+fn is_line_break(b: u8) -> bool {
+    b == b'\n' || b == b'\r'
+}
+
+fn str_bytes(s: Cow<'_, str>) -> Cow<'_, [u8]> {
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+/// The error returned by [`IndentWriter::into_inner`], when the rest of a
+/// partly written indent couldn't be written. It contains the error, and the
+/// [`IndentWriter`], so that it can be retried, or its writer recovered with
+/// [`IndentWriter::into_inner_unflushed`].
+#[derive(Debug)]
+pub struct IntoInnerError<W> {
+    writer: W,
+    error: io::Error,
+}
+
+impl<W> IntoInnerError<W> {
+    /// Get the error that caused [`IndentWriter::into_inner`] to fail.
+    #[inline]
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Get the writer, which can be used to retry
+    /// [`IndentWriter::into_inner`].
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Get the error, discarding the writer.
+    #[inline]
+    pub fn into_error(self) -> io::Error {
+        self.error
+    }
+
+    /// Get the error and the writer.
+    #[inline]
+    pub fn into_parts(self) -> (io::Error, W) {
+        (self.error, self.writer)
+    }
+}
+
+impl<W> From<IntoInnerError<W>> for io::Error {
+    #[inline]
+    fn from(err: IntoInnerError<W>) -> Self {
+        err.error
+    }
+}
+
+impl<W> fmt::Display for IntoInnerError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl<W: fmt::Debug> Error for IntoInnerError<W> {
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
     }
 }
@@ -0,0 +1,162 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use super::IndentWriter;
+
+/// An error from the inner writer of an [`IndentWriter`], with the position
+/// in the output where it happened. These are returned as the inner error
+/// of an [`io::Error`] with the same [`kind`][io::Error::kind], if
+/// [`error_context`][IndentWriter::error_context] is enabled.
+///
+/// # Example
+///
+/// ```
+/// # use std::io::{self, Write};
+/// use indent_write::io::{IndentError, IndentWriter};
+///
+/// let mut buffer = [0; 12];
+/// let mut writer = IndentWriter::new("  ", &mut buffer[..]).error_context(true);
+///
+/// let err = write!(writer, "Line 1\nLine 2\n").unwrap_err();
+/// assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+///
+/// let context = err.get_ref().unwrap().downcast_ref::<IndentError>().unwrap();
+/// assert_eq!((context.line(), context.column()), (1, 3));
+/// ```
+#[derive(Debug)]
+pub struct IndentError {
+    line: usize,
+    column: usize,
+    source: io::Error,
+}
+
+impl IndentError {
+    /// Get the zero-based line of the output position where the error
+    /// happened; see [`IndentWriter::line`].
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get the zero-based column, in bytes, of the output position where the
+    /// error happened; see [`IndentWriter::column`].
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Get the error returned by the inner writer.
+    #[inline]
+    pub fn error(&self) -> &io::Error {
+        &self.source
+    }
+
+    /// Get the error returned by the inner writer, discarding the position.
+    #[inline]
+    pub fn into_error(self) -> io::Error {
+        self.source
+    }
+}
+
+impl fmt::Display for IndentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error writing indented output at line {}, column {}",
+            self.line, self.column
+        )
+    }
+}
+
+impl Error for IndentError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl<'i, W: io::Write> IndentWriter<'i, W> {
+    /// Configure this writer to attach the output position to errors from
+    /// the inner writer. If this is enabled, an error returned by
+    /// [`write`][io::Write::write], [`flush`][io::Write::flush], or the
+    /// other `io::Write` methods has the same
+    /// [`kind`][io::Error::kind], and contains an [`IndentError`] with the
+    /// [`line`][IndentError::line] and [`column`][IndentError::column]
+    /// where it happened, and the original error as its
+    /// [`source`][Error::source]. It's disabled by default.
+    #[inline]
+    #[must_use]
+    pub fn error_context(mut self, enabled: bool) -> Self {
+        self.error_context = enabled;
+        self
+    }
+
+    /// Get the zero-based line of the current output position; that is, the
+    /// number of newlines that have been written to the inner writer so far.
+    ///
+    /// Only output written through this `IndentWriter` is counted; content
+    /// written directly through [`get_mut`][Self::get_mut] is not tracked.
+    /// Output held by this writer isn't counted until it's written; see
+    /// [`pending_bytes`][Self::pending_bytes].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new());
+    ///
+    /// write!(writer, "Line 1\n\nLine 3").unwrap();
+    /// assert_eq!(writer.line(), 2);
+    /// ```
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.writer.position.line
+    }
+
+    /// Get the zero-based column of the current output position, measured in
+    /// bytes written on the current line. The column *includes* the indent,
+    /// so it reflects the actual position in the output, rather than in the
+    /// content written to this `IndentWriter`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new());
+    ///
+    /// write!(writer, "Line 1\n").unwrap();
+    /// assert_eq!(writer.column(), 0);
+    ///
+    /// write!(writer, "é").unwrap();
+    /// assert_eq!(writer.column(), 4);
+    /// ```
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.writer.position.column
+    }
+
+    /// If error context is enabled, attach the current position to an
+    /// error, unless it already has one.
+    pub(super) fn with_context<T>(&self, result: io::Result<T>) -> io::Result<T> {
+        result.map_err(|err| {
+            let wrapped = err.get_ref().is_some_and(|inner| inner.is::<IndentError>());
+
+            match self.error_context && !wrapped {
+                true => io::Error::new(
+                    err.kind(),
+                    IndentError {
+                        line: self.writer.position.line,
+                        column: self.writer.position.column,
+                        source: err,
+                    },
+                ),
+                false => err,
+            }
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead, Read, Write};
+
+use super::IndentWriter;
+
+/// Copy the entire contents of `reader` into `writer`, indenting each
+/// non-empty line with `indent`. Returns the number of bytes read from
+/// `reader`, which doesn't include the indents.
+///
+/// This is like [`io::copy`], but the content is written through an
+/// [`IndentWriter`]. The content is treated as bytes, so it doesn't have to
+/// be valid UTF-8. [`Interrupted`][io::ErrorKind::Interrupted] errors from
+/// `reader` are retried, and any other error from either end is returned.
+///
+/// If `reader` is already an [`io::BufRead`],
+/// [`copy_indented_buf`] avoids using a buffer of its own.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::io::copy_indented;
+///
+/// let mut output = Vec::new();
+/// let copied = copy_indented(&mut "Line 1\n\nLine 2\n".as_bytes(), &mut output, "  ").unwrap();
+///
+/// assert_eq!(copied, 15);
+/// assert_eq!(output, b"  Line 1\n\n  Line 2\n");
+/// ```
+pub fn copy_indented<'i, R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    indent: impl Into<Cow<'i, str>>,
+) -> io::Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut writer = IndentWriter::new(indent, writer);
+    let mut buf = vec![0; 8 * 1024];
+    let mut copied = 0;
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+    }
+
+    Ok(copied)
+}
+
+/// Copy the entire contents of `reader` into `writer`, indenting each
+/// non-empty line with `indent`. Returns the number of bytes read from
+/// `reader`, which doesn't include the indents.
+///
+/// This is the same as [`copy_indented`], but it writes directly from the
+/// buffer of `reader`.
+///
+/// # Example
+///
+/// ```
+/// use std::io::BufReader;
+/// use indent_write::io::copy_indented_buf;
+///
+/// let mut reader = BufReader::new("Line 1\nLine 2".as_bytes());
+/// let mut output = Vec::new();
+/// copy_indented_buf(&mut reader, &mut output, "  ").unwrap();
+///
+/// assert_eq!(output, b"  Line 1\n  Line 2");
+/// ```
+pub fn copy_indented_buf<'i, R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    indent: impl Into<Cow<'i, str>>,
+) -> io::Result<u64>
+where
+    R: BufRead + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut writer = IndentWriter::new(indent, writer);
+    let mut copied = 0;
+
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(buf) => buf,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        let n = buf.len();
+        writer.write_all(buf)?;
+        reader.consume(n);
+        copied += n as u64;
+    }
+
+    Ok(copied)
+}
@@ -0,0 +1,169 @@
+use std::fmt;
+use std::io::{self, Write as _};
+
+use super::{IndentWriter, IntoInnerError};
+
+/// Adapter for writers to indent each line with an indent computed when the
+/// line is written
+///
+/// A `DynamicIndentWriter` adapts an [`io::Write`] object to insert an
+/// indent before each non-empty line, like an [`IndentWriter`], but the
+/// indent for each line is written by a callback, such as to number each
+/// line or to add a timestamp. The callback is called at the start of each
+/// non-empty line, with the zero-based index of the line (counting empty
+/// lines) and a writer to write the indent into.
+///
+/// The indent is written into an internal buffer, and then written to the
+/// inner writer the same way as an [`IndentWriter`]'s indent, so the
+/// callback is called only once for each line, even if the inner writer
+/// accepts only part of the indent, or returns an error. If the callback
+/// itself returns an error, the error is returned, none of the content is
+/// written, and the callback is called again for the same line by the next
+/// write.
+///
+/// # Example
+///
+/// ```
+/// # use std::io::Write;
+/// use indent_write::io::DynamicIndentWriter;
+///
+/// let mut writer = DynamicIndentWriter::new(
+///     |line: usize, dest: &mut dyn Write| write!(dest, "[{}] ", line),
+///     Vec::new(),
+/// );
+///
+/// write!(writer, "first\n\nthird\n").unwrap();
+/// write!(writer, "fourth").unwrap();
+///
+/// assert_eq!(writer.get_ref(), b"[0] first\n\n[2] third\n[3] fourth");
+/// ```
+pub struct DynamicIndentWriter<F, W> {
+    writer: IndentWriter<'static, W>,
+    indent: F,
+
+    lines: LineState,
+}
+
+/// The state of a writer with an indent computed for each line.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct LineState {
+    // The index of the current line
+    pub(super) line: usize,
+
+    // True if the indent that's about to be written has already been
+    // computed
+    prepared: bool,
+}
+
+impl<W: io::Write> IndentWriter<'_, W> {
+    /// Write some of `buf`. If a new indent is about to be written, call
+    /// `indent` to write it first.
+    pub(super) fn write_dynamic(
+        &mut self,
+        buf: &[u8],
+        lines: &mut LineState,
+        indent: impl FnOnce(usize, &mut dyn io::Write) -> io::Result<()>,
+    ) -> io::Result<usize> {
+        if !lines.prepared && self.at_indent_start(buf) {
+            let mut bytes = Vec::new();
+            indent(lines.line, &mut bytes)?;
+            self.set_indent_bytes(bytes);
+            lines.prepared = true;
+        }
+
+        let result = self.write(buf);
+
+        // If none of the indent could be written, it's still prepared
+        lines.prepared = lines.prepared && self.at_indent_start(&[]);
+
+        let n = result?;
+        lines.line += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        Ok(n)
+    }
+}
+
+impl<F, W> DynamicIndentWriter<F, W>
+where
+    F: FnMut(usize, &mut dyn io::Write) -> io::Result<()>,
+    W: io::Write,
+{
+    /// Create a new [`DynamicIndentWriter`], which calls `indent` at the
+    /// start of each non-empty line to write its indent.
+    #[inline]
+    pub fn new(indent: F, writer: W) -> Self {
+        Self {
+            writer: IndentWriter::new("", writer),
+            indent,
+            lines: LineState::default(),
+        }
+    }
+
+    /// Get the zero-based index of the current line: the number of newlines
+    /// written so far.
+    #[inline]
+    pub fn current_line(&self) -> usize {
+        self.lines.line
+    }
+
+    /// Extract the writer from the [`DynamicIndentWriter`]. If an indent was
+    /// only partly written, the rest of it is written first; see
+    /// [`IndentWriter::into_inner`].
+    // The error carries the writer back, the same as `BufWriter::into_inner`
+    #[allow(clippy::result_large_err)]
+    pub fn into_inner(self) -> Result<W, IntoInnerError<Self>> {
+        let Self {
+            writer,
+            indent,
+            lines,
+        } = self;
+
+        writer.into_inner().map_err(|err| IntoInnerError {
+            writer: Self {
+                writer: err.writer,
+                indent,
+                lines,
+            },
+            error: err.error,
+        })
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the indentation
+    /// logic, and isn't counted by [`current_line`][Self::current_line].
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.get_mut()
+    }
+}
+
+impl<F, W> io::Write for DynamicIndentWriter<F, W>
+where
+    F: FnMut(usize, &mut dyn io::Write) -> io::Result<()>,
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let indent = &mut self.indent;
+        self.writer.write_dynamic(buf, &mut self.lines, indent)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<F, W: fmt::Debug> fmt::Debug for DynamicIndentWriter<F, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicIndentWriter")
+            .field("writer", &self.writer)
+            .field("lines", &self.lines)
+            .finish_non_exhaustive()
+    }
+}
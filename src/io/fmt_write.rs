@@ -0,0 +1,62 @@
+use std::fmt;
+use std::io::{self, Write as _};
+
+use super::IndentWriter;
+
+/// An error stashed by the [`fmt::Write`] implementation of an
+/// [`IndentWriter`]. It isn't cloned with the writer, since `io::Error`
+/// isn't `Clone`.
+#[derive(Debug, Default)]
+pub(super) struct StashedError(Option<io::Error>);
+
+impl Clone for StashedError {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
+impl<'i, W: io::Write> IndentWriter<'i, W> {
+    /// Take the last error from the inner writer that was returned as a
+    /// [`fmt::Error`] by this writer's [`fmt::Write`] implementation, if
+    /// there is one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::fmt::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut buffer = [0; 4];
+    /// let mut writer = IndentWriter::new("  ", &mut buffer[..]);
+    ///
+    /// assert!(writer.write_str("too long").is_err());
+    ///
+    /// let error = writer.take_error().unwrap();
+    /// assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    /// assert!(writer.take_error().is_none());
+    /// ```
+    #[inline]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.0.take()
+    }
+}
+
+/// Write to an [`IndentWriter`] as a [`fmt::Write`], such as for a
+/// [`Display`][fmt::Display] implementation that takes a `&mut dyn
+/// fmt::Write`. Each string is written with
+/// [`write_all`][io::Write::write_all]. If that fails, the `io::Error` is
+/// kept, and can be retrieved with [`take_error`][IndentWriter::take_error];
+/// a [`fmt::Error`] from this writer means the error should be checked
+/// there.
+///
+/// Since `IndentWriter` implements both [`io::Write`] and [`fmt::Write`],
+/// [`write!`] is ambiguous if both traits are imported.
+impl<W: io::Write> fmt::Write for IndentWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|err| {
+            self.error.0 = Some(err);
+            fmt::Error
+        })
+    }
+}
@@ -0,0 +1,242 @@
+use std::io;
+
+use super::IndentWriter;
+
+/// The inner writer of an [`IndentWriter`], and its line buffer, if it's
+/// line buffered. Everything the `IndentWriter` writes, including indents,
+/// goes through here.
+#[derive(Debug, Clone)]
+pub(super) struct Output<W> {
+    pub(super) writer: W,
+    buffer: Option<LineBuffer>,
+    pub(super) position: Position,
+}
+
+/// The zero-based position in the output, in bytes, after everything written
+/// to the inner writer so far.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Position {
+    pub(super) line: usize,
+    pub(super) column: usize,
+}
+
+impl Position {
+    /// Update the position after writing `buf`.
+    fn advance(&mut self, buf: &[u8]) {
+        match buf.iter().rposition(|&b| b == b'\n') {
+            Some(end) => {
+                self.line += buf.iter().filter(|&&b| b == b'\n').count();
+                self.column = buf.len() - end - 1;
+            }
+            None => self.column += buf.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LineBuffer {
+    // Output that hasn't been written to the writer yet. If it contains a
+    // newline, it's waiting to be written after an error.
+    buffer: Vec<u8>,
+    capacity: usize,
+}
+
+impl<W> Output<W> {
+    #[inline]
+    pub(super) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: None,
+            position: Position::default(),
+        }
+    }
+
+    /// Create an `Output` for `writer`, and move the contents of the buffer
+    /// into it.
+    pub(super) fn take_buffer<V>(&mut self, writer: V) -> Output<V> {
+        Output {
+            writer,
+            buffer: self.buffer.as_mut().map(|line| LineBuffer {
+                buffer: std::mem::take(&mut line.buffer),
+                capacity: line.capacity,
+            }),
+            position: self.position,
+        }
+    }
+
+    /// The number of bytes in the buffer.
+    pub(super) fn pending_len(&self) -> usize {
+        self.buffer.as_ref().map_or(0, |line| line.buffer.len())
+    }
+}
+
+impl<W: io::Write> Output<W> {
+    /// Write everything in the buffer, without flushing the writer.
+    pub(super) fn write_buffer(&mut self) -> io::Result<()> {
+        let line = match self.buffer {
+            Some(ref mut line) => line,
+            None => return Ok(()),
+        };
+
+        // Only remove what's been written, in case of an error
+        let (written, result) = write_all(&mut self.writer, &line.buffer);
+        self.position.advance(&line.buffer[..written]);
+        line.buffer.drain(..written);
+        result
+    }
+}
+
+/// Write all of `buf` to `writer`, like `write_all`, and return how much of it
+/// was written, even if there was an error.
+fn write_all(writer: &mut impl io::Write, buf: &[u8]) -> (usize, io::Result<()>) {
+    let mut written = 0;
+
+    while written < buf.len() {
+        match writer.write(&buf[written..]) {
+            Ok(0) => return (written, Err(io::ErrorKind::WriteZero.into())),
+            Ok(n) => written += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return (written, Err(err)),
+        }
+    }
+
+    (written, Ok(()))
+}
+
+impl<W: io::Write> io::Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = match self.buffer {
+            Some(ref mut line) => line,
+            None => return self.write_unbuffered(buf),
+        };
+
+        // Finish writing complete lines from an earlier write
+        if line.buffer.contains(&b'\n') {
+            self.write_buffer()?;
+        }
+
+        let line = self.buffer.as_mut().expect("not line buffered");
+
+        match buf.iter().rposition(|&b| b == b'\n') {
+            // Buffer the content, unless it doesn't fit. If it doesn't, write
+            // the buffer, and then write the content directly if it still
+            // doesn't fit.
+            None => {
+                if line.buffer.len() + buf.len() > line.capacity {
+                    self.write_buffer()?;
+                    if buf.len() > self.buffer.as_ref().map_or(0, |line| line.capacity) {
+                        return self.write_unbuffered(buf);
+                    }
+                }
+
+                let line = self.buffer.as_mut().expect("not line buffered");
+                line.buffer.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            // The content completes a line. Write the whole line at once.
+            // Once any of the new content is written, it's been accepted, so
+            // an error writing the rest of it is returned by the next write.
+            // If none of it is written, the error is returned now. The
+            // buffer isn't changed until then, in case the writer panics.
+            Some(end) => {
+                let lines = &buf[..end + 1];
+
+                if line.buffer.is_empty() {
+                    return self.write_unbuffered(lines);
+                }
+
+                let mut output = line.buffer.clone();
+                output.extend_from_slice(lines);
+
+                let (written, result) = write_all(&mut self.writer, &output);
+                self.position.advance(&output[..written]);
+
+                match result {
+                    Err(err) if written <= line.buffer.len() => {
+                        line.buffer.drain(..written);
+                        Err(err)
+                    }
+                    _ => {
+                        output.drain(..written);
+                        line.buffer = output;
+                        Ok(lines.len())
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        if self.buffer.is_some() {
+            return match bufs.iter().find(|buf| !buf.is_empty()) {
+                Some(buf) => self.write(buf),
+                None => Ok(0),
+            };
+        }
+
+        let n = self.writer.write_vectored(bufs)?;
+
+        let mut rest = n;
+        for buf in bufs {
+            let len = buf.len().min(rest);
+            self.position.advance(&buf[..len]);
+            rest -= len;
+        }
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        self.writer.flush()
+    }
+}
+
+impl<W: io::Write> Output<W> {
+    /// Write some of `buf` directly to the writer.
+    fn write_unbuffered(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.position.advance(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<'i, W: io::Write> IndentWriter<'i, W> {
+    /// Configure this writer to buffer each line, including its indent, and
+    /// write it to the inner writer at once when the line is finished,
+    /// rather than writing the indent and the content of the line
+    /// separately. This is useful if each write to the inner writer is
+    /// expensive, such as for an unbuffered pipe or socket.
+    ///
+    /// At most `capacity` bytes are buffered. If a line is longer than that,
+    /// the buffered part is written, and the rest of the line is written as
+    /// it's written to the `IndentWriter`, the same as if it wasn't line
+    /// buffered. Buffered content is written by
+    /// [`flush`][io::Write::flush], [`flush_pending`][Self::flush_pending],
+    /// and [`into_inner`][Self::into_inner].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new()).line_buffered(1024);
+    ///
+    /// write!(writer, "one\ntwo").unwrap();
+    /// assert_eq!(writer.get_ref(), b"  one\n");
+    ///
+    /// writer.flush().unwrap();
+    /// assert_eq!(writer.get_ref(), b"  one\n  two");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn line_buffered(mut self, capacity: usize) -> Self {
+        self.writer.buffer = Some(LineBuffer {
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+        });
+        self
+    }
+}
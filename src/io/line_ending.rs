@@ -0,0 +1,128 @@
+use std::io::{self, Write as _};
+
+use super::IndentWriter;
+
+/// The line ending written by an [`IndentWriter`] for each newline in the
+/// written content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LineEnding {
+    /// Write each newline unchanged.
+    #[default]
+    Lf,
+
+    /// Write each `\n` as `\r\n`. A `\n` that's already part of a `\r\n`
+    /// isn't changed.
+    CrLf,
+}
+
+/// The state of a writer that translates newlines.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct LineEndingState {
+    pub(super) line_ending: LineEnding,
+
+    // True if the last byte of content written was a `\r`
+    last_cr: bool,
+
+    // True if the `\r` of a translated newline was written, but the `\n`
+    // hasn't been written yet
+    pending_lf: bool,
+}
+
+impl LineEndingState {
+    /// The number of bytes that have been accepted, but not written.
+    pub(super) fn pending_len(&self) -> usize {
+        usize::from(self.pending_lf)
+    }
+
+    /// Forget the content written so far, keeping the line ending.
+    pub(super) fn reset(&mut self) {
+        self.last_cr = false;
+        self.pending_lf = false;
+    }
+}
+
+impl<'i, W: io::Write> IndentWriter<'i, W> {
+    /// Set the line ending written for each newline in the written content.
+    /// The default is [`LineEnding::Lf`], which writes newlines unchanged.
+    ///
+    /// The reported number of bytes written is always in terms of the
+    /// written content, so a write that returns 1 for a `\n` may have
+    /// written both the `\r` and the `\n`. If the inner writer accepts the
+    /// `\r` but not the `\n`, the `\n` is written by the next write, or
+    /// [`flush`][io::Write::flush], or [`into_inner`][Self::into_inner].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::{IndentWriter, LineEnding};
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new()).line_ending(LineEnding::CrLf);
+    ///
+    /// write!(writer, "one\ntwo\r\n\nthree\n").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"  one\r\n  two\r\n\r\n  three\r\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending.line_ending = line_ending;
+        self
+    }
+
+    /// Write some of `buf`, which is content rather than an indent,
+    /// translating newlines. Returns the number of bytes of `buf` that were
+    /// written.
+    pub(super) fn write_content(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.line_ending.line_ending == LineEnding::Lf {
+            return self.writer.write(buf);
+        }
+
+        let state = &mut self.line_ending;
+
+        // Find the first `\n` that isn't already part of a `\r\n`
+        let bare_lf = buf.iter().enumerate().position(|(i, &b)| {
+            b == b'\n'
+                && !match i {
+                    0 => state.last_cr,
+                    i => buf[i - 1] == b'\r',
+                }
+        });
+
+        let n = match bare_lf {
+            // We're at a bare newline. Write it as `\r\n`.
+            Some(0) => {
+                return match self.writer.write(b"\r\n")? {
+                    0 => Ok(0),
+                    n => {
+                        state.last_cr = false;
+                        state.pending_lf = n < 2;
+                        Ok(1)
+                    }
+                };
+            }
+
+            // Write the content up to the bare newline
+            Some(len) => self.writer.write(&buf[..len])?,
+            None => self.writer.write(buf)?,
+        };
+
+        if n > 0 {
+            state.last_cr = buf[n - 1] == b'\r';
+        }
+
+        Ok(n)
+    }
+
+    /// Write the `\n` of a translated newline, if only the `\r` was written.
+    pub(super) fn write_pending_lf(&mut self) -> io::Result<()> {
+        if self.line_ending.pending_lf {
+            match self.writer.write(b"\n")? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                _ => self.line_ending.pending_lf = false,
+            }
+        }
+
+        Ok(())
+    }
+}
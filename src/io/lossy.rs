@@ -0,0 +1,201 @@
+use std::io;
+use std::str;
+
+use super::IndentWriter;
+
+const REPLACEMENT: &[u8] = "\u{FFFD}".as_bytes();
+
+/// The state of a writer that replaces invalid UTF-8.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Lossy {
+    // The start of a UTF-8 sequence, at the end of an earlier write, that
+    // isn't complete yet
+    held: Vec<u8>,
+
+    // Output for content that was already accepted, which hasn't been
+    // written yet
+    pending: Vec<u8>,
+}
+
+impl Lossy {
+    /// The number of bytes that have been accepted, but not written.
+    pub(super) fn pending_len(&self) -> usize {
+        self.held.len() + self.pending.len()
+    }
+}
+
+impl<'i, W: io::Write> IndentWriter<'i, W> {
+    /// Configure this writer to replace invalid UTF-8 in written content
+    /// with U+FFFD REPLACEMENT CHARACTER, instead of writing it unchanged.
+    /// Each invalid sequence is replaced with a single replacement
+    /// character, the same as with [`String::from_utf8_lossy`]. The invalid
+    /// bytes are counted as written, so [`write_all`][io::Write::write_all]
+    /// still makes progress.
+    ///
+    /// A sequence may be split across several writes. If a write ends with
+    /// an incomplete sequence, it's held back until the next write shows
+    /// whether it's valid. [`into_inner`][Self::into_inner] replaces any
+    /// incomplete sequence at the end of the output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::IndentWriter;
+    ///
+    /// let mut writer = IndentWriter::new("  ", Vec::new()).lossy_utf8(true);
+    ///
+    /// writer.write_all(b"bad: \xFF\nsplit: \xF0\x9F").unwrap();
+    /// writer.write_all(b"\x98\x80\n").unwrap();
+    ///
+    /// let output = writer.into_inner().unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "  bad: \u{FFFD}\n  split: 😀\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn lossy_utf8(mut self, lossy: bool) -> Self {
+        self.lossy = match lossy {
+            true => Some(Lossy::default()),
+            false => None,
+        };
+        self
+    }
+
+    pub(super) fn write_lossy(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_lossy_pending()?;
+
+        let lossy = self.lossy.as_ref().expect("not in lossy mode");
+
+        // Complete the held-back sequence, one byte at a time. The held
+        // bytes are the valid start of a sequence, so either the next byte
+        // completes it, or continues it, or it's invalid. The state isn't
+        // changed until the output for it has been written.
+        if !lossy.held.is_empty() {
+            let mut held = lossy.held.clone();
+            let mut consumed = 0;
+
+            let output = loop {
+                let b = match buf.get(consumed) {
+                    Some(&b) => b,
+                    None => {
+                        self.lossy.as_mut().expect("not in lossy mode").held = held;
+                        return Ok(consumed);
+                    }
+                };
+
+                held.push(b);
+
+                match str::from_utf8(&held) {
+                    Ok(_) => {
+                        consumed += 1;
+                        break held;
+                    }
+                    Err(err) if err.error_len().is_none() => consumed += 1,
+                    Err(_) => break REPLACEMENT.to_vec(),
+                }
+            };
+
+            if !self.write_output(&output)? {
+                return Ok(0);
+            }
+
+            self.lossy.as_mut().expect("not in lossy mode").held.clear();
+
+            // If the held bytes were invalid, none of `buf` was used yet.
+            // Finish writing the replacement before continuing.
+            if consumed > 0 {
+                return Ok(consumed);
+            }
+
+            self.write_lossy_pending()?;
+        }
+
+        let err = match str::from_utf8(buf) {
+            Ok(_) => return self.write_valid(buf),
+            Err(err) => err,
+        };
+
+        if err.valid_up_to() > 0 {
+            return self.write_valid(&buf[..err.valid_up_to()]);
+        }
+
+        match err.error_len() {
+            // The rest of the buffer is the start of a sequence; hold it back
+            None => {
+                let lossy = self.lossy.as_mut().expect("not in lossy mode");
+                lossy.held.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            // Replace the invalid sequence
+            Some(len) => match self.write_output(REPLACEMENT)? {
+                true => Ok(len),
+                false => Ok(0),
+            },
+        }
+    }
+
+    /// Write some of `valid`, which is valid UTF-8. If the inner writer
+    /// accepts only part of a character, the rest of it is accepted too,
+    /// and written by the next write, so that the next write doesn't start
+    /// with a continuation byte, which would be replaced.
+    fn write_valid(&mut self, valid: &[u8]) -> io::Result<usize> {
+        let n = self.write_indented(valid)?;
+        let end = valid[n..]
+            .iter()
+            .position(|&b| b & 0xC0 != 0x80)
+            .map_or(valid.len(), |len| n + len);
+
+        let lossy = self.lossy.as_mut().expect("not in lossy mode");
+        lossy.pending.extend_from_slice(&valid[n..end]);
+        Ok(end)
+    }
+
+    /// Write `output`, for content that's being accepted by the current
+    /// write. Whatever isn't written is written by the next write. Returns
+    /// false if none of it could be written, in which case the content
+    /// shouldn't be accepted.
+    fn write_output(&mut self, output: &[u8]) -> io::Result<bool> {
+        let n = self.write_indented(output)?;
+        if n == 0 {
+            return Ok(false);
+        }
+
+        let lossy = self.lossy.as_mut().expect("not in lossy mode");
+        lossy.pending.extend_from_slice(&output[n..]);
+        Ok(true)
+    }
+
+    /// Write any output for content that was already accepted.
+    pub(super) fn write_lossy_pending(&mut self) -> io::Result<()> {
+        loop {
+            // The pending output is only removed once it's been written
+            let pending = match self.lossy {
+                Some(ref lossy) if !lossy.pending.is_empty() => lossy.pending.clone(),
+                _ => return Ok(()),
+            };
+
+            match self.write_indented(&pending)? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                n => {
+                    if let Some(ref mut lossy) = self.lossy {
+                        lossy.pending.drain(..n);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replace any incomplete sequence at the end of the output, and write
+    /// any output for content that was already accepted.
+    pub(super) fn finish_lossy(&mut self) -> io::Result<()> {
+        if let Some(ref mut lossy) = self.lossy {
+            if !lossy.held.is_empty() {
+                lossy.held.clear();
+                lossy.pending.extend_from_slice(REPLACEMENT);
+            }
+        }
+
+        self.write_lossy_pending()
+    }
+}
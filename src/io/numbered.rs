@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+use std::io;
+
+use super::dynamic::LineState;
+use super::{IndentWriter, IntoInnerError};
+
+/// Adapter for writers to number each line
+///
+/// A `NumberedWriter` adapts an [`io::Write`] object to prefix each
+/// non-empty line with its line number and a separator, such as `"42: "`.
+/// Empty lines are counted, but not numbered, so that the numbers match
+/// the lines of the output. By default, lines are numbered from 1, with a
+/// separator of `": "`.
+///
+/// The number is rendered into an internal buffer when the line starts, and
+/// written the same way as an [`IndentWriter`]'s indent, so it's written
+/// correctly even if the inner writer accepts only part of it.
+///
+/// # Example
+///
+/// ```
+/// # use std::io::Write;
+/// use indent_write::io::NumberedWriter;
+///
+/// let mut writer = NumberedWriter::new(Vec::new());
+///
+/// write!(writer, "fn main() {{\n\n    build();\n}}\n").unwrap();
+///
+/// assert_eq!(
+///     writer.get_ref(),
+///     b"1: fn main() {\n\n3:     build();\n4: }\n",
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct NumberedWriter<'s, W> {
+    writer: IndentWriter<'static, W>,
+    lines: LineState,
+    start: usize,
+    separator: Cow<'s, str>,
+}
+
+impl<'s, W: io::Write> NumberedWriter<'s, W> {
+    /// Create a new [`NumberedWriter`].
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: IndentWriter::new("", writer),
+            lines: LineState::default(),
+            start: 1,
+            separator: Cow::Borrowed(": "),
+        }
+    }
+
+    /// Set the number of the first line. The default is 1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// use indent_write::io::NumberedWriter;
+    ///
+    /// let mut writer = NumberedWriter::new(Vec::new()).start(98).separator(" | ");
+    ///
+    /// write!(writer, "a\nb\nc").unwrap();
+    ///
+    /// assert_eq!(writer.get_ref(), b"98 | a\n99 | b\n100 | c");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn start(mut self, start: usize) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Set the separator written between each line number and the line.
+    /// The default is `": "`.
+    #[inline]
+    #[must_use]
+    pub fn separator(mut self, separator: impl Into<Cow<'s, str>>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Get the number of the current line. If the current line is
+    /// non-empty, this is the number it was written with.
+    #[inline]
+    pub fn current_line(&self) -> usize {
+        self.start + self.lines.line
+    }
+
+    /// Extract the writer from the [`NumberedWriter`]. If a line number was
+    /// only partly written, the rest of it is written first; see
+    /// [`IndentWriter::into_inner`].
+    // The error carries the writer back, the same as `BufWriter::into_inner`
+    #[allow(clippy::result_large_err)]
+    pub fn into_inner(self) -> Result<W, IntoInnerError<Self>> {
+        let Self {
+            writer,
+            lines,
+            start,
+            separator,
+        } = self;
+
+        writer.into_inner().map_err(|err| IntoInnerError {
+            writer: Self {
+                writer: err.writer,
+                lines,
+                start,
+                separator,
+            },
+            error: err.error,
+        })
+    }
+
+    /// Get a reference to the wrapped writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Get a mutable reference to the wrapped writer.
+    ///
+    /// Content written directly to the inner writer bypasses the numbering
+    /// logic, and its lines aren't counted.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.get_mut()
+    }
+}
+
+impl<W: io::Write> io::Write for NumberedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start = self.start;
+        let separator = &self.separator;
+
+        self.writer
+            .write_dynamic(buf, &mut self.lines, |line, dest| {
+                write!(dest, "{}{}", start + line, separator)
+            })
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
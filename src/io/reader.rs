@@ -0,0 +1,174 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+
+use super::is_line_break;
+use super::IndentState::{self, *};
+
+/// Adapter for readers to indent each line
+///
+/// An `IndentReader` adapts an [`io::BufRead`] object to insert an indent
+/// before each non-empty line of the content read from it, the same as an
+/// [`IndentWriter`][super::IndentWriter] does for written content. An indent
+/// is only inserted once the first byte of the line has been read from the
+/// inner reader, so there's no indent after a trailing newline at the end of
+/// the content.
+///
+/// To indent something that's only [`io::Read`], such as a file or a child
+/// process's output, wrap it in an [`io::BufReader`]. An `IndentReader` is
+/// also an [`io::BufRead`], without any buffer of its own, so its lines can
+/// be read with [`lines`][BufRead::lines].
+///
+/// # Example
+///
+/// ```
+/// use std::io::Read;
+/// use indent_write::io::IndentReader;
+///
+/// let mut reader = IndentReader::new("> ", "Line 1\n\nLine 2\n".as_bytes());
+/// let mut output = String::new();
+/// reader.read_to_string(&mut output).unwrap();
+///
+/// assert_eq!(output, "> Line 1\n\n> Line 2\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndentReader<'i, R> {
+    reader: R,
+    indent: Cow<'i, [u8]>,
+    state: IndentState,
+
+    // The length of the rest of the current line, if its end has been
+    // returned by `fill_buf`
+    line_end: Option<usize>,
+}
+
+impl<'i, R: BufRead> IndentReader<'i, R> {
+    /// Create a new [`IndentReader`].
+    #[inline]
+    pub fn new(indent: impl Into<Cow<'i, str>>, reader: R) -> Self {
+        Self::new_bytes(super::str_bytes(indent.into()), reader)
+    }
+
+    /// Create a new [`IndentReader`], with an indent that isn't necessarily
+    /// valid UTF-8.
+    #[inline]
+    pub fn new_bytes(indent: impl Into<Cow<'i, [u8]>>, reader: R) -> Self {
+        Self {
+            reader,
+            indent: indent.into(),
+            state: NeedIndent,
+            line_end: None,
+        }
+    }
+
+    /// Extract the reader from the [`IndentReader`]. Any part of an indent
+    /// that hasn't been read yet is discarded.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Get a reference to the wrapped reader
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the wrapped reader.
+    ///
+    /// Content read directly from the inner reader bypasses the indentation
+    /// logic.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R: BufRead> io::Read for IndentReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let data = self.fill_buf()?;
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.consume(n);
+
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for IndentReader<'_, R> {
+    /// Get the next part of the indented content. This is either the rest of
+    /// an indent, or content from the inner reader, up to the end of the
+    /// current line, or up to the next non-empty line.
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        loop {
+            match self.state {
+                // We're in the middle of an indent. Return the rest of it,
+                // unless it's empty.
+                WritingIndent(written) if written < self.indent.len() => {
+                    return Ok(&self.indent[written..]);
+                }
+                WritingIndent(_) => self.state = MidLine,
+
+                // Start the indent if we're at the start of a non-empty line
+                NeedIndent => match self.reader.fill_buf()?.first() {
+                    Some(&b) if !is_line_break(b) => self.state = WritingIndent(0),
+                    _ => break,
+                },
+
+                MidLine => break,
+            }
+        }
+
+        let data = self.reader.fill_buf()?;
+
+        let len = match self.state {
+            // Return the rest of this line, and remember where it ends
+            MidLine => match data.iter().position(|&b| b == b'\n') {
+                Some(len) => {
+                    self.line_end = Some(len + 1);
+                    len + 1
+                }
+                None => {
+                    self.line_end = None;
+                    data.len()
+                }
+            },
+
+            // Return the empty lines
+            _ => data
+                .iter()
+                .position(|&b| !is_line_break(b))
+                .unwrap_or(data.len()),
+        };
+
+        Ok(&data[..len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.state {
+            WritingIndent(written) => {
+                self.state = match written + amt >= self.indent.len() {
+                    true => MidLine,
+                    false => WritingIndent(written + amt),
+                }
+            }
+
+            // If we've reached the end of the line, request an indent
+            MidLine => {
+                self.reader.consume(amt);
+                self.line_end = match self.line_end {
+                    Some(len) if amt >= len => {
+                        self.state = NeedIndent;
+                        None
+                    }
+                    line_end => line_end.map(|len| len - amt),
+                };
+            }
+
+            NeedIndent => self.reader.consume(amt),
+        }
+    }
+}
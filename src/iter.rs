@@ -0,0 +1,148 @@
+//! Iterator adapters for indenting lines.
+//!
+//! This module requires the `alloc` feature.
+
+use alloc::borrow::Cow;
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+
+/// Iterator adapter that indents lines
+///
+/// An `IndentLines` yields each line from an iterator of lines (such as
+/// [`str::lines`]), with `prefix` inserted before each non-empty line, the
+/// same way as a [`fmt::IndentWriter`][crate::fmt::IndentWriter]. Lines are
+/// indented lazily, one at a time. Empty lines are yielded unchanged, without
+/// allocating; non-empty lines are copied into a new `String` unless they're
+/// already owned. The lines shouldn't contain newlines.
+///
+/// Create one with [`indent_lines`].
+#[derive(Debug, Clone)]
+pub struct IndentLines<'a, 'p, I> {
+    iter: I,
+    prefix: &'p str,
+    newlines: bool,
+    phantom: PhantomData<Cow<'a, str>>,
+}
+
+/// Indent an iterator of lines, which can be `&str`, `String`, or
+/// `Cow<str>`. See [`IndentLines`] for details.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::iter::indent_lines;
+///
+/// let lines: Vec<_> = indent_lines("Line 1\n\nLine 2".lines(), "  ").collect();
+/// assert_eq!(lines, ["  Line 1", "", "  Line 2"]);
+/// ```
+#[inline]
+pub fn indent_lines<'a, 'p, I>(iter: I, prefix: &'p str) -> IndentLines<'a, 'p, I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Into<Cow<'a, str>>,
+{
+    IndentLines {
+        iter: iter.into_iter(),
+        prefix,
+        newlines: false,
+        phantom: PhantomData,
+    }
+}
+
+impl<'a, 'p, I> IndentLines<'a, 'p, I> {
+    /// Append a newline to each line, so that the lines can be written
+    /// directly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use indent_write::iter::indent_lines;
+    ///
+    /// let text: String = indent_lines(vec!["a", "", "b"], "  ")
+    ///     .with_newlines()
+    ///     .collect();
+    ///
+    /// assert_eq!(text, "  a\n\n  b\n");
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_newlines(self) -> Self {
+        Self {
+            newlines: true,
+            ..self
+        }
+    }
+
+    /// Get the prefix being inserted before each non-empty line
+    #[inline]
+    pub fn prefix(&self) -> &'p str {
+        self.prefix
+    }
+
+    /// Extract the underlying iterator from the `IndentLines`
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+
+    fn indent(&self, line: Cow<'a, str>) -> Cow<'a, str> {
+        if line.is_empty() {
+            return match self.newlines {
+                true => Cow::Borrowed("\n"),
+                false => line,
+            };
+        }
+
+        let mut line = line.into_owned();
+        line.insert_str(0, self.prefix);
+
+        if self.newlines {
+            line.push('\n');
+        }
+
+        Cow::Owned(line)
+    }
+}
+
+impl<'a, I> Iterator for IndentLines<'a, '_, I>
+where
+    I: Iterator,
+    I::Item: Into<Cow<'a, str>>,
+{
+    type Item = Cow<'a, str>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Cow<'a, str>> {
+        self.iter.next().map(|line| self.indent(line.into()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, I> DoubleEndedIterator for IndentLines<'a, '_, I>
+where
+    I: DoubleEndedIterator,
+    I::Item: Into<Cow<'a, str>>,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Cow<'a, str>> {
+        self.iter.next_back().map(|line| self.indent(line.into()))
+    }
+}
+
+impl<'a, I> ExactSizeIterator for IndentLines<'a, '_, I>
+where
+    I: ExactSizeIterator,
+    I::Item: Into<Cow<'a, str>>,
+{
+}
+
+impl<'a, I> FusedIterator for IndentLines<'a, '_, I>
+where
+    I: FusedIterator,
+    I::Item: Into<Cow<'a, str>>,
+{
+}
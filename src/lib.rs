@@ -1,4 +1,5 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+#![forbid(unsafe_code)]
 
 //! Simple indentation adapters for [`io::Write`][std::io::Write],
 //! [`fmt::Write`][std::fmt::Write], and [`Display`][std::fmt::Display]. Each
@@ -7,35 +8,119 @@
 //!
 //! See [`fmt::IndentWriter`], [`io::IndentWriter`], and
 //! [`indentable::Indentable`] for examples.
+//!
+//! # Features
+//!
+//! - `std` (enabled by default): enables the [`io`] module. Without it, this
+//!   crate is `no_std`, and the [`fmt`] and [`indentable`] modules can be
+//!   used with any [`core::fmt::Write`] destination.
+//! - `alloc` (enabled by `std`): enables functionality that needs to
+//!   allocate, such as [`indent_str`], [`fmt::SharedLevel`], and the
+//!   [`iter`] module.
+//! - `wrap`: enables word wrapping with `fmt::IndentWriter::with_word_wrap`.
+//! - `derive`: enables `#[derive(IndentDisplay)]`, which implements
+//!   [`Display`][std::fmt::Display] for structs and enums by writing each of
+//!   their fields on an indented line.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod macros;
+#[cfg(feature = "alloc")]
+mod text;
 
 pub mod fmt;
 pub mod indentable;
+#[cfg(feature = "alloc")]
+pub mod iter;
 
 #[cfg(feature = "std")]
 pub mod io;
 
-trait Inspect<T> {
-    fn inspect(self, func: impl FnOnce(&T)) -> Self;
-}
+#[cfg(feature = "alloc")]
+pub use text::{dedent_str, indent_str, strip_margin};
 
-impl<T> Inspect<T> for Option<T> {
-    #[inline]
-    fn inspect(self, func: impl FnOnce(&T)) -> Self {
-        if let Some(ref value) = self {
-            func(value)
-        }
+/// Derive [`Display`][core::fmt::Display] for a struct or enum, writing its
+/// name followed by each of its fields on its own indented line, as
+/// `name: value`. Nested values that also derive `IndentDisplay` are
+/// indented further, since each line of a field's value is indented.
+///
+/// For enums, the name of the variant is written, followed by its fields.
+/// Fields of tuple structs and variants are named by their index. Every
+/// generic type parameter is required to implement `Display`.
+///
+/// The derive also implements [`indentable::IndentFields`]. It's configured
+/// with `#[indent(...)]` attributes:
+///
+/// - `#[indent(prefix = "...")]`, on the type: the indent for each field.
+///   The default is two spaces.
+/// - `#[indent(skip)]`, on a field: don't write the field.
+/// - `#[indent(flatten)]`, on a field: write the fields of the field's value
+///   in place of the field. Its type must implement
+///   [`IndentFields`][indentable::IndentFields].
+///
+/// This macro requires the `derive` feature.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::IndentDisplay;
+///
+/// #[derive(IndentDisplay)]
+/// struct Limits {
+///     memory: u32,
+///     threads: u8,
+/// }
+///
+/// #[derive(IndentDisplay)]
+/// enum Backend {
+///     Local,
+///     Remote { host: &'static str, port: u16 },
+/// }
+///
+/// #[derive(IndentDisplay)]
+/// #[indent(prefix = "| ")]
+/// struct Config {
+///     name: &'static str,
+///     backend: Backend,
+///     #[indent(flatten)]
+///     limits: Limits,
+///     #[indent(skip)]
+///     secret: &'static str,
+/// }
+///
+/// let config = Config {
+///     name: "prod",
+///     backend: Backend::Remote { host: "example.com", port: 443 },
+///     limits: Limits { memory: 512, threads: 4 },
+///     secret: "hunter2",
+/// };
+///
+/// assert_eq!(
+///     config.to_string(),
+///     "Config
+/// | name: prod
+/// | backend: Remote
+/// |   host: example.com
+/// |   port: 443
+/// | memory: 512
+/// | threads: 4",
+/// );
+/// ```
+#[cfg(feature = "derive")]
+pub use indent_write_derive::IndentDisplay;
 
-        self
-    }
-}
-
-impl<T, E> Inspect<T> for Result<T, E> {
-    #[inline]
-    fn inspect(self, func: impl FnOnce(&T)) -> Self {
-        if let Ok(ref value) = self {
-            func(value)
+#[doc(hidden)]
+pub mod __private {
+    /// Used by macros to reborrow their destination with method call
+    /// semantics, the same way that `write!` does, so that both owned writers
+    /// and `&mut` references can be passed.
+    pub trait Reborrow {
+        #[inline]
+        fn __indent_write_reborrow(&mut self) -> &mut Self {
+            self
         }
-
-        self
     }
+
+    impl<T: ?Sized> Reborrow for T {}
 }
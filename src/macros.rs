@@ -0,0 +1,151 @@
+/// Write formatted content to a [`fmt::Write`][core::fmt::Write] object,
+/// indenting each non-empty line with the given indent.
+///
+/// This is equivalent to calling [`write!`] on a [`fmt::IndentWriter`]
+/// wrapping the destination, for just this one statement. Like `write!`, it
+/// returns a [`fmt::Result`][core::fmt::Result]. For
+/// [`io::Write`][std::io::Write] destinations, use [`io_write_indented!`][crate::io_write_indented].
+///
+/// [`fmt::IndentWriter`]: crate::fmt::IndentWriter
+///
+/// # Example
+///
+/// ```
+/// use indent_write::write_indented;
+///
+/// let mut output = String::new();
+/// write_indented!(output, "  ", "{}\n{}", "Line 1", "Line 2").unwrap();
+///
+/// assert_eq!(output, "  Line 1\n  Line 2");
+/// ```
+#[macro_export]
+macro_rules! write_indented {
+    ($dst:expr, $indent:expr, $($arg:tt)*) => {{
+        use $crate::__private::Reborrow as _;
+        ::core::fmt::Write::write_fmt(
+            &mut $crate::fmt::IndentWriter::new($indent, $dst.__indent_write_reborrow()),
+            ::core::format_args!($($arg)*),
+        )
+    }};
+}
+
+/// Write formatted content, followed by a newline, to a
+/// [`fmt::Write`][core::fmt::Write] object, indenting each non-empty line
+/// with the given indent.
+///
+/// This is equivalent to calling [`writeln!`] on a [`fmt::IndentWriter`]
+/// wrapping the destination, for just this one statement. Like `writeln!`,
+/// it returns a [`fmt::Result`][core::fmt::Result]. For
+/// [`io::Write`][std::io::Write] destinations, use [`io_writeln_indented!`][crate::io_writeln_indented].
+///
+/// [`fmt::IndentWriter`]: crate::fmt::IndentWriter
+///
+/// # Example
+///
+/// ```
+/// use indent_write::writeln_indented;
+///
+/// let mut output = String::new();
+/// writeln_indented!(output, "  ", "{} = {}", "key", "value\n\nmore").unwrap();
+/// writeln_indented!(output, "  ").unwrap();
+///
+/// assert_eq!(output, "  key = value\n\n  more\n\n");
+/// ```
+#[macro_export]
+macro_rules! writeln_indented {
+    ($dst:expr, $indent:expr $(,)?) => {
+        $crate::write_indented!($dst, $indent, "\n")
+    };
+    ($dst:expr, $indent:expr, $($arg:tt)*) => {
+        $crate::write_indented!($dst, $indent, "{}\n", ::core::format_args!($($arg)*))
+    };
+}
+
+/// Write formatted content to an [`io::Write`][std::io::Write] object,
+/// indenting each non-empty line with the given indent.
+///
+/// This is equivalent to calling [`write!`] on an [`io::IndentWriter`]
+/// wrapping the destination, for just this one statement. Like `write!`, it
+/// returns an [`io::Result`][std::io::Result].
+///
+/// This macro requires the `std` feature.
+///
+/// [`io::IndentWriter`]: crate::io::IndentWriter
+///
+/// # Example
+///
+/// ```
+/// use indent_write::io_write_indented;
+///
+/// let mut output = Vec::new();
+/// io_write_indented!(output, "  ", "{}\n{}", "Line 1", "Line 2").unwrap();
+///
+/// assert_eq!(output, b"  Line 1\n  Line 2");
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! io_write_indented {
+    ($dst:expr, $indent:expr, $($arg:tt)*) => {{
+        use $crate::__private::Reborrow as _;
+        ::std::io::Write::write_fmt(
+            &mut $crate::io::IndentWriter::new($indent, $dst.__indent_write_reborrow()),
+            ::core::format_args!($($arg)*),
+        )
+    }};
+}
+
+/// Write formatted content, followed by a newline, to an
+/// [`io::Write`][std::io::Write] object, indenting each non-empty line with
+/// the given indent.
+///
+/// This is equivalent to calling [`writeln!`] on an [`io::IndentWriter`]
+/// wrapping the destination, for just this one statement. Like `writeln!`,
+/// it returns an [`io::Result`][std::io::Result].
+///
+/// This macro requires the `std` feature.
+///
+/// [`io::IndentWriter`]: crate::io::IndentWriter
+///
+/// # Example
+///
+/// ```
+/// use indent_write::io_writeln_indented;
+///
+/// let mut output = Vec::new();
+/// io_writeln_indented!(output, "\t", "{}:\n{}", "key", "value").unwrap();
+///
+/// assert_eq!(output, b"\tkey:\n\tvalue\n");
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! io_writeln_indented {
+    ($dst:expr, $indent:expr $(,)?) => {
+        $crate::io_write_indented!($dst, $indent, "\n")
+    };
+    ($dst:expr, $indent:expr, $($arg:tt)*) => {
+        $crate::io_write_indented!($dst, $indent, "{}\n", ::core::format_args!($($arg)*))
+    };
+}
+
+/// Create a [`String`][alloc::string::String] from formatted content,
+/// indenting each non-empty line with the given indent. This is a wrapper
+/// around [`fmt::format_indented`][crate::fmt::format_indented].
+///
+/// This macro requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::indent_format;
+///
+/// let output = indent_format!("    ", "{}\n\n{}\n", "Line 1", "Line 3");
+///
+/// assert_eq!(output, "    Line 1\n\n    Line 3\n");
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! indent_format {
+    ($indent:expr, $($arg:tt)*) => {
+        $crate::fmt::format_indented($indent, ::core::format_args!($($arg)*))
+    };
+}
@@ -0,0 +1,156 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Indent a string, inserting `prefix` before each non-empty line.
+///
+/// This produces exactly the same output as writing `text` to a
+/// [`fmt::IndentWriter`][crate::fmt::IndentWriter]: empty lines are left
+/// untouched, and no prefix is added after a trailing newline. If the result
+/// would be identical to `text`, such as when `prefix` is empty or `text`
+/// contains only newlines, it is returned without allocating.
+///
+/// This function requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Cow;
+/// use indent_write::indent_str;
+///
+/// assert_eq!(indent_str("Line 1\n\nLine 2\n", "  "), "  Line 1\n\n  Line 2\n");
+/// assert!(matches!(indent_str("\n\n", "  "), Cow::Borrowed("\n\n")));
+/// ```
+pub fn indent_str<'a>(text: &'a str, prefix: &str) -> Cow<'a, str> {
+    let is_content = |line: &&str| *line != "\n";
+    let count = text.split_inclusive('\n').filter(is_content).count();
+
+    if prefix.is_empty() || count == 0 {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len() + count * prefix.len());
+
+    for line in text.split_inclusive('\n') {
+        if is_content(&line) {
+            output.push_str(prefix);
+        }
+        output.push_str(line);
+    }
+
+    Cow::Owned(output)
+}
+
+/// Remove the longest common leading whitespace from each line of a string.
+///
+/// The common prefix is computed from the leading spaces and tabs of each
+/// line that contains anything other than whitespace; lines that are blank
+/// don't contribute to it. The prefix is compared byte-for-byte, so a line
+/// indented with a tab and a line indented with spaces have nothing in
+/// common. Blank lines have as much of the prefix removed as they contain,
+/// so they remain blank. If there is nothing to remove, `text` is returned
+/// without allocating.
+///
+/// This is useful for cleaning up indented multi-line string literals
+/// before indenting them with [`indent_str`] or an `IndentWriter`.
+///
+/// This function requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::dedent_str;
+///
+/// let text = "
+///     fn main() {
+///         println!();
+///     }
+/// ";
+///
+/// assert_eq!(dedent_str(text), "\nfn main() {\n    println!();\n}\n");
+/// ```
+pub fn dedent_str(text: &str) -> Cow<'_, str> {
+    let common = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(leading_whitespace)
+        .fold(None, |common: Option<&str>, ws| {
+            Some(match common {
+                None => ws,
+                Some(common) => common_prefix(common, ws),
+            })
+        })
+        .unwrap_or("");
+
+    if common.is_empty() {
+        return Cow::Borrowed(text);
+    }
+
+    let output = text
+        .split_inclusive('\n')
+        .map(|line| match line.strip_prefix(common) {
+            Some(rest) => rest,
+            None => &line[common_prefix(common, leading_whitespace(line)).len()..],
+        })
+        .collect();
+
+    Cow::Owned(output)
+}
+
+/// Remove the margin from each line of a string: any leading whitespace,
+/// followed by the `margin` character.
+///
+/// This is the same as Scala's `stripMargin`. It's useful for multi-line
+/// string literals that mark the start of each line with a margin character
+/// like `|`, so that their content can have its own leading whitespace.
+/// Lines that don't start with the margin (after any leading whitespace) are
+/// left unchanged. If there is nothing to remove, `text` is returned without
+/// allocating.
+///
+/// This function requires the `alloc` feature.
+///
+/// # Example
+///
+/// ```
+/// use indent_write::strip_margin;
+///
+/// let text = "fn main() {
+///            |    println!(\"a | b\");
+///            |}";
+///
+/// assert_eq!(strip_margin(text, '|'), "fn main() {\n    println!(\"a | b\");\n}");
+/// ```
+pub fn strip_margin(text: &str, margin: char) -> Cow<'_, str> {
+    if text
+        .split_inclusive('\n')
+        .all(|line| strip_line_margin(line, margin).is_none())
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let output = text
+        .split_inclusive('\n')
+        .map(|line| strip_line_margin(line, margin).unwrap_or(line))
+        .collect();
+
+    Cow::Owned(output)
+}
+
+/// The rest of `line` after its leading spaces and tabs and `margin`, if it
+/// has a margin.
+fn strip_line_margin(line: &str, margin: char) -> Option<&str> {
+    line.trim_start_matches(&[' ', '\t'][..])
+        .strip_prefix(margin)
+}
+
+/// The leading spaces and tabs of `line`.
+fn leading_whitespace(line: &str) -> &str {
+    let len = line.len() - line.trim_start_matches(&[' ', '\t'][..]).len();
+    &line[..len]
+}
+
+/// The longest common prefix of two strings of ASCII whitespace.
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count();
+
+    &a[..len]
+}
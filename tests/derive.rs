@@ -0,0 +1,132 @@
+#![cfg(feature = "derive")]
+
+use std::fmt::{self, Display};
+
+use indent_write::indentable::{IndentFields, Indentable};
+use indent_write::IndentDisplay;
+
+#[derive(IndentDisplay)]
+struct Unit;
+
+#[derive(IndentDisplay)]
+struct Point(i32, #[indent(skip)] i32, pub i32);
+
+#[derive(IndentDisplay)]
+struct Line {
+    start: Point,
+    end: Point,
+}
+
+#[derive(IndentDisplay)]
+enum Shape {
+    Empty,
+    Dot(Point),
+    Segment {
+        #[indent(flatten)]
+        line: Line,
+        r#type: &'static str,
+    },
+}
+
+#[derive(IndentDisplay)]
+#[indent(prefix = "\t")]
+pub(crate) struct Drawing<'a, T>
+where
+    T: Copy,
+{
+    pub title: &'a str,
+    shapes: Shapes<'a>,
+    layer: T,
+    #[indent(skip)]
+    _hidden: std::marker::PhantomData<fn() -> T>,
+}
+
+#[derive(IndentDisplay)]
+struct Buffer<const N: usize> {
+    #[indent(skip)]
+    _data: [u8; N],
+    len: usize,
+}
+
+// Displayed with a hand-written impl, to check that derived types nest inside
+// other types' output
+struct Shapes<'a>(&'a [Shape]);
+
+impl Display for Shapes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.len())?;
+        for shape in self.0 {
+            write!(f, "\n{}", shape.indented("- "))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_derive_struct() {
+    assert_eq!(Unit.to_string(), "Unit");
+    assert_eq!(Point(1, 2, 3).to_string(), "Point\n  0: 1\n  2: 3");
+    assert_eq!(
+        Line {
+            start: Point(1, 2, 3),
+            end: Point(4, 5, 6),
+        }
+        .to_string(),
+        "Line\n  start: Point\n    0: 1\n    2: 3\n  end: Point\n    0: 4\n    2: 6"
+    );
+}
+
+#[test]
+fn test_derive_enum() {
+    assert_eq!(Shape::Empty.to_string(), "Empty");
+    assert_eq!(
+        Shape::Dot(Point(1, 0, 2)).to_string(),
+        "Dot\n  0: Point\n    0: 1\n    2: 2"
+    );
+    assert_eq!(
+        Shape::Segment {
+            line: Line {
+                start: Point(1, 0, 1),
+                end: Point(2, 0, 2),
+            },
+            r#type: "dashed",
+        }
+        .to_string(),
+        "Segment\n  start: Point\n    0: 1\n    2: 1\n  end: Point\n    0: 2\n    2: 2\n  type: dashed"
+    );
+}
+
+#[test]
+fn test_derive_generics() {
+    let shapes = [Shape::Empty, Shape::Dot(Point(1, 0, 2))];
+    let drawing = Drawing {
+        title: "demo",
+        shapes: Shapes(&shapes),
+        layer: 'x',
+        _hidden: std::marker::PhantomData,
+    };
+
+    assert_eq!(
+        drawing.to_string(),
+        "Drawing\n\ttitle: demo\n\tshapes: 2\n\t- Empty\n\t- Dot\n\t-   0: Point\n\t-     0: 1\n\t-     2: 2\n\tlayer: x"
+    );
+    assert_eq!(
+        Buffer {
+            _data: [0; 4],
+            len: 4
+        }
+        .to_string(),
+        "Buffer\n  len: 4"
+    );
+}
+
+#[test]
+fn test_derive_fields() {
+    let point = Point(1, 2, 3);
+    let mut fields = String::new();
+    point.fmt_fields(&mut fields).unwrap();
+
+    // Skipped fields aren't written
+    assert_eq!(fields, "\n0: 1\n2: 3");
+    assert_eq!(point.1, 2);
+}
@@ -1,9 +1,16 @@
+#![allow(
+    clippy::redundant_static_lifetimes,
+    clippy::write_literal,
+    clippy::write_with_newline,
+    clippy::writeln_empty_string
+)]
+
 use std::fmt::{self, Write};
 
-use indent_write::fmt::IndentWriter;
+use indent_write::fmt::{Indent, IndentWriter, ListItemWriter, QuoteWriter};
 
 // This is a wrapper for fmt::Write that only writes one char at a time, to test
-// the invariants of IndentableWrite
+// the invariants of IndentWriter
 #[derive(Debug, Clone)]
 struct OneByteAtATime<W>(W);
 
@@ -136,3 +143,2233 @@ fn test_partial_writes_combined() {
     }
     assert_eq!(dest, "    Hello, World\n    😀 😀 😀\n    😀 😀 😀\n");
 }
+
+#[test]
+fn test_needs_indent_tracking() {
+    let mut writer = IndentWriter::new("\t", String::new());
+    assert!(writer.needs_indent());
+
+    writer.write_str("\n\n").unwrap();
+    assert!(writer.needs_indent());
+
+    writer.write_str("😀").unwrap();
+    assert!(!writer.needs_indent());
+
+    writer.write_char('\n').unwrap();
+    assert!(writer.needs_indent());
+
+    let writer = IndentWriter::new_skip_initial("\t", String::new());
+    assert!(!writer.needs_indent());
+}
+
+#[test]
+fn test_line_column_tracking() {
+    let mut writer = IndentWriter::new("    ", String::new());
+    assert_eq!((writer.line(), writer.column()), (0, 0));
+
+    writer.write_str("😀 😀\n\n").unwrap();
+    assert_eq!((writer.line(), writer.column()), (2, 0));
+
+    writer.write_str("ab").unwrap();
+    assert_eq!((writer.line(), writer.column()), (2, 6));
+
+    writer.write_char('c').unwrap();
+    assert_eq!((writer.line(), writer.column()), (2, 7));
+
+    writer.write_char('\n').unwrap();
+    writer.write_char('\n').unwrap();
+    assert_eq!((writer.line(), writer.column()), (4, 0));
+
+    writer.write_str("x\ny").unwrap();
+    assert_eq!((writer.line(), writer.column()), (5, 5));
+}
+
+#[test]
+fn test_line_column_tracking_nested() {
+    let mut dest = String::new();
+    let mut outer = IndentWriter::new("\t", &mut dest);
+    {
+        let mut inner = IndentWriter::new("\t", OneByteAtATime(&mut outer));
+
+        writeln!(inner, "{}", "😀 😀").unwrap();
+        write!(inner, "{}", "😀").unwrap();
+
+        // Each writer tracks the position in its own output
+        assert_eq!((inner.line(), inner.column()), (1, 2));
+    }
+    assert_eq!((outer.line(), outer.column()), (1, 3));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_trim_prefix_on_blank_lines() {
+    let mut writer = IndentWriter::new("    // ", String::new()).trim_prefix_on_blank_lines(true);
+
+    write!(writer, "😀\n \n\n\t 😀 \n").unwrap();
+    assert_eq!(writer.get_ref(), "    // 😀\n    // \n\n    // \t 😀 \n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_trim_prefix_on_blank_lines_split() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::new("    // ", &mut dest).trim_prefix_on_blank_lines(true);
+        let mut writer = OneByteAtATime(writer);
+
+        write!(writer, "{}\n  \n\n  {}\n  ", "😀", "😀").unwrap();
+        writer.0.flush().unwrap();
+    }
+    assert_eq!(dest, "    // 😀\n    //  \n\n    //   😀\n    //  ");
+}
+
+#[test]
+fn test_max_width() {
+    test_harness!(w => IndentWriter::with_max_width("    ", 7, w), expect: "    \t😀 \n    😀 😀\n    \t\t😀\n     😀 \n    😀\n    \t😀 \n    😀 😀\n")
+}
+
+#[test]
+fn test_max_width_partial_writes() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::with_max_width("> ", 4, &mut dest);
+        let mut writer = OneByteAtATime(writer);
+
+        writeln!(writer, "{}", "😀😀😀😀😀").unwrap();
+        writeln!(writer, "{}", "ab\n\nabcd").unwrap();
+    }
+    assert_eq!(dest, "> 😀😀\n> 😀😀\n> 😀\n> ab\n\n> ab\n> cd\n");
+}
+
+#[test]
+fn test_max_width_wide_indent() {
+    let mut writer = IndentWriter::with_max_width("    ", 2, String::new());
+    write!(writer, "abc\n").unwrap();
+    assert_eq!(writer.get_ref(), "    a\n    b\n    c\n");
+}
+
+#[cfg(feature = "wrap")]
+#[test]
+fn test_word_wrap() {
+    let mut dest = String::new();
+    {
+        let mut writer = IndentWriter::with_word_wrap("    ", 10, &mut dest);
+        for line in CONTENT {
+            writeln!(writer, "{}", line.trim_start()).unwrap();
+        }
+    }
+
+    // Each emoji is two columns wide
+    assert_eq!(
+        dest,
+        "    😀 😀\n    😀\n    😀 😀\n    😀\n    😀 😀\n    😀\n"
+    );
+}
+
+#[cfg(feature = "wrap")]
+#[test]
+fn test_word_wrap_partial_writes() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::with_word_wrap("> ", 8, &mut dest);
+        let mut writer = OneByteAtATime(writer);
+
+        writeln!(writer, "{}", "ab cd ef gh").unwrap();
+        writeln!(writer, "{}", "abcdefghijklmn\n\n  abc  def").unwrap();
+        write!(writer, "{}", "日本語のテキスト").unwrap();
+        writer.0.flush().unwrap();
+    }
+    assert_eq!(
+        dest,
+        "> ab cd\n> ef gh\n> abcdef\n> ghijkl\n> mn\n\n>   abc\n> def\n> 日本語\n> のテキ\n> スト"
+    );
+}
+
+#[cfg(feature = "wrap")]
+#[test]
+fn test_comment_wrap() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::with_comment_wrap("/// ", 12, &mut dest);
+        let mut writer = OneByteAtATime(writer);
+
+        writeln!(writer, "{}", "😀 😀 😀 😀 😀\n\n\n😀").unwrap();
+        writeln!(writer, "{}", "abcdefghijkl").unwrap();
+    }
+
+    // Each emoji is two columns wide, leaving 8 columns for content
+    assert_eq!(
+        dest,
+        "/// 😀 😀 😀\n/// 😀 😀\n///\n///\n/// 😀\n/// abcdefgh\n/// ijkl\n"
+    );
+}
+
+#[test]
+fn test_expand_tabs() {
+    test_harness!(w => IndentWriter::new("  ", w).expand_tabs(4), expect: "    😀 😀 😀\n        😀 😀 😀\n    😀 😀 😀\n")
+}
+
+#[test]
+fn test_expand_tabs_partial_writes() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::new("\t", &mut dest).expand_tabs(8);
+        let mut writer = OneByteAtATime(writer);
+
+        writeln!(writer, "{}", "a\tb").unwrap();
+        write!(writer, "{}", "😀😀\t").unwrap();
+        writeln!(writer, "{}", "\tc").unwrap();
+    }
+    assert_eq!(dest, "\ta       b\n\t😀😀              c\n");
+}
+
+#[test]
+fn test_expand_tabs_max_width() {
+    // Expanded tabs count toward the width
+    let mut writer = IndentWriter::with_max_width("", 4, String::new()).expand_tabs(4);
+    writer.write_str("a\tbcdefgh\n").unwrap();
+    assert_eq!(writer.get_ref(), "a   \nbcde\nfgh\n");
+
+    // The output doesn't depend on how the content is split
+    for width in 4..12 {
+        let content = "ab\tcdefgh\n\t\tx\ty\n";
+
+        let mut by_str = IndentWriter::with_max_width("> ", width, String::new()).expand_tabs(4);
+        by_str.write_str(content).unwrap();
+
+        let mut by_char = IndentWriter::with_max_width("> ", width, String::new()).expand_tabs(4);
+        content.chars().for_each(|c| by_char.write_char(c).unwrap());
+
+        assert_eq!(by_char.get_ref(), by_str.get_ref(), "width: {}", width);
+        for line in by_str.get_ref().lines() {
+            assert!(line.len() <= width, "{:?}, width: {}", line, width);
+        }
+    }
+}
+
+#[test]
+fn test_indent_spaces_and_tabs() {
+    test_harness!(w => IndentWriter::spaces(4, w), expect: "    \t😀 😀 😀\n    \t\t😀 😀 😀\n    \t😀 😀 😀\n");
+    test_harness!(w => IndentWriter::tabs(1, w), expect: "\t\t😀 😀 😀\n\t\t\t😀 😀 😀\n\t\t😀 😀 😀\n");
+}
+
+#[test]
+fn test_indent_large_count() {
+    let mut dest = String::new();
+    {
+        let writer = OneByteAtATime(&mut dest);
+        let mut writer = IndentWriter::new(Indent::Spaces(40), writer);
+        write!(writer, "{}", "😀\n\n😀").unwrap();
+    }
+
+    let indent = " ".repeat(40);
+    assert_eq!(dest, format!("{0}😀\n\n{0}😀", indent));
+    assert_eq!(Indent::Tabs(20).to_string(), "\t".repeat(20));
+}
+
+#[test]
+fn test_builder_skip_initial() {
+    let builder = IndentWriter::builder("    ").initial_indent(false);
+    test_harness!(w => builder.build(w), expect: "\t😀 😀 😀\n    \t\t😀 😀 😀\n    \t😀 😀 😀\n")
+}
+
+#[test]
+fn test_builder_indent_empty_lines() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::builder("> ")
+            .indent_empty_lines(true)
+            .first_line_prefix("- ")
+            .build(&mut dest);
+        let mut writer = OneByteAtATime(writer);
+
+        write!(writer, "{}", "\n\n😀\n\n").unwrap();
+    }
+    assert_eq!(dest, "- \n> \n> 😀\n> \n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_builder_indent_empty_lines_trimmed() {
+    let mut writer = IndentWriter::builder("// ")
+        .indent_empty_lines(true)
+        .trim_prefix_on_blank_lines(true)
+        .build(String::new());
+
+    write!(writer, "a\n\n \nb\n").unwrap();
+    assert_eq!(writer.get_ref(), "// a\n//\n// \n// b\n");
+}
+
+// A writer that counts the number of calls to write_str
+#[derive(Debug)]
+struct CountingWriter<'a, W> {
+    writer: W,
+    count: &'a std::cell::Cell<usize>,
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.count.set(self.count.get() + 1);
+        self.writer.write_str(s)
+    }
+}
+
+fn write_nesting_content(w: &mut impl Write) {
+    write!(w, "{}\n\n{}", "😀 😀", "\t😀\n\n").unwrap();
+}
+
+#[test]
+fn test_nested_matches_nesting() {
+    let count = std::cell::Cell::new(0);
+
+    // Naive nesting, counting the calls made at every level
+    let mut naive = String::new();
+    {
+        let mut indent1 = IndentWriter::new(
+            "\t",
+            CountingWriter {
+                writer: &mut naive,
+                count: &count,
+            },
+        );
+        writeln!(indent1, "{}", "😀").unwrap();
+        {
+            let mut indent2 = IndentWriter::new(
+                "  ",
+                CountingWriter {
+                    writer: &mut indent1,
+                    count: &count,
+                },
+            );
+            write_nesting_content(&mut indent2);
+            {
+                let mut indent3 = IndentWriter::new(
+                    "> ",
+                    CountingWriter {
+                        writer: &mut indent2,
+                        count: &count,
+                    },
+                );
+                write_nesting_content(&mut indent3);
+            }
+            write_nesting_content(&mut indent2);
+        }
+    }
+    let naive_count = count.replace(0);
+
+    let mut flat = String::new();
+    {
+        let mut indent1 = IndentWriter::new(
+            "\t",
+            CountingWriter {
+                writer: &mut flat,
+                count: &count,
+            },
+        );
+        writeln!(indent1, "{}", "😀").unwrap();
+        {
+            let mut indent2 = indent1.nested("  ");
+            write_nesting_content(&mut indent2);
+            {
+                let mut indent3 = indent2.nested("> ");
+                write_nesting_content(&mut indent3);
+            }
+            write_nesting_content(&mut indent2);
+        }
+    }
+    let flat_count = count.get();
+
+    assert_eq!(flat, naive);
+    assert!(
+        flat_count * 3 < naive_count * 2,
+        "flattened: {}, naive: {}",
+        flat_count,
+        naive_count
+    );
+}
+
+#[test]
+fn test_nested_mid_line() {
+    let mut writer = IndentWriter::new("\t", String::new());
+    write!(writer, "key: ").unwrap();
+    {
+        let mut nested = writer.nested("  ");
+        write!(nested, "{}", "value\n\nmore\n").unwrap();
+    }
+    assert!(writer.needs_indent());
+    assert_eq!(writer.line(), 3);
+    assert_eq!(writer.get_ref(), "\tkey:   value\n\n\t  more\n");
+}
+
+#[test]
+fn test_line_suffix() {
+    test_harness!(w => IndentWriter::new("    ", w).line_suffix(" \\"), expect: "    \t😀 😀 😀 \\\n    \t\t😀 😀 😀 \\\n    \t😀 😀 😀 \\\n")
+}
+
+#[test]
+fn test_line_suffix_partial_writes() {
+    let mut dest = String::new();
+    {
+        let writer = IndentWriter::new("  ", &mut dest).line_suffix(" \\");
+        let mut writer = OneByteAtATime(writer);
+
+        write!(writer, "{}", "#define X(a)\n\n").unwrap();
+        write!(writer, "{}", "do {").unwrap();
+        writeln!(writer, "{}", " a; } while (0)").unwrap();
+        write!(writer, "{}", "last").unwrap();
+
+        writer.0.finish().unwrap();
+    }
+    assert_eq!(
+        dest,
+        "  #define X(a) \\\n\n  do { a; } while (0) \\\n  last\n"
+    );
+}
+
+#[test]
+fn test_block_error_skips_footer() {
+    let mut dest = String::new();
+    let mut writer = IndentWriter::new("  ", &mut dest);
+
+    let result = writer.block("{", "}", |body| {
+        writeln!(body, "{}", "😀")?;
+        Err(fmt::Error)
+    });
+
+    assert!(result.is_err());
+    assert_eq!(dest, "  {\n    😀\n");
+}
+
+#[test]
+fn test_block_partial_writes() {
+    let mut dest = String::new();
+    {
+        let mut writer = IndentWriter::new("\t", OneByteAtATime(&mut dest));
+        writer
+            .block("a {", "}", |body| {
+                body.write_str("b\n\nc\n")?;
+                body.block("d {", "}", |body| body.write_str("e\n"))
+            })
+            .unwrap();
+    }
+    assert_eq!(
+        dest,
+        "\ta {\n\t\tb\n\n\t\tc\n\t\td {\n\t\t\te\n\t\t}\n\t}\n"
+    );
+}
+
+#[test]
+fn test_writeln_indented_formatter() {
+    struct Item;
+
+    impl fmt::Display for Item {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            writeln!(f, "item:")?;
+            indent_write::writeln_indented!(f, "\t", "{}\n{}", "😀 😀", "😀")
+        }
+    }
+
+    assert_eq!(Item.to_string(), "item:\n\t😀 😀\n\t😀\n");
+}
+
+#[test]
+fn test_levels() {
+    test_harness!(w => IndentWriter::builder(Indent::Spaces(2)).level(2).build(w), expect: "    \t😀 😀 😀\n    \t\t😀 😀 😀\n    \t😀 😀 😀\n");
+
+    let mut writer = IndentWriter::new("> ", String::new());
+    {
+        let mut writer = writer.scoped_indent();
+        writeln!(writer, "{}", "😀\n\n😀").unwrap();
+        writer
+            .with_indent(|writer| write!(writer, "{}", "😀"))
+            .unwrap();
+        assert_eq!(writer.level(), 2);
+    }
+    writeln!(writer, "\n{}", "😀").unwrap();
+
+    assert_eq!(writer.get_ref(), "> > 😀\n\n> > 😀\n> > > 😀\n> 😀\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_levels_trimmed() {
+    let mut writer = IndentWriter::builder("// ")
+        .level(3)
+        .trim_prefix_on_blank_lines(true)
+        .build(String::new());
+
+    write!(writer, "a\n \n").unwrap();
+    writer.set_level(2);
+    write!(writer, "b\n").unwrap();
+
+    assert_eq!(writer.get_ref(), "// // // a\n// // // \n// // b\n");
+}
+
+#[test]
+fn test_scoped_indent_restored_after_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut writer = IndentWriter::new("\t", String::new());
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        writer.with_indent(|writer| {
+            writeln!(writer, "{}", "😀").unwrap();
+            panic!("oops");
+        })
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(writer.level(), 1);
+
+    writeln!(writer, "{}", "😀").unwrap();
+    assert_eq!(writer.get_ref(), "\t\t😀\n\t😀\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_shared_level_separate_writers() {
+    use indent_write::fmt::SharedLevel;
+
+    let level = SharedLevel::with_level(1);
+    let mut dest = String::new();
+
+    {
+        let mut writer = IndentWriter::with_shared_level("\t", level.clone(), &mut dest);
+        write!(writer, "{}", "😀 ").unwrap();
+    }
+    {
+        // This writer continues the line started by the previous one
+        let mut writer = IndentWriter::with_shared_level("\t", level.clone(), &mut dest);
+        assert!(!writer.needs_indent());
+        writeln!(writer, "{}", "😀").unwrap();
+
+        let _guard = level.indent();
+        writeln!(writer, "{}", "😀\n\n😀").unwrap();
+    }
+
+    assert_eq!(level.get(), 1);
+    assert_eq!(dest, "\t😀 😀\n\t\t😀\n\n\t\t😀\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_shared_level_no_double_indent() {
+    use indent_write::fmt::SharedLevel;
+
+    struct Item<'a>(&'a SharedLevel, &'a str);
+
+    impl fmt::Display for Item<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut writer = IndentWriter::with_shared_level("  ", self.0.clone(), f);
+            writeln!(writer, "{}", self.1)
+        }
+    }
+
+    let level = SharedLevel::new();
+    let mut dest = String::new();
+    {
+        let mut writer = IndentWriter::with_shared_level("  ", level.clone(), &mut dest);
+        writeln!(writer, "{}", Item(&level, "a\nb")).unwrap();
+
+        let _guard = level.indent();
+        write!(writer, "{}{}", Item(&level, "c"), Item(&level, "d\n\ne")).unwrap();
+    }
+
+    assert_eq!(dest, "a\nb\n\n  c\n  d\n\n  e\n");
+}
+
+#[test]
+fn test_indentable_nested() {
+    use indent_write::indentable::Indentable;
+
+    struct Report;
+
+    impl fmt::Display for Report {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            writeln!(f, "error: failed")?;
+            writeln!(f, "caused by:")?;
+            write!(f, "{}", "io error\n\nretry\n".indented("  "))
+        }
+    }
+
+    assert_eq!(
+        format!("{}", Report.indented("| ")),
+        "| error: failed\n| caused by:\n|   io error\n\n|   retry\n"
+    );
+    assert_eq!(
+        format!("note: {}", Report.indented_skip_initial("      ")),
+        "note: error: failed\n      caused by:\n        io error\n\n        retry\n"
+    );
+}
+
+#[test]
+fn test_indented_by_width() {
+    use indent_write::indentable::Indentable;
+
+    // A tree that receives its depth through the format string
+    struct Node(&'static str, &'static [Node]);
+
+    impl fmt::Display for Node {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let depth = f.width().unwrap_or(0);
+            writeln!(f, "{:1$}", self.0.indented_by_width(), depth)?;
+            self.1
+                .iter()
+                .try_for_each(|child| write!(f, "{:1$}", child, depth + 2))
+        }
+    }
+
+    let tree = Node("root", &[Node("a", &[Node("b", &[])]), Node("c\nd", &[])]);
+
+    assert_eq!(format!("{}", tree), "root\n  a\n    b\n  c\n  d\n");
+    assert_eq!(
+        format!("{:3}", tree),
+        "   root\n     a\n       b\n     c\n     d\n"
+    );
+    assert_eq!(
+        format!("{:<6.1}|", "x\n\ny".indented_by_width_with("\t")),
+        "\t\t\t\t\t\tx\n\n\t\t\t\t\t\ty|"
+    );
+    assert_eq!(format!("{:0}", "x".indented_by_width_with("\t")), "x");
+}
+
+#[test]
+fn test_join_indented() {
+    use indent_write::indentable::join_indented;
+
+    let empty: [&str; 0] = [];
+    assert_eq!(format!("{}", join_indented(&empty, ", ", "  ")), "");
+    assert_eq!(
+        format!("{}", join_indented(["a\nb"], ", ", "  ")),
+        "  a\n  b"
+    );
+
+    // Items ending in a newline don't double the separator's newline
+    assert_eq!(
+        format!("{}", join_indented(["a\n", "b", "c\n\n"], "\n", "> ")),
+        "> a\n> b\n> c\n\n"
+    );
+    assert_eq!(
+        format!(
+            "{}",
+            join_indented(["a\n", "b"], "\n\n", "> ").indent_separator(true)
+        ),
+        "> a\n\n> b"
+    );
+
+    // Any cloneable iterator works, and can be printed more than once
+    let joined = join_indented((1..=3).map(|i| i * 10), "\n", "\t");
+    assert_eq!(joined.to_string(), "\t10\n\t20\n\t30");
+    assert_eq!(joined.to_string(), "\t10\n\t20\n\t30");
+}
+
+#[test]
+fn test_indented_debug() {
+    use indent_write::indentable::IndentableDebug;
+
+    let value = vec![Some("😀"), None];
+
+    assert_eq!(
+        format!("{}", (&value).indented_debug("\t")),
+        "\t[\n\t    Some(\n\t        \"😀\",\n\t    ),\n\t    None,\n\t]"
+    );
+    assert_eq!(
+        format!("{:?}", (&value[1..]).indented_debug("\t")),
+        "\t[\n\t    None,\n\t]"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_format_indented() {
+    use indent_write::fmt::format_indented;
+
+    let output = format_indented(
+        Indent::Tabs(1),
+        format_args!("{}\n\n{}", CONTENT[0], CONTENT[1]),
+    );
+    assert_eq!(output, "\t\t😀 😀 😀\n\n\t\t\t😀 😀 😀");
+
+    assert_eq!(format_indented("  ", format_args!("")), "");
+    assert_eq!(format_indented("  ", format_args!("\n\n")), "\n\n");
+}
+
+/// Call `func` with every string of up to `len` characters drawn from
+/// `alphabet`.
+#[cfg(feature = "alloc")]
+fn for_each_string(alphabet: &[char], len: usize, func: &mut impl FnMut(&str)) {
+    fn recurse(alphabet: &[char], len: usize, buf: &mut String, func: &mut impl FnMut(&str)) {
+        func(buf);
+
+        if len > 0 {
+            for &c in alphabet {
+                buf.push(c);
+                recurse(alphabet, len - 1, buf, func);
+                buf.pop();
+            }
+        }
+    }
+
+    recurse(alphabet, len, &mut String::new(), func)
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_indent_str_matches_writer() {
+    use indent_write::indent_str;
+
+    for prefix in ["", "\t", "> "] {
+        for_each_string(&['😀', ' ', '\n', '\r'], 6, &mut |text| {
+            let mut writer = IndentWriter::new(prefix, String::new());
+            writer.write_str(text).unwrap();
+
+            assert_eq!(
+                indent_str(text, prefix),
+                writer.get_ref().as_str(),
+                "text: {:?}, prefix: {:?}",
+                text,
+                prefix
+            );
+        });
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_dedent_str() {
+    use std::borrow::Cow;
+
+    use indent_write::dedent_str;
+
+    assert_eq!(dedent_str("    a\n\n      b\n    c"), "a\n\n  b\nc");
+    assert_eq!(dedent_str("  a\n \n      \n  b\n"), "a\n\n    \nb\n");
+    assert_eq!(dedent_str("\t  a\r\n\t b\r\n"), " a\r\nb\r\n");
+    assert_eq!(dedent_str("\ta\n    b\n"), "\ta\n    b\n");
+
+    assert!(matches!(dedent_str("a\n  b\n"), Cow::Borrowed(_)));
+    assert!(matches!(dedent_str("  \n\n"), Cow::Borrowed(_)));
+    assert!(matches!(dedent_str(""), Cow::Borrowed(_)));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_strip_margin() {
+    use std::borrow::Cow;
+
+    use indent_write::strip_margin;
+
+    assert_eq!(strip_margin("  |a\n\t| b\n|\n", '|'), "a\n b\n\n");
+    assert_eq!(strip_margin("a\n  |b\nc | d\n", '|'), "a\nb\nc | d\n");
+    assert_eq!(strip_margin("  ||a\r\n  #b\r\n", '|'), "|a\r\n  #b\r\n");
+    assert_eq!(strip_margin("  \u{2502}a\n", '\u{2502}'), "a\n");
+
+    assert!(matches!(
+        strip_margin("a\n  b | c\n", '|'),
+        Cow::Borrowed(_)
+    ));
+    assert!(matches!(strip_margin("", '|'), Cow::Borrowed(_)));
+}
+
+#[test]
+fn test_dedent_writer() {
+    use indent_write::fmt::DedentWriter;
+
+    let mut writer = DedentWriter::new("\t\t", String::new());
+    write!(writer, "\t\t\t😀\n\t😀\n\n\t\t😀\t\t\n\t").unwrap();
+    writer.flush().unwrap();
+
+    assert_eq!(writer.get_ref(), "\t😀\n\t😀\n\n😀\t\t\n\t");
+}
+
+#[test]
+fn test_dedent_writer_partial_writes() {
+    use indent_write::fmt::DedentWriter;
+
+    // A multi-byte prefix split across writes, and a line that matches part
+    // of the prefix before diverging from it
+    let mut writer = DedentWriter::new("😀 ", OneByteAtATime(String::new()));
+    write!(writer, "😀 a\n😀b\n😀\n 😀 😀 c\n😀").unwrap();
+    assert_eq!(writer.get_ref().0, "a\n😀b\n😀\n 😀 😀 c\n");
+
+    writer.flush().unwrap();
+    assert_eq!(writer.get_ref().0, "a\n😀b\n😀\n 😀 😀 c\n😀");
+
+    let mut dest = String::new();
+    {
+        let mut writer = DedentWriter::new("    ", &mut dest);
+        for piece in &["  ", "  a\n ", " ", "\n  ", "", "  ", " b\n"] {
+            writer.write_str(piece).unwrap();
+        }
+    }
+    assert_eq!(dest, "a\n  \n b\n");
+}
+
+// A writer that panics on the `n`th call to `write_str`, counting from 1
+#[derive(Debug, Default)]
+struct PanicOnNth {
+    dest: String,
+    calls: usize,
+    n: usize,
+}
+
+impl PanicOnNth {
+    fn new(n: usize) -> Self {
+        Self {
+            n,
+            ..Self::default()
+        }
+    }
+}
+
+impl Write for PanicOnNth {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.calls += 1;
+        if self.calls == self.n {
+            panic!("write {} failed", self.n);
+        }
+
+        self.dest.push_str(s);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_resume_after_panic_in_indent() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // A deep indent of spaces is written from the cache in one piece, so
+    // use a color to split it: a reset, the indent, and the restored color.
+    // The panic happens after the first or second of them.
+    for n in 4..=5 {
+        let mut writer = IndentWriter::new(Indent::Spaces(10), PanicOnNth::new(n)).ansi_aware(true);
+        writer.set_level(2);
+        write!(writer, "{}\n", "\x1b[31m😀").unwrap();
+
+        let result = catch_unwind(AssertUnwindSafe(|| write!(writer, "{}\n", "😀")));
+        assert!(result.is_err());
+
+        write!(writer, "{}\n", "😀").unwrap();
+        assert_eq!(
+            writer.get_ref().dest,
+            format!("{0}\x1b[31m😀\n\x1b[0m{0}\x1b[31m😀\n", " ".repeat(20)),
+            "n: {}",
+            n,
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ansi_aware_resume_after_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // With a color in effect, the indent is written in three pieces: a
+    // reset, the indent, and the restored color. The panic happens after
+    // the first or second of them.
+    for n in 4..=5 {
+        let mut writer = IndentWriter::new("> ", PanicOnNth::new(n)).ansi_aware(true);
+        write!(writer, "{}\n", "\x1b[31m😀").unwrap();
+
+        let result = catch_unwind(AssertUnwindSafe(|| write!(writer, "{}\n", "😀")));
+        assert!(result.is_err());
+
+        write!(writer, "{}\n", "😀").unwrap();
+        assert_eq!(
+            writer.get_ref().dest,
+            "> \x1b[31m😀\n\x1b[0m> \x1b[31m😀\n",
+            "n: {}",
+            n
+        );
+    }
+}
+
+#[test]
+fn test_resume_after_panic_in_suffix() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut writer = IndentWriter::new("\t", PanicOnNth::new(4)).line_suffix(" \\");
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        writer
+            .write_str("😀\n")
+            .and_then(|()| writer.write_char('\n'))
+    }));
+    assert!(result.is_err());
+    assert_eq!(writer.get_ref().dest, "\t😀 \\");
+
+    writer.write_char('\n').unwrap();
+    writer.write_str("😀\n").unwrap();
+    assert_eq!(writer.get_ref().dest, "\t😀 \\\n\t😀 \\\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_resume_after_panic_in_held_back_whitespace() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut writer = IndentWriter::new("> ", PanicOnNth::new(2)).trim_prefix_on_blank_lines(true);
+
+    // The whitespace is held back by the first write, and the panic happens
+    // while writing it out after the indent.
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        writer
+            .write_str("  ")
+            .and_then(|()| writer.write_str("😀\n"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(writer.get_ref().dest, "> ");
+
+    writer.write_str("😀\n  \n").unwrap();
+    assert_eq!(writer.get_ref().dest, ">   😀\n>  \n");
+}
+
+// A writer that fails the `n`th call to `write_str` or `write_char`,
+// counting from 1, and succeeds otherwise
+#[derive(Debug, Default)]
+struct FailOnNth {
+    dest: String,
+    calls: usize,
+    n: usize,
+}
+
+impl Write for FailOnNth {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.calls += 1;
+        if self.calls == self.n {
+            return Err(fmt::Error);
+        }
+
+        self.dest.push_str(s);
+        Ok(())
+    }
+}
+
+// Write `content` in pieces, retrying the remainder of each piece after an
+// error, and check that the output is unaffected by every possible failure.
+fn check_retries<'i>(make_writer: impl Fn(FailOnNth) -> IndentWriter<'i, FailOnNth>) {
+    let pieces: &[&str] = &["\t😀 ", "😀\n\n  \t", "😀\t\t", " ", "\n", "😀\n😀"];
+
+    let write_all = |n: usize| {
+        let mut writer = make_writer(FailOnNth {
+            n,
+            ..FailOnNth::default()
+        });
+
+        for &piece in pieces {
+            let mut piece = piece;
+            while writer.write_str(piece).is_err() {
+                piece = &piece[writer.last_write_accepted()..];
+            }
+        }
+
+        let calls = writer.get_ref().calls;
+        (writer.into_inner().dest, calls)
+    };
+
+    let (expected, calls) = write_all(0);
+
+    for n in 1..=calls {
+        assert_eq!(write_all(n).0, expected, "failing call {}", n);
+    }
+}
+
+#[test]
+fn test_retry_after_error() {
+    check_retries(|w| IndentWriter::new("> ", w));
+    check_retries(|w| {
+        let mut writer = IndentWriter::new(Indent::Spaces(20), w).line_suffix(" |");
+        writer.set_level(2);
+        writer
+    });
+    check_retries(|w| IndentWriter::new("\t", w).expand_tabs(4));
+    check_retries(|w| IndentWriter::with_max_width("> ", 5, w));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_retry_after_error_trimmed() {
+    check_retries(|w| {
+        IndentWriter::new("> ", w)
+            .trim_prefix_on_blank_lines(true)
+            .expand_tabs(3)
+    });
+}
+
+#[test]
+fn test_prefix_with_newline() {
+    let mut writer = IndentWriter::new("\n  ", String::new());
+    write!(writer, "{}\n\n{}", "😀", "😀 😀").unwrap();
+
+    assert_eq!(writer.get_ref(), "\n  😀\n\n\n  😀 😀");
+    assert_eq!((writer.line(), writer.column()), (4, 5));
+
+    writer.set_level(2);
+    writer.write_str("\n😀\n").unwrap();
+    assert_eq!(writer.get_ref(), "\n  😀\n\n\n  😀 😀\n\n  \n  😀\n");
+
+    check_retries(|w| {
+        let mut writer = IndentWriter::new("\n> ", w).line_suffix(" |");
+        writer.set_level(2);
+        writer
+    });
+}
+
+#[test]
+fn test_indented_ignores_formatting_options() {
+    use indent_write::indentable::{Indentable, IndentableDebug};
+
+    // Without `padded`, the formatting options are ignored regardless of
+    // the enabled features
+    let content = "😀\n\n😀 😀 😀\n";
+    assert_eq!(
+        format!("{:->12.3}", content.indented("\t")),
+        "\t😀\n\n\t😀 😀 😀\n"
+    );
+    assert_eq!(
+        format!("{:*^5}", content.indented_skip_initial("\t")),
+        "😀\n\n\t😀 😀 😀\n"
+    );
+    assert_eq!(
+        format!("{:>4}", Some(1).indented_debug("")),
+        "Some(\n    1,\n)"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_indented_padded() {
+    use indent_write::indentable::{Indentable, IndentableDebug};
+
+    let content = "😀\n\n😀 😀 😀\n";
+
+    assert_eq!(
+        format!(
+            "{:->12.10}",
+            "Line 1\nLong line 2\n\nLine 3".indented("  ").padded()
+        ),
+        "----  Line 1\n--  Long lin\n\n----  Line 3"
+    );
+
+    assert_eq!(
+        format!("{:6}|", content.indented("\t").padded()),
+        "\t😀    \n\n\t😀 😀 😀\n|"
+    );
+    assert_eq!(
+        format!("{:*^5}", content.indented("\t").padded()),
+        "*\t😀**\n\n\t😀 😀 😀\n"
+    );
+    assert_eq!(
+        format!("{:.3}", content.indented_skip_initial("\t").padded()),
+        "😀\n\n\t😀 \n"
+    );
+    assert_eq!(
+        format!("{:>4}", Some(1).indented_debug("").padded()),
+        "Some(\n    1,\n   )"
+    );
+    assert_eq!(
+        format!("{:>4}", content.indented_once("-").padded()),
+        "  -😀\n\n-😀 😀 😀\n"
+    );
+
+    // Without a width or precision, the output is unchanged
+    assert_eq!(
+        format!("{}", content.indented("\t").padded()),
+        content.indented("\t").to_string()
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_indented_once_matches_indented() {
+    use indent_write::indentable::{Indentable, Padded};
+
+    let contents = [
+        "",
+        "\n",
+        "a",
+        "a\n",
+        "\n\na\n",
+        "😀\n\n😀 😀 😀\n",
+        "x\n  y\n\tz",
+    ];
+    let indents = ["", "  ", "\t", "> "];
+
+    for content in contents {
+        for indent in indents {
+            let expected = content.indented(indent);
+            let once = content.indented_once(indent);
+
+            assert_eq!(content.indented_to_string(indent), expected.to_string());
+            assert_eq!(once.rendered().unwrap(), expected.to_string());
+
+            // Printed twice, to check the replayed output as well
+            for _ in 0..2 {
+                assert_eq!(once.to_string(), expected.to_string());
+                assert_eq!(format!("{:>6}", once), format!("{:>6}", expected));
+                assert_eq!(format!("{:*^5.2}", once), format!("{:*^5.2}", expected));
+                assert_eq!(
+                    format!("{:*^5.2}", Padded { item: &once }),
+                    format!("{:*^5.2}", expected.padded()),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_indented_once_error() {
+    use indent_write::indentable::Indentable;
+
+    struct FailsOnce<'a>(&'a std::cell::Cell<bool>);
+
+    impl fmt::Display for FailsOnce<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a\nb")?;
+            match self.0.replace(false) {
+                true => Err(fmt::Error),
+                false => Ok(()),
+            }
+        }
+    }
+
+    let fail = std::cell::Cell::new(true);
+    let once = FailsOnce(&fail).indented_once("  ");
+    let mut dest = String::new();
+
+    // Nothing is written if rendering fails, and it's retried next time
+    assert!(write!(dest, "{}", once).is_err());
+    assert_eq!(dest, "");
+    write!(dest, "{}", once).unwrap();
+    assert_eq!(dest, "  a\n  b");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_write_fmt_single_pass() {
+    let count = std::cell::Cell::new(0);
+    let mut dest = String::new();
+
+    {
+        let mut writer = IndentWriter::new(
+            "\t",
+            CountingWriter {
+                writer: &mut dest,
+                count: &count,
+            },
+        );
+
+        writeln!(writer, "{} -> {}", "😀", 1).unwrap();
+        assert_eq!(count.replace(0), 2);
+
+        writeln!(writer, "{}\n\n{}", "😀", "😀").unwrap();
+        assert_eq!(count.replace(0), 5);
+    }
+
+    assert_eq!(dest, "\t😀 -> 1\n\t😀\n\n\t😀\n");
+}
+
+#[test]
+fn test_skip_already_indented() {
+    let mut writer = IndentWriter::new("> ", String::new()).skip_already_indented(true);
+    writer.set_level(2);
+
+    write!(writer, "{}\n> > {}\n> {}\n\n>\n", "😀", "😀", "😀").unwrap();
+    assert_eq!(writer.get_ref(), "> > 😀\n> > 😀\n> > > 😀\n\n> > >\n");
+}
+
+#[test]
+fn test_skip_already_indented_write_char() {
+    // Writing one char at a time is the same as writing the whole string
+    for content in &["> x\n", "a\n>y\n", "> > a\n>\n\n> b"] {
+        let mut by_str = IndentWriter::new("> ", String::new()).skip_already_indented(true);
+        by_str.write_str(content).unwrap();
+        by_str.flush().unwrap();
+
+        let mut by_char = IndentWriter::new("> ", String::new()).skip_already_indented(true);
+        content.chars().for_each(|c| by_char.write_char(c).unwrap());
+        by_char.flush().unwrap();
+
+        assert_eq!(by_char.get_ref(), by_str.get_ref(), "{:?}", content);
+    }
+
+    let mut writer = IndentWriter::new("> ", String::new()).skip_already_indented(true);
+    writer.write_str("a\n").unwrap();
+    writer.write_str(">").unwrap();
+    writer.write_char('y').unwrap();
+    writer.write_str("\n").unwrap();
+    assert_eq!(writer.get_ref(), "> a\n> >y\n");
+}
+
+#[test]
+fn test_set_skip_already_indented() {
+    let mut writer = IndentWriter::new("> ", String::new());
+    writer.set_skip_already_indented(true);
+    writer.write_str("> a\n>").unwrap();
+
+    // The held back start of the line is written when it's disabled
+    writer.set_skip_already_indented(false);
+    writer.write_str(" b\n> c\n").unwrap();
+    assert_eq!(writer.get_ref(), "> a\n> > b\n> > c\n");
+}
+
+#[test]
+fn test_skip_already_indented_partial_writes() {
+    let mut dest = String::new();
+    {
+        let mut writer =
+            IndentWriter::new("😀 ", OneByteAtATime(&mut dest)).skip_already_indented(true);
+        for piece in &["😀", " a\n😀", "b\n", "😀 😀 c\n😀"] {
+            writer.write_str(piece).unwrap();
+        }
+        assert_eq!(writer.get_ref().0.as_str(), "😀 a\n😀 😀b\n😀 😀 c\n");
+
+        writer.flush().unwrap();
+    }
+    assert_eq!(dest, "😀 a\n😀 😀b\n😀 😀 c\n😀 😀");
+
+    check_retries(|w| IndentWriter::new("\t", w).skip_already_indented(true));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_squeeze_blank_lines() {
+    let mut writer = IndentWriter::new("\t", String::new()).squeeze_blank_lines(true);
+    for piece in &["\n", "\n", "😀\n", "\n", "  ", "\n", "\t\n\n😀", "\n\n", ""] {
+        writer.write_str(piece).unwrap();
+    }
+    assert_eq!(writer.get_ref(), "\n\t😀\n\n\t😀\n\n");
+
+    let mut writer = IndentWriter::new("// ", String::new())
+        .squeeze_blank_lines(true)
+        .trim_prefix_on_blank_lines(true);
+    for c in "😀\n  \n\n😀\n".chars() {
+        writer.write_char(c).unwrap();
+    }
+    assert_eq!(writer.get_ref(), "// 😀\n//  \n// 😀\n");
+
+    check_retries(|w| IndentWriter::new("> ", w).squeeze_blank_lines(true));
+}
+
+#[test]
+fn test_ensure_newline() {
+    let mut writer = IndentWriter::new("\t", String::new()).line_suffix(";");
+
+    writer.ensure_newline().unwrap();
+    write!(writer, "{}", "😀").unwrap();
+    writer.ensure_newline().unwrap();
+    writer.ensure_newline().unwrap();
+    write!(writer, "{}\n", "😀").unwrap();
+    writer.ensure_newline().unwrap();
+
+    assert_eq!(writer.line(), 2);
+    assert_eq!(writer.finish().unwrap(), "\t😀\n\t😀;\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ensure_newline_shared_level() {
+    use indent_write::fmt::SharedLevel;
+
+    let level = SharedLevel::with_level(1);
+    let mut dest = String::new();
+
+    {
+        let mut writer = IndentWriter::with_shared_level("  ", level.clone(), &mut dest);
+        write!(writer, "{}", "😀").unwrap();
+        writer.ensure_newline().unwrap();
+    }
+    {
+        let mut writer = IndentWriter::with_shared_level("  ", level.clone(), &mut dest);
+        assert!(writer.needs_indent());
+        write!(writer, "{}", "😀").unwrap();
+    }
+
+    assert_eq!(dest, "  😀\n  😀");
+}
+
+#[test]
+fn test_write_lines() {
+    let mut writer = IndentWriter::new("\t", String::new());
+
+    writer.write_lines(std::iter::empty::<&str>()).unwrap();
+    writer
+        .write_separated(std::iter::empty::<&str>(), ", ")
+        .unwrap();
+    assert_eq!(writer.get_ref(), "");
+
+    writer.write_lines(CONTENT).unwrap();
+    writer.write_lines(&["😀\n😀", ""]).unwrap();
+    writer.write_separated(&["😀", "😀\n😀"], ",\n").unwrap();
+
+    assert_eq!(
+        writer.get_ref(),
+        "\t\t😀 😀 😀\n\t\t\t😀 😀 😀\n\t\t😀 😀 😀\n\t😀\n\t😀\n\n\t😀,\n\t😀\n\t😀"
+    );
+}
+
+#[test]
+fn test_write_lines_error() {
+    struct Item(bool);
+
+    impl fmt::Display for Item {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.0 {
+                true => f.write_str("😀"),
+                false => Err(fmt::Error),
+            }
+        }
+    }
+
+    let items = [Item(true), Item(false), Item(true)];
+
+    let mut writer = IndentWriter::new("\t", String::new());
+    assert!(writer.write_lines(&items).is_err());
+    assert_eq!(writer.get_ref(), "\t😀\n");
+
+    let mut writer = IndentWriter::new("\t", String::new());
+    assert!(writer.write_separated(&items, ", ").is_err());
+    assert_eq!(writer.get_ref(), "\t😀, ");
+}
+
+#[test]
+fn test_extend() {
+    let mut writer = IndentWriter::new("\t", String::new());
+    writer.extend("😀\n\n😀 😀\n😀".chars());
+    writer.extend(vec![" ", "😀\n", "\n😀"]);
+
+    assert_eq!(writer.get_ref(), "\t😀\n\n\t😀 😀\n\t😀 😀\n\n\t😀");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_extend_strings() {
+    let mut writer = IndentWriter::new("\t", String::new());
+    writer.extend(vec![String::from("😀\n"), String::from("\n😀")]);
+
+    assert_eq!(writer.get_ref(), "\t😀\n\n\t😀");
+}
+
+#[test]
+fn test_try_extend_error() {
+    let mut writer = IndentWriter::new(
+        "\t",
+        FailOnNth {
+            dest: String::new(),
+            calls: 0,
+            n: 4,
+        },
+    );
+
+    assert!(writer.try_extend(["😀\n", "😀\n", "😀\n"]).is_err());
+    assert_eq!(writer.get_ref().dest, "\t😀\n\t");
+}
+
+#[test]
+#[should_panic]
+fn test_extend_panics_on_error() {
+    let mut writer = IndentWriter::new(
+        "\t",
+        FailOnNth {
+            dest: String::new(),
+            calls: 0,
+            n: 1,
+        },
+    );
+
+    writer.extend("😀".chars());
+}
+
+#[test]
+fn test_indent_if_alternate() {
+    use indent_write::fmt::indent_if_alternate;
+
+    struct Lines;
+
+    impl fmt::Display for Lines {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut writer = indent_if_alternate(f, "\t");
+            writer.write_str("😀\n\n😀")
+        }
+    }
+
+    assert_eq!(format!("{}", Lines), "😀\n\n😀");
+    assert_eq!(format!("{:#}", Lines), "\t😀\n\n\t😀");
+}
+
+#[test]
+fn test_list_item_writer() {
+    let mut writer = ListItemWriter::new("- ", String::new());
+
+    writer.write_str("😀 😀\n😀").unwrap();
+    writer.write_str(" 😀\n\n😀\n").unwrap();
+    writer.next_item();
+    writer.write_str("\n😀\n").unwrap();
+    writer.next_item();
+    write!(writer, "{}\n{}", 1, 2).unwrap();
+
+    assert_eq!(
+        writer.get_ref(),
+        "- 😀 😀\n  😀 😀\n\n  😀\n\n- 😀\n- 1\n  2"
+    );
+}
+
+#[test]
+fn test_list_item_writer_nested() {
+    let mut outer = ListItemWriter::new("- ", String::new());
+
+    writeln!(outer, "First\nitem").unwrap();
+    {
+        let mut inner = ListItemWriter::new("* ", IndentWriter::new("  ", &mut outer));
+        writeln!(inner, "😀\n😀").unwrap();
+        inner.next_item();
+        writeln!(inner, "😀").unwrap();
+    }
+    outer.next_item();
+    writeln!(outer, "Second").unwrap();
+
+    assert_eq!(
+        outer.get_ref(),
+        "- First\n  item\n    * 😀\n      😀\n    * 😀\n- Second\n"
+    );
+}
+
+#[test]
+fn test_quote_writer() {
+    let content = "😀\n> 😀\n>😀\n> > 😀\n>>  😀\n\n  😀\n>\n> >  \n";
+    let expected = "> 😀\n>> 😀\n>> 😀\n>>> 😀\n>>>  😀\n>\n>   😀\n>>\n>>>\n";
+
+    let mut writer = QuoteWriter::new(String::new());
+    writer.write_str(content).unwrap();
+    assert_eq!(writer.get_ref(), expected);
+
+    // One char at a time, so that every line start is split across writes
+    let mut writer = QuoteWriter::new(String::new());
+    content.chars().for_each(|c| writer.write_char(c).unwrap());
+    assert_eq!(writer.get_ref(), expected);
+}
+
+#[test]
+fn test_quote_writer_nested() {
+    let mut writer = QuoteWriter::new(QuoteWriter::new(String::new()));
+
+    write!(writer, "😀\n> 😀\n\n> >").unwrap();
+    writer.flush().unwrap();
+    writer.get_mut().flush().unwrap();
+
+    assert_eq!(writer.get_ref().get_ref(), ">> 😀\n>>> 😀\n>>\n>>>> ");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_line_prefix_writer() {
+    use indent_write::fmt::LinePrefixWriter;
+    use std::borrow::Cow;
+
+    let mut lines = Vec::new();
+    let mut writer = LinePrefixWriter::new(
+        |line: &str| {
+            lines.push(line.to_owned());
+            match line.starts_with('+') {
+                true => Cow::Borrowed("+"),
+                false => Cow::Borrowed(" "),
+            }
+        },
+        String::new(),
+    );
+
+    for piece in &["+😀", " 😀\n", "\n", "😀\n+", "", "😀"] {
+        writer.write_str(piece).unwrap();
+    }
+
+    assert_eq!(writer.get_ref(), "++😀 😀\n \n 😀\n");
+    assert_eq!(writer.finish().unwrap(), "++😀 😀\n \n 😀\n++😀");
+    assert_eq!(lines, ["+😀 😀", "", "😀", "+😀"]);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_line_prefix_writer_drop() {
+    use indent_write::fmt::LinePrefixWriter;
+    use std::borrow::Cow;
+
+    let mut dest = String::new();
+    {
+        let mut writer = LinePrefixWriter::new(|_: &str| Cow::Borrowed("\t"), &mut dest);
+        write!(writer, "😀\n😀").unwrap();
+    }
+
+    assert_eq!(dest, "\t😀\n\t😀");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_line_prefix_writer_max_line_len() {
+    use indent_write::fmt::LinePrefixWriter;
+    use std::borrow::Cow;
+
+    let mut lines = Vec::new();
+    let mut writer = LinePrefixWriter::new(
+        |line: &str| {
+            lines.push(line.to_owned());
+            Cow::Borrowed("\t")
+        },
+        String::new(),
+    )
+    .max_line_len(6);
+
+    // 😀 is 4 bytes, so the line is split on a char boundary
+    write!(writer, "😀 😀 😀\n😀 ").unwrap();
+    write!(writer, "😀 😀\n😀").unwrap();
+
+    assert_eq!(writer.finish().unwrap(), "\t😀 😀 😀\n\t😀 😀 😀\n\t😀");
+    assert_eq!(lines, ["😀 ", "😀 ", "😀"]);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_line_map_writer() {
+    use indent_write::fmt::LineMapWriter;
+    use std::borrow::Cow;
+
+    fn strip(line: &str) -> Cow<'_, str> {
+        Cow::Owned(line.trim_end().to_owned())
+    }
+
+    // Under an IndentWriter, the mapped lines include the indent
+    let mut dest = String::new();
+    {
+        let mut writer = IndentWriter::new("\t", LineMapWriter::new(strip, &mut dest));
+        for piece in &["😀 \r", "\n\t\n 😀", "  \n", "😀 "] {
+            writer.write_str(piece).unwrap();
+        }
+    }
+    assert_eq!(dest, "\t😀\r\n\n\t 😀\n\t😀");
+
+    // One char at a time, with an IndentWriter over it
+    let mut writer = IndentWriter::new(
+        "> ",
+        OneByteAtATime(LineMapWriter::new(strip, String::new())),
+    );
+    writer.write_str("😀 \r\n\n😀\t\n").unwrap();
+    assert_eq!(writer.into_inner().0.finish().unwrap(), "> 😀\r\n\n> 😀\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_line_filter_writer() {
+    use indent_write::fmt::LineFilterWriter;
+
+    fn keep(line: &str) -> bool {
+        !line.trim_start().starts_with('#')
+    }
+
+    const PIECES: &[&str] = &["😀\n# ", "😀\n\n  #😀\n😀", "\n#"];
+
+    // Filtering before indenting drops lines cleanly
+    let mut writer = LineFilterWriter::new(keep, IndentWriter::new("\t", String::new()));
+    for piece in PIECES {
+        writer.write_str(piece).unwrap();
+    }
+    assert_eq!(writer.finish().unwrap().get_ref(), "\t😀\n\n\t😀\n");
+
+    // Filtering after indenting sees the indent; lines are still dropped
+    // along with their indents
+    let mut dest = String::new();
+    {
+        let mut writer = IndentWriter::new("\t", LineFilterWriter::new(keep, &mut dest));
+        for piece in PIECES {
+            writer.write_str(piece).unwrap();
+        }
+    }
+    assert_eq!(dest, "\t😀\n\n\t😀\n");
+
+    // The final line is kept if it matches
+    let mut writer = LineFilterWriter::new(keep, String::new());
+    writer.write_str("# 😀\n😀").unwrap();
+    assert_eq!(writer.finish().unwrap(), "😀");
+}
+
+#[test]
+fn test_gutter_writer() {
+    use indent_write::fmt::GutterWriter;
+
+    let mut writer = GutterWriter::new(98, GutterWriter::width_for(101), String::new());
+
+    for piece in &["😀", " 😀\n\n", "", "😀\n😀"] {
+        writer.write_str(piece).unwrap();
+    }
+    assert_eq!(writer.line(), 101);
+
+    assert_eq!(writer.get_ref(), " 98 | 😀 😀\n    |\n100 | 😀\n101 | 😀");
+}
+
+#[test]
+fn test_gutter_writer_narrow() {
+    use indent_write::fmt::GutterWriter;
+
+    let mut writer = GutterWriter::new(9, 1, String::new());
+    write!(writer, "😀\n😀\n").unwrap();
+
+    assert_eq!(writer.get_ref(), "9 | 😀\n10 | 😀\n");
+}
+
+#[test]
+fn test_empty_indent_passthrough() {
+    let count = std::cell::Cell::new(0);
+    let mut dest = String::new();
+    {
+        let mut writer = IndentWriter::new(
+            "",
+            CountingWriter {
+                writer: &mut dest,
+                count: &count,
+            },
+        );
+
+        writer.write_str("😀\n\n😀 😀\n\t😀").unwrap();
+        assert_eq!(count.get(), 1);
+        assert!(!writer.needs_indent());
+
+        writer.write_str("\n\n").unwrap();
+        assert_eq!(count.get(), 2);
+        assert!(writer.needs_indent());
+        assert_eq!(writer.line(), 5);
+    }
+
+    assert_eq!(dest, "😀\n\n😀 😀\n\t😀\n\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_reserve_for() {
+    let content = "😀 😀\n\n\t😀\n".repeat(1000);
+
+    let mut writer = IndentWriter::new("> ", String::new());
+    writer.set_level(2);
+    writer.reserve_for(&content);
+    let capacity = writer.get_ref().capacity();
+
+    writer.write_str(&content).unwrap();
+
+    assert_eq!(writer.get_ref().capacity(), capacity);
+    assert_eq!(*writer.get_ref(), "> > 😀 😀\n\n> > \t😀\n".repeat(1000));
+
+    let mut writer = IndentWriter::new("    ", String::new()).line_suffix(";");
+    writer.reserve_hint(100, 10);
+    assert!(writer.get_ref().capacity() >= 150);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ansi_aware() {
+    let content = "\x1b[1m\x1b[31m😀\n😀\x1b[0;32m\n\n😀\x1b[m\n😀\n";
+
+    let mut writer = IndentWriter::new("> ", String::new()).ansi_aware(true);
+    content.chars().for_each(|c| writer.write_char(c).unwrap());
+    assert_eq!(
+        writer.get_ref(),
+        "> \x1b[1m\x1b[31m😀\n\
+         \x1b[0m> \x1b[1;31m😀\x1b[0;32m\n\
+         \n\
+         \x1b[0m> \x1b[32m😀\x1b[m\n\
+         > 😀\n"
+    );
+
+    // Without the option, content is never altered
+    let mut writer = IndentWriter::new("> ", String::new());
+    writer.write_str(content).unwrap();
+    assert_eq!(
+        writer.get_ref(),
+        "> \x1b[1m\x1b[31m😀\n> 😀\x1b[0;32m\n\n> 😀\x1b[m\n> 😀\n"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ansi_aware_merges_sequences() {
+    let mut writer = IndentWriter::new("> ", String::new()).ansi_aware(true);
+
+    // Colors replace earlier colors, and attributes are set and cleared
+    // individually
+    write!(writer, "\x1b[1;4m\x1b[31;44m\x1b[38;5;208m\x1b[24m😀\n😀\n").unwrap();
+    assert_eq!(
+        writer.get_ref(),
+        "> \x1b[1;4m\x1b[31;44m\x1b[38;5;208m\x1b[24m😀\n\x1b[0m> \x1b[1;38;5;208;44m😀\n"
+    );
+
+    // Changing the color on every line doesn't make each indent longer
+    let mut writer = IndentWriter::new("> ", String::new()).ansi_aware(true);
+    let mut lengths = Vec::new();
+    for line in 0..200 {
+        let start = writer.get_ref().len();
+        write!(writer, "\x1b[{}m😀\n", 30 + line % 8).unwrap();
+        lengths.push(writer.get_ref().len() - start);
+    }
+    assert!(
+        lengths[1..].iter().all(|&len| len == lengths[1]),
+        "{:?}",
+        lengths
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ansi_aware_malformed() {
+    let mut writer = IndentWriter::new("> ", String::new()).ansi_aware(true);
+
+    // An unterminated sequence, a non-SGR sequence, and an escape that isn't
+    // a CSI sequence are all passed through, and don't affect the indent
+    write!(writer, "\x1b[31\n\x1b[2K😀\n\x1bX😀\n\x1b[").unwrap();
+    write!(writer, "34m😀\n😀\n").unwrap();
+
+    assert_eq!(
+        writer.get_ref(),
+        "> \x1b[31\n> \x1b[2K😀\n> \x1bX😀\n> \x1b[34m😀\n\x1b[0m> \x1b[34m😀\n"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_ansi_aware_max_width() {
+    // Escape sequences, including the colors restored after the indent,
+    // don't count toward the width
+    let mut writer = IndentWriter::with_max_width("> ", 6, String::new()).ansi_aware(true);
+    writer.write_str("\x1b[31mabcdefgh\x1b[0mij\n").unwrap();
+    assert_eq!(
+        writer.get_ref(),
+        "> \x1b[31mabcd\n\x1b[0m> \x1b[31mefgh\x1b[0m\n> ij\n"
+    );
+
+    // Truncation measures lines the same way, even if an escape sequence is
+    // split across writes
+    let content = "\x1b[1;31mabcd\x1b[0mef\n\x1b[32mabcdefg\n";
+    let mut expected =
+        IndentWriter::with_max_width_truncate("> ", 6, "…", String::new()).ansi_aware(true);
+    expected.write_str(content).unwrap();
+    assert_eq!(
+        expected.get_ref(),
+        "> \x1b[1;31mabc…\x1b[0m\n> \x1b[32mabc…\n"
+    );
+
+    for split in 1..content.len() {
+        let mut writer =
+            IndentWriter::with_max_width_truncate("> ", 6, "…", String::new()).ansi_aware(true);
+        writer.write_str(&content[..split]).unwrap();
+        writer.write_str(&content[split..]).unwrap();
+        assert_eq!(writer.get_ref(), expected.get_ref(), "split: {}", split);
+    }
+
+    for content in &["\x1b[31m\x1b[31m\x1b[31m", "a b\x1b[31m c d e f g h"] {
+        let mut writer = IndentWriter::with_max_width("> ", 6, String::new()).ansi_aware(true);
+        writer.write_str(content).unwrap();
+        assert!(writer.get_ref().len() < 200, "{:?}", writer.get_ref());
+    }
+}
+
+#[cfg(feature = "wrap")]
+#[test]
+fn test_ansi_aware_word_wrap() {
+    let mut writer = IndentWriter::with_word_wrap("> ", 10, String::new()).ansi_aware(true);
+    writer
+        .write_str("\x1b[31mone two three\x1b[0m four\n")
+        .unwrap();
+    assert_eq!(
+        writer.get_ref(),
+        "> \x1b[31mone two\n\x1b[0m> \x1b[31mthree\x1b[0m\n> four\n"
+    );
+}
+
+#[test]
+fn test_bom() {
+    // The byte order mark alone in its own write, and followed by content
+    for pieces in &[
+        &["\u{FEFF}", "", "😀\n\u{FEFF}😀\n"][..],
+        &["\u{FEFF}😀\n\u{FEFF}😀\n"],
+    ] {
+        let mut writer = IndentWriter::new("\t", String::new());
+        let mut stripped = IndentWriter::new("\t", String::new()).strip_bom(true);
+
+        for piece in pieces.iter() {
+            writer.write_str(piece).unwrap();
+            stripped.write_str(piece).unwrap();
+        }
+
+        assert_eq!(writer.get_ref(), "\u{FEFF}\t😀\n\t\u{FEFF}😀\n");
+        assert_eq!(stripped.get_ref(), "\t😀\n\t\u{FEFF}😀\n");
+        assert_eq!(writer.column(), 0);
+    }
+
+    // A byte order mark written with write_char
+    let mut writer = IndentWriter::builder("\t")
+        .strip_bom(true)
+        .build(String::new());
+    writer.write_char('\u{FEFF}').unwrap();
+    writer.write_char('😀').unwrap();
+    assert_eq!(writer.get_ref(), "\t😀");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_treat_whitespace_as_blank() {
+    let content = "😀\n \t \n\n  😀\n\t";
+    let pieces: Vec<String> = content.chars().map(String::from).collect();
+
+    for (writer, expected) in [
+        (
+            IndentWriter::new("> ", String::new()).treat_whitespace_as_blank(true),
+            "> 😀\n \t \n\n>   😀\n\t",
+        ),
+        (
+            IndentWriter::new("> ", String::new()).drop_whitespace_on_blank_lines(true),
+            "> 😀\n\n\n>   😀\n",
+        ),
+        (
+            IndentWriter::builder("> ")
+                .indent_empty_lines(true)
+                .trim_prefix_on_blank_lines(true)
+                .drop_whitespace_on_blank_lines(true)
+                .build(String::new()),
+            "> 😀\n>\n>\n>   😀\n",
+        ),
+    ] {
+        // In one write, and split into single chars
+        for pieces in &[vec![content.to_owned()], pieces.clone()] {
+            let mut writer = writer.clone();
+            for piece in pieces {
+                writer.write_str(piece).unwrap();
+            }
+            writer.flush().unwrap();
+
+            assert_eq!(writer.get_ref(), expected, "pieces: {:?}", pieces);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_treat_whitespace_as_blank_cap() {
+    let mut writer = IndentWriter::new("> ", String::new()).treat_whitespace_as_blank(true);
+
+    let spaces = " ".repeat(3000);
+    writer.write_str(&spaces).unwrap();
+    assert_eq!(writer.get_ref(), "");
+
+    // Too much whitespace to hold back; the line is indented
+    writer.write_str(&spaces).unwrap();
+    writer.write_str("\n").unwrap();
+    assert_eq!(*writer.get_ref(), format!("> {}{}\n", spaces, spaces));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_translate_indent() {
+    use indent_write::fmt::{IndentStyle, TranslateIndentWriter};
+
+    fn translate(from: IndentStyle, to: IndentStyle, content: &str) -> String {
+        let mut whole = TranslateIndentWriter::new(from, to, String::new());
+        whole.write_str(content).unwrap();
+        whole.flush().unwrap();
+
+        // Split into single chars, so that every run of leading whitespace
+        // is split across writes
+        let mut split = TranslateIndentWriter::new(from, to, String::new());
+        content.chars().for_each(|c| split.write_char(c).unwrap());
+        split.flush().unwrap();
+
+        assert_eq!(whole.get_ref(), split.get_ref());
+        whole.into_inner()
+    }
+
+    assert_eq!(
+        translate(
+            IndentStyle::Tabs,
+            IndentStyle::Spaces(2),
+            "😀\n\t😀\t😀\n\t\t \t😀\n\t \n \t😀\n\t"
+        ),
+        "😀\n  😀\t😀\n     \t😀\n\t \n \t😀\n\t"
+    );
+
+    assert_eq!(
+        translate(
+            IndentStyle::Spaces(4),
+            IndentStyle::Tabs,
+            "😀\n    😀    😀\n          😀\n        \n\t    😀\n"
+        ),
+        "😀\n\t😀    😀\n\t\t  😀\n        \n\t    😀\n"
+    );
+
+    // Translating the content, and then indenting it
+    let mut writer = TranslateIndentWriter::new(
+        IndentStyle::Spaces(2),
+        IndentStyle::Spaces(4),
+        IndentWriter::new("> ", String::new()),
+    );
+    write!(writer, "😀\n  😀\n   😀\n").unwrap();
+    assert_eq!(writer.get_ref().get_ref(), "> 😀\n>     😀\n>      😀\n");
+}
+
+#[test]
+fn test_max_level() {
+    use indent_write::fmt::MaxLevelError;
+
+    let mut writer = IndentWriter::builder("\t")
+        .max_level(2)
+        .build(String::new());
+
+    {
+        let mut writer = writer.try_scoped_indent().unwrap();
+        writer.write_str("😀\n").unwrap();
+
+        let err: MaxLevelError = writer.try_scoped_indent().unwrap_err();
+        assert_eq!((err.level(), err.max_level()), (3, 2));
+        assert_eq!(
+            err.to_string(),
+            "indentation level 3 exceeds the maximum of 2"
+        );
+
+        // Levels set past the limit make writes fail, but empty lines can
+        // still be written, since they aren't indented
+        writer.set_level(3);
+        writer.write_str("\n").unwrap();
+        assert!(writer.write_str("😀").is_err());
+    }
+
+    writer.write_str("😀\n").unwrap();
+    assert_eq!(writer.get_ref(), "\t\t😀\n\n\t😀\n");
+}
+
+#[test]
+fn test_tree_writer() {
+    use indent_write::fmt::TreeWriter;
+
+    let mut root = TreeWriter::new(String::new());
+    writeln!(root, "😀").unwrap();
+    {
+        let mut a = root.child(false);
+        a.write_str("😀\n😀").unwrap();
+        a.write_str(" 😀\n").unwrap();
+        {
+            let mut b = a.child(false);
+            writeln!(b, "😀\n\n😀").unwrap();
+            writeln!(b.child(true), "😀\n😀").unwrap();
+        }
+        writeln!(a.child(true), "😀").unwrap();
+    }
+    writeln!(root.child(true).child(true), "😀\n😀").unwrap();
+
+    assert_eq!(
+        root.get_ref(),
+        "😀\n\
+         ├── 😀\n\
+         │   😀 😀\n\
+         │   ├── 😀\n\
+         \n\
+         │   │   😀\n\
+         │   │   └── 😀\n\
+         │   │       😀\n\
+         │   └── 😀\n\
+         └── └── 😀\n\
+         \x20       😀\n"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_align_writer() {
+    use indent_write::fmt::AlignWriter;
+
+    let content = "😀😀 = 😀\n😀 = 😀 = 😀\n😀\n=\n \t\n😀 = 😀\n😀😀😀 = 😀";
+    let expected = "\t😀😀 = 😀\n\t😀  = 😀 = 😀\n\t😀\n\t   =\n\t \t\n\t😀   = 😀\n\t😀😀😀 = 😀";
+
+    // In one write, and split into single chars
+    let mut whole = AlignWriter::new('=', IndentWriter::new("\t", String::new()));
+    whole.write_str(content).unwrap();
+    whole.flush_block().unwrap();
+    assert_eq!(whole.get_ref().get_ref(), expected);
+
+    let mut split = AlignWriter::new('=', IndentWriter::new("\t", String::new()));
+    content.chars().for_each(|c| split.write_char(c).unwrap());
+    assert_eq!(
+        split.get_ref().get_ref(),
+        "\t😀😀 = 😀\n\t😀  = 😀 = 😀\n\t😀\n\t   =\n\t \t\n"
+    );
+    split.flush_block().unwrap();
+    assert_eq!(split.get_ref().get_ref(), expected);
+}
+
+#[test]
+fn test_set_enabled() {
+    let mut writer = IndentWriter::new("\t", String::new()).line_suffix(";");
+    writer.write_str("Line 1\nLine 2, ").unwrap();
+
+    // Disabling mid-line doesn't affect the current line's indent
+    writer.set_enabled(false);
+    assert!(!writer.is_enabled());
+    writer.write_str("continued\n\nLine 3\nLine 4, ").unwrap();
+
+    // Enabling mid-line doesn't indent the rest of the line
+    writer.set_enabled(true);
+    writer.write_char('c').unwrap();
+    writer.write_str("ontinued\nLine 5\n").unwrap();
+
+    assert_eq!(
+        writer.get_ref(),
+        "\tLine 1;\n\tLine 2, continued\n\nLine 3\nLine 4, continued;\n\tLine 5;\n"
+    );
+}
+
+#[test]
+fn test_write_raw() {
+    let mut writer = IndentWriter::new("  ", String::new()).line_suffix(";");
+    writer.write_str("a\nb").unwrap();
+
+    // Raw content mid-line
+    writer.write_raw(" <raw>\n").unwrap();
+    writer.write_str("c\n").unwrap();
+
+    // Raw content at the start of a line, ending mid-line
+    writer.write_raw("  <raw\n  raw>").unwrap();
+    writer.write_str(" d\ne\n").unwrap();
+
+    assert_eq!(
+        writer.get_ref(),
+        "  a;\n  b <raw>\n  c;\n  <raw\n  raw> d;\n  e;\n"
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_error_chain() {
+    use indent_write::fmt::{write_error_chain, ErrorChain};
+    use std::error::Error;
+
+    #[derive(Debug)]
+    struct Cycle;
+
+    impl fmt::Display for Cycle {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("😀\n\n😀")
+        }
+    }
+
+    impl Error for Cycle {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&Cycle)
+        }
+    }
+
+    let output = ErrorChain::new(&Cycle, "\t").to_string();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 33 * 3 + 1);
+    assert_eq!(lines[..6], ["😀", "", "😀", "\t😀", "", "\t😀"]);
+    assert_eq!(lines[99], format!("{}...", "\t".repeat(33)));
+
+    // The chain is indented as a whole by an outer writer
+    let mut writer = IndentWriter::new("> ", String::new());
+    write_error_chain(&mut writer, &std::fmt::Error, "\t").unwrap();
+    writer.write_str("\n").unwrap();
+    write!(writer, "{}", ErrorChain::new(&Cycle, "\t").max_depth(1)).unwrap();
+    assert_eq!(
+        writer.get_ref(),
+        "> an error occurred when formatting an argument\n\
+         > 😀\n\n> 😀\n> \t😀\n\n> \t😀\n> \t\t..."
+    );
+}
+
+#[test]
+fn test_level_prefixes() {
+    use indent_write::fmt::LevelOverflow;
+
+    const PREFIXES: &[&str] = &["\t", "😀 ", "| "];
+    const INPUT: &str = "😀\n\n 😀 \n😀";
+
+    // Stack a writer for each prefix, with the first prefix outermost
+    fn write_nested(writer: &mut dyn Write, prefixes: &[&str]) {
+        match prefixes.split_first() {
+            None => writer.write_str(INPUT).unwrap(),
+            Some((prefix, rest)) => write_nested(&mut IndentWriter::new(*prefix, writer), rest),
+        }
+    }
+
+    for &overflow in &[LevelOverflow::RepeatLast, LevelOverflow::Cycle] {
+        for level in 0..8 {
+            let prefixes: Vec<&str> = (0..level)
+                .map(|i| match overflow {
+                    LevelOverflow::RepeatLast => PREFIXES[i.min(PREFIXES.len() - 1)],
+                    LevelOverflow::Cycle => PREFIXES[i % PREFIXES.len()],
+                })
+                .collect();
+
+            let mut expected = String::new();
+            write_nested(&mut expected, &prefixes);
+
+            let mut writer = IndentWriter::builder("")
+                .level_prefixes(PREFIXES, overflow)
+                .build(String::new());
+            writer.set_level(level);
+            writer.write_str(INPUT).unwrap();
+
+            assert_eq!(*writer.get_ref(), expected, "{:?} {}", overflow, level);
+        }
+    }
+
+    let mut writer = IndentWriter::new("", String::new()).level_prefixes(&[], LevelOverflow::Cycle);
+    writer.set_level(3);
+    writer.write_str(INPUT).unwrap();
+    assert_eq!(writer.get_ref(), INPUT);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_level_prefixes_trim() {
+    use indent_write::fmt::LevelOverflow;
+
+    let mut writer = IndentWriter::new("", String::new())
+        .level_prefixes(&["\t", "| ", "  "], LevelOverflow::RepeatLast)
+        .trim_prefix_on_blank_lines(true);
+    writer.set_level(4);
+    writer.write_str("😀\n  \n😀\n").unwrap();
+    assert_eq!(writer.get_ref(), "\t|     😀\n\t|  \n\t|     😀\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_indent_cache() {
+    struct Recorder(Vec<String>);
+
+    impl Write for Recorder {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.push(s.to_owned());
+            Ok(())
+        }
+    }
+
+    let mut writer = IndentWriter::new("😀 ", Recorder(Vec::new()));
+    writer.set_level(10);
+    writer.write_str("a\nb\n\nc\n").unwrap();
+
+    // The cache is rebuilt when the level changes
+    writer.set_level(2);
+    writer.write_str("d\n").unwrap();
+
+    let calls = &writer.get_ref().0;
+    let full = "😀 ".repeat(10);
+    assert_eq!(calls.iter().filter(|call| **call == full).count(), 3);
+    assert_eq!(calls.iter().filter(|call| *call == "😀 😀 ").count(), 1);
+    assert!(!calls.iter().any(|call| call == "😀 "));
+    assert_eq!(
+        calls.concat(),
+        format!("{0}a\n{0}b\n\n{0}c\n😀 😀 d\n", full)
+    );
+
+    // Deep indents of spaces or tabs are cached too
+    for (indent, full) in &[
+        (Indent::Spaces(4), " ".repeat(80)),
+        (Indent::Tabs(1), "\t".repeat(20)),
+    ] {
+        let mut writer = IndentWriter::new(*indent, Recorder(Vec::new()));
+        writer.set_level(20);
+        writer.write_str("a\nb\n").unwrap();
+
+        let calls = &writer.get_ref().0;
+        assert_eq!(*calls, [full.as_str(), "a\n", full, "b\n"]);
+    }
+}
+
+#[test]
+fn test_blank_line() {
+    let mut writer = IndentWriter::new("\t", String::new());
+    writer.blank_line().unwrap();
+    writer.ensure_newline().unwrap();
+    assert_eq!(writer.get_ref(), "");
+
+    writer.write_str("😀\n").unwrap();
+    writer.blank_line().unwrap();
+    writer.blank_line().unwrap();
+    writer.write_str("😀\n\n\n").unwrap();
+    writer.blank_line().unwrap();
+    writer.write_char('😀').unwrap();
+    writer.write_char('\n').unwrap();
+    writer.write_char('\n').unwrap();
+    writer.blank_line().unwrap();
+    writer.write_str("😀").unwrap();
+
+    assert_eq!(writer.get_ref(), "\t😀\n\n\t😀\n\n\n\t😀\n\n\t😀");
+
+    // A line ended by `write_char` isn't blank
+    let mut writer = IndentWriter::new("  ", String::new());
+    writer.write_str("\n\na").unwrap();
+    writer.write_char('\n').unwrap();
+    writer.blank_line().unwrap();
+    writer.write_str("b\n").unwrap();
+    assert_eq!(writer.get_ref(), "\n\n  a\n\n  b\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_max_width_truncate() {
+    let mut writer = IndentWriter::with_max_width_truncate("\t", 8, " [..]", String::new());
+
+    // The discarding state persists across writes
+    writer.write_str("😀 😀 😀 ").unwrap();
+    writer.write_str("😀 😀 😀\n😀").unwrap();
+    writer.write_str(" 😀 😀 😀\n\n").unwrap();
+
+    // Short lines, and lines that exactly fit, are unchanged
+    writer.write_str("😀\n😀😀😀😀😀😀😀\n").unwrap();
+    writer.write_char('😀').unwrap();
+    writer.write_str("😀😀😀😀😀😀😀\n").unwrap();
+
+    assert_eq!(
+        writer.get_ref(),
+        "\t😀  [..]\n\t😀 😀 😀 😀\n\n\t😀\n\t😀😀😀😀😀😀😀\n\t😀😀 [..]\n"
+    );
+
+    // A marker that doesn't fit is left out
+    let mut writer = IndentWriter::builder("\t")
+        .max_width_truncate(4, "…")
+        .build(String::new());
+    writer.write_str("😀😀😀").unwrap();
+    writer.flush().unwrap();
+    writer.write_str("😀😀\n😀😀😀😀\n").unwrap();
+
+    assert_eq!(writer.get_ref(), "\t😀😀😀\n\t😀😀…\n");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_max_width_truncate_expand_tabs() {
+    let content = "😀😀\téa\tb\n\t😀\t😀\n";
+
+    for width in 4..16 {
+        let new_writer = || {
+            IndentWriter::with_max_width_truncate("  ", width, "…", String::new()).expand_tabs(4)
+        };
+
+        let mut expected = new_writer();
+        expected.write_str(content).unwrap();
+
+        for line in expected.get_ref().lines() {
+            assert!(line.chars().count() <= width, "{:?}", expected.get_ref());
+        }
+
+        // The output doesn't depend on how the line is split across writes
+        for split in content.char_indices().map(|(idx, _)| idx) {
+            let mut writer = new_writer();
+            writer.write_str(&content[..split]).unwrap();
+            writer.write_str(&content[split..]).unwrap();
+            assert_eq!(writer.get_ref(), expected.get_ref(), "split: {}", split);
+        }
+
+        let mut writer = new_writer();
+        content.chars().for_each(|c| writer.write_char(c).unwrap());
+        assert_eq!(writer.get_ref(), expected.get_ref());
+    }
+
+    let mut writer =
+        IndentWriter::with_max_width_truncate("  ", 7, "…", String::new()).expand_tabs(4);
+    writer.write_str(content).unwrap();
+    assert_eq!(writer.get_ref(), "  😀😀…\n    😀…\n");
+}
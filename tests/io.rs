@@ -1,12 +1,21 @@
 #![cfg(feature = "std")]
+#![allow(
+    clippy::redundant_static_lifetimes,
+    clippy::write_literal,
+    clippy::write_with_newline,
+    clippy::writeln_empty_string
+)]
 
-use std::io::{self, Write};
+use std::io::{self, BufRead, Read, Write};
 use std::str::from_utf8;
 
-use indent_write::io::IndentWriter;
+use indent_write::io::{
+    copy_indented, copy_indented_buf, DynamicIndentWriter, IndentError, IndentReader, IndentWriter,
+    LineEnding, NumberedWriter,
+};
 
 // This is a wrapper for io::Write that only writes one byte at a time, to test
-// the invariants of IndentableWrite
+// the invariants of IndentWriter
 #[derive(Debug, Clone)]
 struct OneByteAtATime<W>(W);
 
@@ -153,3 +162,1420 @@ fn test_partial_writes_combined() {
         Ok("    Hello, World\n    😀 😀 😀\n    😀 😀 😀\n")
     );
 }
+
+// A writer that writes one byte at a time, and fails once it has written
+// `remaining` bytes
+#[derive(Debug)]
+struct FailAfter {
+    dest: Vec<u8>,
+    remaining: usize,
+}
+
+impl Write for FailAfter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match (self.remaining, buf) {
+            (_, []) => Ok(0),
+            (0, _) => Err(io::Error::other("out of space")),
+            (_, [b, ..]) => {
+                self.remaining -= 1;
+                self.dest.push(*b);
+                Ok(1)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_into_inner() {
+    // Part of a code point is forwarded, rather than held back
+    let mut writer = IndentWriter::new("\t", Vec::new());
+    writer.write_all(&"x😀".as_bytes()[..3]).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), b"\tx\xF0\x9F");
+
+    // A partially written indent is finished before the writer is returned
+    let mut writer = IndentWriter::new(
+        "😀",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 2,
+        },
+    );
+    writer.write_all(b"Line\n").unwrap_err();
+    writer.get_mut().remaining = 1;
+
+    let err = writer.into_inner().unwrap_err();
+    assert_eq!(err.error().kind(), io::ErrorKind::Other);
+    assert_eq!(err.to_string(), "out of space");
+
+    let mut writer = err.into_inner();
+    assert_eq!(writer.get_ref().dest, b"\xF0\x9F\x98");
+    writer.get_mut().remaining = 100;
+    assert_eq!(writer.into_inner().unwrap().dest, "😀".as_bytes());
+}
+
+#[test]
+fn test_into_inner_unflushed() {
+    let mut writer = IndentWriter::new(
+        "    ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 2,
+        },
+    );
+    writer.write_all(b"Line\n").unwrap_err();
+    assert_eq!(writer.into_inner_unflushed().dest, b"  ");
+}
+
+#[test]
+fn test_set_indent_partial() {
+    let mut writer = IndentWriter::new(
+        "1234",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 2,
+        },
+    );
+
+    // The first indent is interrupted partway through
+    writer.write_all(b"a\nb\n").unwrap_err();
+    writer.set_indent("::");
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"a\nb\n").unwrap();
+
+    assert_eq!(writer.indent(), Some("::"));
+    assert_eq!(writer.get_ref().dest, b"1234a\n::b\n");
+
+    // The same, with partial writes from the outside
+    let mut dest = Vec::new();
+    {
+        let mut writer = OneByteAtATime(IndentWriter::new("\t", &mut dest));
+        write!(writer, "a\n").unwrap();
+        writer.0.set_indent("    ");
+        write!(writer, "b\n\nc").unwrap();
+    }
+    assert_eq!(from_utf8(&dest), Ok("\ta\n    b\n\n    c"));
+}
+
+#[test]
+fn test_owned_indent_partial() {
+    let indent = String::from("😀 ");
+    let mut writer = IndentWriter::new(
+        indent.clone(),
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 3,
+        },
+    );
+    drop(indent);
+
+    writer.write_all(b"a\nb").unwrap_err();
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"a\nb").unwrap();
+
+    assert_eq!(from_utf8(&writer.get_ref().dest), Ok("😀 a\n😀 b"));
+}
+
+#[test]
+fn test_bytes_indent_partial() {
+    let mut writer = IndentWriter::new_bytes(
+        b"\x1b[2m\xFF\xFE",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 5,
+        },
+    );
+    assert_eq!(writer.indent(), None);
+
+    writer.write_all(b"a\n\nb").unwrap_err();
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"a\n\nb").unwrap();
+
+    assert_eq!(
+        writer.get_ref().dest,
+        b"\x1b[2m\xFF\xFEa\n\n\x1b[2m\xFF\xFEb"
+    );
+
+    writer.set_indent("> ");
+    assert_eq!(writer.indent_bytes(), b"> ");
+    assert_eq!(writer.indent(), Some("> "));
+}
+
+#[test]
+fn test_binary_content() {
+    let mut dest = Vec::new();
+    {
+        let mut writer = OneByteAtATime(IndentWriter::new("> ", &mut dest));
+        writer.write_all(b"\xFF\xF0\x9F\n\0\n\n\x98\x80").unwrap();
+    }
+    assert_eq!(dest, b"> \xFF\xF0\x9F\n> \0\n\n> \x98\x80");
+}
+
+#[test]
+fn test_lossy_utf8() {
+    let mut writer = IndentWriter::new("> ", Vec::new()).lossy_utf8(true);
+
+    // Invalid bytes next to newlines
+    writer.write_all(b"a\xFF\n\xFE\n\n\xC0b\n").unwrap();
+
+    // A valid sequence split across writes, then an invalid one
+    assert_eq!(writer.write(b"\xF0\x9F").unwrap(), 2);
+    assert_eq!(writer.write(b"\x98").unwrap(), 1);
+    assert_eq!(writer.write(b"\x80\xF0\x9F\x98").unwrap(), 1);
+    assert_eq!(writer.write(b"\xF0\x9F\x98").unwrap(), 3);
+    assert_eq!(writer.write(b"\n").unwrap(), 1);
+    writer.write_all(b"\xE2\x82").unwrap();
+
+    let output = writer.into_inner().unwrap();
+    assert_eq!(
+        from_utf8(&output),
+        Ok("> a\u{FFFD}\n> \u{FFFD}\n\n> \u{FFFD}b\n> 😀\u{FFFD}\n> \u{FFFD}")
+    );
+}
+
+#[test]
+fn test_lossy_utf8_matches_from_utf8_lossy() {
+    let pieces: &[&[u8]] = &[
+        b"a",
+        b"\n",
+        b"\xFF",
+        b"\xF0\x9F",
+        b"\x98\x80",
+        b"\xE2",
+        b"\x82\xAC",
+    ];
+
+    // Every sequence of three pieces, written in every split
+    for a in pieces {
+        for b in pieces {
+            for c in pieces {
+                let input = [*a, *b, *c].concat();
+                let lossy = String::from_utf8_lossy(&input);
+                let expected = indent_write::indent_str(&lossy, "> ");
+
+                for split in 0..=input.len() {
+                    let mut dest = Vec::new();
+                    {
+                        let writer = OneByteAtATime(&mut dest);
+                        let writer = IndentWriter::new("> ", writer).lossy_utf8(true);
+                        let mut writer = OneByteAtATime(writer);
+
+                        writer.write_all(&input[..split]).unwrap();
+                        writer.write_all(&input[split..]).unwrap();
+                        writer.0.into_inner().unwrap();
+                    }
+
+                    assert_eq!(from_utf8(&dest), Ok(&*expected), "{:?} / {}", input, split);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lossy_utf8_short_writes() {
+    // Valid characters are never split, even if the inner writer only
+    // accepts part of one
+    for limit in 1..=4 {
+        let writer = Vectored {
+            dest: Vec::new(),
+            limit,
+            writes: 0,
+        };
+        let mut writer = IndentWriter::new("> ", writer).lossy_utf8(true);
+
+        writer
+            .write_all(b"\xF0\x9F\x98\x80\n\xC3\xA9 \xFF")
+            .unwrap();
+        writer.write_all(b"\xFF\n").unwrap();
+        writer.flush().unwrap();
+
+        let output = writer.into_inner().unwrap().dest;
+        assert_eq!(
+            from_utf8(&output),
+            Ok("> 😀\n> é \u{FFFD}\u{FFFD}\n"),
+            "limit: {}",
+            limit
+        );
+    }
+
+    let mut dest = Vec::new();
+    let mut writer = IndentWriter::new("> ", OneByteAtATime(&mut dest)).lossy_utf8(true);
+    writer.write_all("😀\n".as_bytes()).unwrap();
+    writer.into_inner().unwrap();
+    assert_eq!(from_utf8(&dest), Ok("> 😀\n"));
+}
+
+#[test]
+fn test_lossy_utf8_errors() {
+    let mut writer = IndentWriter::new(
+        "",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 1,
+        },
+    )
+    .lossy_utf8(true);
+
+    // The invalid byte is accepted, even though its replacement isn't
+    // entirely written
+    assert_eq!(writer.write(b"\xFFa").unwrap(), 1);
+    writer.write(b"a").unwrap_err();
+
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"a").unwrap();
+    assert_eq!(from_utf8(&writer.get_ref().dest), Ok("\u{FFFD}a"));
+}
+
+#[test]
+fn test_dynamic_indent_partial_writes() {
+    let mut calls = Vec::new();
+    let mut dest = Vec::new();
+    {
+        let mut writer = DynamicIndentWriter::new(
+            |line: usize, dest: &mut dyn Write| {
+                calls.push(line);
+                write!(dest, "{:03}| ", line)
+            },
+            OneByteAtATime(&mut dest),
+        );
+
+        write!(writer, "a\n\nb\nc").unwrap();
+        assert_eq!(writer.current_line(), 3);
+    }
+
+    assert_eq!(from_utf8(&dest), Ok("000| a\n\n002| b\n003| c"));
+    assert_eq!(calls, [0, 2, 3]);
+}
+
+#[test]
+fn test_dynamic_indent_errors() {
+    let mut calls = 0;
+    let mut writer = DynamicIndentWriter::new(
+        |_, dest: &mut dyn Write| {
+            calls += 1;
+            dest.write_all(b"-> ")
+        },
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 0,
+        },
+    );
+
+    // Neither an error before the indent is written, nor one partway
+    // through it, calls the callback again
+    writer.write_all(b"a\n").unwrap_err();
+    writer.get_mut().remaining = 2;
+    writer.write_all(b"a\n").unwrap_err();
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"a\nb").unwrap();
+
+    let dest = writer.into_inner().unwrap().dest;
+    assert_eq!(from_utf8(&dest), Ok("-> a\n-> b"));
+    assert_eq!(calls, 2);
+
+    // Errors from the callback are returned, and it's called again
+    let mut fail = true;
+    let mut writer = DynamicIndentWriter::new(
+        |_, dest: &mut dyn Write| match std::mem::replace(&mut fail, false) {
+            true => Err(io::Error::other("no indent")),
+            false => dest.write_all(b"> "),
+        },
+        Vec::new(),
+    );
+
+    assert_eq!(writer.write(b"a").unwrap_err().to_string(), "no indent");
+    writer.write_all(b"a").unwrap();
+    assert_eq!(writer.get_ref(), b"> a");
+}
+
+#[test]
+fn test_numbered_writer_partial_writes() {
+    let mut dest = Vec::new();
+    {
+        let writer = NumberedWriter::new(OneByteAtATime(&mut dest))
+            .start(9)
+            .separator(String::from(" | "));
+        let mut writer = OneByteAtATime(writer);
+
+        write!(writer, "a\n\n").unwrap();
+        assert_eq!(writer.0.current_line(), 11);
+        write!(writer, "b\nc").unwrap();
+        assert_eq!(writer.0.current_line(), 12);
+    }
+
+    assert_eq!(from_utf8(&dest), Ok("9 | a\n\n11 | b\n12 | c"));
+}
+
+#[test]
+fn test_numbered_writer_errors() {
+    let mut writer = NumberedWriter::new(FailAfter {
+        dest: Vec::new(),
+        remaining: 2,
+    })
+    .start(100);
+
+    // The number is interrupted partway through, and resumed
+    writer.write_all(b"a\nb\n").unwrap_err();
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"a\nb\n").unwrap();
+
+    let dest = writer.into_inner().unwrap().dest;
+    assert_eq!(from_utf8(&dest), Ok("100: a\n101: b\n"));
+}
+
+#[test]
+fn test_first_line_prefix_partial_writes() {
+    let mut dest = Vec::new();
+    {
+        let writer = IndentWriter::with_first_line_prefix("😀 ", "\t", OneByteAtATime(&mut dest));
+        let mut writer = OneByteAtATime(writer);
+
+        write!(writer, "a\nb\n").unwrap();
+        writer.0.start_record();
+        write!(writer, "c\n\nd").unwrap();
+    }
+    assert_eq!(from_utf8(&dest), Ok("😀 a\n\tb\n😀 c\n\n\td"));
+}
+
+#[test]
+fn test_start_record_mid_indent() {
+    let mut writer = IndentWriter::with_first_line_prefix(
+        "first: ",
+        "rest: ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 0,
+        },
+    );
+
+    // Nothing has been written yet, so the record starts with this line
+    writer.write_all(b"a\n").unwrap_err();
+    writer.start_record();
+    writer.get_mut().remaining = 11;
+    writer.write_all(b"a\nb\n").unwrap_err();
+
+    // The continuation indent is partly written, so it's finished first
+    writer.start_record();
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"b\nc\n").unwrap();
+
+    assert_eq!(
+        from_utf8(&writer.get_ref().dest),
+        Ok("first: a\nrest: b\nfirst: c\n")
+    );
+}
+
+#[test]
+fn test_hanging_partial_writes() {
+    let mut dest = Vec::new();
+    {
+        let writer = IndentWriter::new_hanging("    ", OneByteAtATime(&mut dest));
+        let mut writer = OneByteAtATime(writer);
+
+        for record in ["E1: bad\ninput\n\n", "E2: missing\nfile\n"] {
+            writer.0.start_record();
+            write!(writer, "{}", record).unwrap();
+        }
+    }
+    assert_eq!(
+        from_utf8(&dest),
+        Ok("E1: bad\n    input\n\nE2: missing\n    file\n")
+    );
+
+    // Starting a record partway through an indent finishes that indent
+    let mut writer = IndentWriter::new_hanging(
+        "    ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 4,
+        },
+    );
+    writer.write_all(b"a\nb\n").unwrap_err();
+    writer.start_record();
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"b\nc\n").unwrap();
+    assert_eq!(from_utf8(&writer.get_ref().dest), Ok("a\n    b\nc\n"));
+}
+
+#[test]
+fn test_crlf_partial_writes() {
+    let content = "one\r\n\r\ntwo\n\nthree\r\n\r\n\r\nfour\r\n";
+    let expected = "  one\r\n\r\n  two\n\n  three\r\n\r\n\r\n  four\r\n";
+
+    let mut dest = Vec::new();
+    write!(IndentWriter::new("  ", &mut dest), "{}", content).unwrap();
+    assert_eq!(from_utf8(&dest), Ok(expected));
+
+    // Splitting each `\r\n` between writes
+    let mut dest = Vec::new();
+    {
+        let mut writer = IndentWriter::new("  ", &mut dest);
+        for piece in content.split_inclusive('\r') {
+            writer.write_all(piece.as_bytes()).unwrap();
+        }
+    }
+    assert_eq!(from_utf8(&dest), Ok(expected));
+
+    let mut dest = Vec::new();
+    write!(
+        OneByteAtATime(IndentWriter::new("  ", OneByteAtATime(&mut dest))),
+        "{}",
+        content
+    )
+    .unwrap();
+    assert_eq!(from_utf8(&dest), Ok(expected));
+}
+
+#[test]
+fn test_crlf_line_ending_partial_writes() {
+    let content = "one\ntwo\r\n\nthree\r\rfour\n";
+    let expected = "  one\r\n  two\r\n\r\n  three\r\rfour\r\n";
+
+    let mut dest = Vec::new();
+    {
+        let writer =
+            IndentWriter::new("  ", OneByteAtATime(&mut dest)).line_ending(LineEnding::CrLf);
+        let mut writer = OneByteAtATime(writer);
+        write!(writer, "{}", content).unwrap();
+        writer.flush().unwrap();
+    }
+    assert_eq!(from_utf8(&dest), Ok(expected));
+
+    // An `\r\n` split between writes isn't translated again
+    let mut dest = Vec::new();
+    {
+        let mut writer = IndentWriter::new("  ", &mut dest).line_ending(LineEnding::CrLf);
+        for piece in content.split_inclusive('\r') {
+            writer.write_all(piece.as_bytes()).unwrap();
+        }
+    }
+    assert_eq!(from_utf8(&dest), Ok(expected));
+}
+
+#[test]
+fn test_crlf_line_ending_errors() {
+    // The inner writer accepts the `\r` of the translated newline, but not
+    // the `\n`. The newline is reported as written.
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 5,
+        },
+    )
+    .line_ending(LineEnding::CrLf);
+    writer.write_all(b"ab\n").unwrap();
+    assert_eq!(writer.get_ref().dest, b"  ab\r");
+
+    // The `\n` is retried by the next write
+    writer.write(b"c").unwrap_err();
+    writer.get_mut().remaining = 1;
+    writer.write(b"c").unwrap_err();
+    assert_eq!(writer.get_ref().dest, b"  ab\r\n");
+
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"c\n").unwrap();
+    writer.flush().unwrap();
+    assert_eq!(from_utf8(&writer.get_ref().dest), Ok("  ab\r\n  c\r\n"));
+
+    // Or by flushing
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 4,
+        },
+    )
+    .line_ending(LineEnding::CrLf);
+    writer.write_all(b"a\n").unwrap();
+    writer.flush().unwrap_err();
+    writer.get_mut().remaining = 1;
+    writer.flush().unwrap();
+    assert_eq!(writer.into_inner().unwrap().dest, b"  a\r\n");
+}
+
+// A writer that accepts at most `limit` bytes from each vectored write, and
+// counts the writes
+#[derive(Debug, Default)]
+struct Vectored {
+    dest: Vec<u8>,
+    limit: usize,
+    writes: usize,
+}
+
+impl Write for Vectored {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[io::IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.writes += 1;
+        let mut written = 0;
+        for buf in bufs {
+            let n = buf.len().min(self.limit - written);
+            self.dest.extend_from_slice(&buf[..n]);
+            written += n;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_vectored() {
+    // Each indent is written with its line
+    let mut writer = IndentWriter::new(
+        "    ",
+        Vectored {
+            limit: usize::MAX,
+            ..Vectored::default()
+        },
+    );
+    let content = "one\n\ntwo\nthree";
+    let mut buf = content.as_bytes();
+    while !buf.is_empty() {
+        let n = writer
+            .write_vectored(&[io::IoSlice::new(&[]), io::IoSlice::new(buf)])
+            .unwrap();
+        buf = &buf[n..];
+    }
+    assert_eq!(
+        from_utf8(&writer.get_ref().dest),
+        Ok("    one\n\n    two\n    three")
+    );
+    assert_eq!(writer.get_ref().writes, 4);
+
+    // Partial writes of the indent and the line
+    for limit in 1..8 {
+        let mut writer = IndentWriter::new(
+            "    ",
+            Vectored {
+                limit,
+                ..Vectored::default()
+            },
+        );
+        let mut buf = content.as_bytes();
+        while !buf.is_empty() {
+            let n = writer.write_vectored(&[io::IoSlice::new(buf)]).unwrap();
+            assert!(n > 0);
+            buf = &buf[n..];
+        }
+        assert_eq!(
+            from_utf8(&writer.get_ref().dest),
+            Ok("    one\n\n    two\n    three"),
+            "limit: {}",
+            limit
+        );
+    }
+}
+
+#[test]
+fn test_write_all() {
+    let content = "one\r\n\r\n  two\n\n\nthree\nfour";
+
+    // Each run of content between indents is written at once
+    let mut writer = IndentWriter::new(
+        "\t",
+        Vectored {
+            limit: usize::MAX,
+            ..Vectored::default()
+        },
+    );
+    writer.write_all(content.as_bytes()).unwrap();
+    writer.write_all(b"\n\nfive").unwrap();
+    assert_eq!(
+        from_utf8(&writer.get_ref().dest),
+        Ok("\tone\r\n\r\n\t  two\n\n\n\tthree\n\tfour\n\n\tfive")
+    );
+    assert_eq!(writer.get_ref().writes, 11);
+
+    // This matches writing one byte at a time
+    for split in 0..content.len() {
+        let (head, tail) = content.as_bytes().split_at(split);
+        let mut writer = IndentWriter::new("\t", Vec::new());
+        writer.write_all(head).unwrap();
+        writer.write_all(tail).unwrap();
+
+        let mut expected = Vec::new();
+        write!(
+            OneByteAtATime(IndentWriter::new("\t", &mut expected)),
+            "{}",
+            content
+        )
+        .unwrap();
+        assert_eq!(writer.get_ref(), &expected, "split: {}", split);
+    }
+
+    // Errors from the inner writer are returned
+    let mut writer = IndentWriter::new(
+        "\t",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 6,
+        },
+    );
+    writer.write_all(content.as_bytes()).unwrap_err();
+    assert_eq!(writer.get_ref().dest, b"\tone\r\n");
+}
+
+#[test]
+fn test_write_fmt() {
+    let mut writer = IndentWriter::new(
+        "  ",
+        Vectored {
+            limit: usize::MAX,
+            ..Vectored::default()
+        },
+    );
+
+    // The formatted content is written at once, rather than in fragments
+    writeln!(writer, "{}: {}\n{}", "key", 10, "value").unwrap();
+    assert_eq!(
+        from_utf8(&writer.get_ref().dest),
+        Ok("  key: 10\n  value\n")
+    );
+    assert_eq!(writer.get_ref().writes, 4);
+}
+
+// A writer that fails the first `failures` flushes
+#[derive(Debug, Default)]
+struct FailFlush {
+    dest: Vec<u8>,
+    failures: usize,
+    flushed: usize,
+}
+
+impl Write for FailFlush {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.dest.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.failures {
+            0 => {
+                self.flushed = self.dest.len();
+                Ok(())
+            }
+            _ => {
+                self.failures -= 1;
+                Err(io::Error::other("flush failed"))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_finish() {
+    // The inner writer is flushed, and its error is returned with the writer
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailFlush {
+            failures: 1,
+            ..FailFlush::default()
+        },
+    );
+    write!(writer, "one\ntwo").unwrap();
+
+    let err = writer.finish().unwrap_err();
+    assert_eq!(err.error().to_string(), "flush failed");
+
+    let inner = err.into_inner().finish().unwrap();
+    assert_eq!(from_utf8(&inner.dest), Ok("  one\n  two"));
+    assert_eq!(inner.flushed, inner.dest.len());
+
+    // A partly written indent is finished first
+    let mut writer = IndentWriter::new(
+        "    ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 6,
+        },
+    );
+    writer.write_all(b"a\nb").unwrap_err();
+    let err = writer.finish().unwrap_err();
+    let mut writer = err.into_inner();
+    writer.get_mut().remaining = 4;
+    assert_eq!(writer.finish().unwrap().dest, b"    a\n    ");
+}
+
+#[test]
+fn test_into_parts() {
+    // Nothing is written to a failing writer, and the pending output is
+    // returned
+    let failing = || FailAfter {
+        dest: Vec::new(),
+        remaining: 0,
+    };
+
+    let mut writer = IndentWriter::new("  ", failing()).lossy_utf8(true);
+    writer.write_all(b"\xF0\x9F").unwrap();
+    let (inner, pending) = writer.into_parts();
+    assert_eq!(inner.dest, b"");
+    assert_eq!(from_utf8(&pending), Ok("  \u{FFFD}"));
+
+    // The rest of a partly written indent
+    let mut writer = IndentWriter::new(
+        "    ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 3,
+        },
+    );
+    writer.write_all(b"a").unwrap_err();
+    let (inner, pending) = writer.into_parts();
+    assert_eq!(inner.dest, b"   ");
+    assert_eq!(pending, b" ");
+
+    // The newline of a translated line ending
+    let mut writer = IndentWriter::new(
+        "",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 2,
+        },
+    )
+    .line_ending(LineEnding::CrLf);
+    writer.write_all(b"a\n").unwrap();
+    writer.write_all(b"b").unwrap_err();
+    let (inner, pending) = writer.into_parts();
+    assert_eq!(inner.dest, b"a\r");
+    assert_eq!(pending, b"\n");
+}
+
+#[test]
+fn test_pending_state() {
+    let mut writer = IndentWriter::new(
+        "    ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 7,
+        },
+    );
+    assert!(writer.needs_indent());
+    assert!(!writer.has_pending());
+
+    writer.write_all(b"a").unwrap();
+    assert!(!writer.needs_indent());
+
+    writer.write_all(b"\nb").unwrap_err();
+    assert!(writer.needs_indent());
+    assert_eq!(writer.pending_bytes(), 3);
+
+    // Pending output is written without flushing the inner writer
+    writer.flush_pending().unwrap_err();
+    writer.get_mut().remaining = 3;
+    writer.flush_pending().unwrap();
+    assert!(!writer.has_pending());
+    assert!(!writer.needs_indent());
+    assert_eq!(writer.get_ref().dest, b"    a\n    ");
+
+    // An incomplete UTF-8 sequence is held
+    let mut writer = IndentWriter::new("", FailFlush::default()).lossy_utf8(true);
+    writer.write_all(b"\xF0\x9F").unwrap();
+    assert_eq!(writer.pending_bytes(), 2);
+    writer.flush_pending().unwrap();
+    assert_eq!(writer.pending_bytes(), 2);
+    assert_eq!(writer.get_ref().flushed, 0);
+}
+
+#[test]
+fn test_indent_reader_small_buffers() {
+    let content = "one\n\ntwo\r\n\r\n  three\n\n";
+    let expected = "    one\n\n    two\r\n\r\n      three\n\n";
+
+    for size in 1..=3 {
+        let mut reader = IndentReader::new("    ", content.as_bytes());
+        let mut output = Vec::new();
+        let mut buf = [0; 3];
+
+        loop {
+            match reader.read(&mut buf[..size]).unwrap() {
+                0 => break,
+                n => output.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        assert_eq!(from_utf8(&output), Ok(expected), "size: {}", size);
+    }
+
+    // There's no indent after the end of the content, and the last line
+    // doesn't need a newline
+    let mut output = String::new();
+    IndentReader::new("", "a\nb".as_bytes())
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "a\nb");
+
+    let mut output = Vec::new();
+    io::copy(
+        &mut IndentReader::new("| ", io::BufReader::with_capacity(2, "a\nb".as_bytes())),
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!(output, b"| a\n| b");
+}
+
+#[test]
+fn test_indent_reader_lines() {
+    let content = "one\n\n  two\nthree";
+
+    for capacity in 1..4 {
+        let reader = IndentReader::new(
+            "> ",
+            io::BufReader::with_capacity(capacity, content.as_bytes()),
+        );
+        let lines: Vec<String> = reader.lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["> one", "", ">   two", "> three"]);
+    }
+
+    // The prefix is returned by itself, and can be partly consumed
+    let mut reader = IndentReader::new("> ", content.as_bytes());
+    assert_eq!(reader.fill_buf().unwrap(), b"> ");
+    reader.consume(1);
+    assert_eq!(reader.fill_buf().unwrap(), b" ");
+    reader.consume(1);
+    assert_eq!(reader.fill_buf().unwrap(), b"one\n");
+    reader.consume(2);
+    assert_eq!(reader.fill_buf().unwrap(), b"e\n");
+    reader.consume(2);
+    assert_eq!(reader.fill_buf().unwrap(), b"\n");
+}
+
+// A reader that returns `Interrupted` before each read
+struct Interrupting<R> {
+    reader: R,
+    interrupt: bool,
+}
+
+impl<R: Read> Read for Interrupting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(2);
+        self.interrupt = !self.interrupt;
+        match self.interrupt {
+            true => Err(io::ErrorKind::Interrupted.into()),
+            false => self.reader.read(&mut buf[..len]),
+        }
+    }
+}
+
+#[test]
+fn test_copy_indented() {
+    let content: &[u8] = b"one\n\n\xFFtwo\n";
+
+    let mut reader = Interrupting {
+        reader: content,
+        interrupt: false,
+    };
+    let mut output = Vec::new();
+    assert_eq!(copy_indented(&mut reader, &mut output, "  ").unwrap(), 10);
+    assert_eq!(output, b"  one\n\n  \xFFtwo\n");
+
+    let mut reader = io::BufReader::with_capacity(3, content);
+    let mut output = Vec::new();
+    assert_eq!(
+        copy_indented_buf(&mut reader, &mut output, "  ").unwrap(),
+        10
+    );
+    assert_eq!(output, b"  one\n\n  \xFFtwo\n");
+
+    // Write errors are returned
+    let mut output = FailAfter {
+        dest: Vec::new(),
+        remaining: 4,
+    };
+    copy_indented(&mut &*content, &mut output, "  ").unwrap_err();
+    assert_eq!(output.dest, b"  on");
+}
+
+#[test]
+fn test_fmt_write() {
+    fn render(dest: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        write!(dest, "{}\n{}", "one", "two")
+    }
+
+    // Partial writes are retried
+    let mut dest = Vec::new();
+    let mut writer = IndentWriter::new("  ", OneByteAtATime(&mut dest));
+    render(&mut writer).unwrap();
+    assert!(writer.take_error().is_none());
+    assert_eq!(from_utf8(&dest), Ok("  one\n  two"));
+
+    // The error from the inner writer is kept
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 4,
+        },
+    );
+    render(&mut writer).unwrap_err();
+
+    let err = writer.take_error().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert_eq!(err.to_string(), "out of space");
+    assert!(writer.take_error().is_none());
+}
+
+// A writer that writes one byte at a time, and panics on every `panic_every`th
+// call without writing anything
+#[derive(Debug)]
+struct PanicEvery {
+    dest: Vec<u8>,
+    calls: usize,
+    panic_every: usize,
+}
+
+impl Write for PanicEvery {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.calls += 1;
+        if self.calls.is_multiple_of(self.panic_every) {
+            panic!("scripted panic");
+        }
+
+        match *buf {
+            [] => Ok(0),
+            [b, ..] => {
+                self.dest.push(b);
+                Ok(1)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_panic_safety() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let input: &[u8] = b"one\n\n\xF0\x9F\x98\x80 two\xF0\x9F\nthree\xFF\n";
+    let expected = "    one\r\n\r\n    😀 two\u{FFFD}\r\n    three\u{FFFD}\r\n";
+
+    for panic_every in 2..6 {
+        let mut writer = IndentWriter::new(
+            "    ",
+            PanicEvery {
+                dest: Vec::new(),
+                calls: 0,
+                panic_every,
+            },
+        )
+        .lossy_utf8(true)
+        .line_ending(LineEnding::CrLf);
+
+        // Retry each write after a panic, one byte at a time
+        let mut buf = input;
+        while !buf.is_empty() {
+            if let Ok(n) = catch_unwind(AssertUnwindSafe(|| writer.write(&buf[..1]))) {
+                buf = &buf[n.unwrap()..];
+            }
+        }
+
+        while catch_unwind(AssertUnwindSafe(|| writer.flush())).is_err() {}
+        let output = writer.into_inner().unwrap().dest;
+        assert_eq!(from_utf8(&output), Ok(expected), "every {}", panic_every);
+    }
+}
+
+#[test]
+fn test_line_buffered() {
+    let content = "one\n\ntwo\n  three\nfour";
+    let counting = || Vectored {
+        limit: usize::MAX,
+        ..Vectored::default()
+    };
+
+    let mut unbuffered = IndentWriter::new("    ", counting());
+    write!(unbuffered, "{}", content).unwrap();
+    unbuffered.flush().unwrap();
+    assert_eq!(unbuffered.get_ref().writes, 8);
+
+    // Each line is written at once, including its indent
+    for capacity in [16, 1024] {
+        let mut writer = IndentWriter::new("    ", counting()).line_buffered(capacity);
+        for piece in content.split_inclusive(' ') {
+            writer.write_all(piece.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert_eq!(writer.get_ref().dest, unbuffered.get_ref().dest);
+        assert_eq!(writer.get_ref().writes, 4);
+    }
+
+    // Lines longer than the capacity spill
+    let mut writer = IndentWriter::new("    ", counting()).line_buffered(4);
+    write!(writer, "{}", content).unwrap();
+    let writer = writer.into_inner().unwrap();
+    assert_eq!(writer.dest, unbuffered.get_ref().dest);
+    assert!(writer.writes > 4);
+
+    // Partial writes by the inner writer
+    let mut dest = Vec::new();
+    {
+        let mut writer = IndentWriter::new("    ", OneByteAtATime(&mut dest)).line_buffered(1024);
+        write!(writer, "{}", content).unwrap();
+        assert!(writer.has_pending());
+        writer.flush_pending().unwrap();
+    }
+    assert_eq!(dest, unbuffered.get_ref().dest);
+}
+
+#[test]
+fn test_line_buffered_errors() {
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 3,
+        },
+    )
+    .line_buffered(64);
+
+    // The line is accepted, and the error is returned by the next write
+    writer.write_all(b"one\n").unwrap();
+    assert_eq!(writer.get_ref().dest, b"  o");
+    assert_eq!(writer.pending_bytes(), 3);
+    writer.write_all(b"two").unwrap_err();
+
+    writer.get_mut().remaining = 100;
+    writer.write_all(b"two").unwrap();
+    assert_eq!(writer.get_ref().dest, b"  one\n");
+
+    let (inner, pending) = writer.into_parts();
+    assert_eq!(inner.dest, b"  one\n");
+    assert_eq!(pending, b"  two");
+
+    // If none of the line can be written, the error is returned right away
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 0,
+        },
+    )
+    .line_buffered(64);
+
+    writer.write_all(b"one").unwrap();
+    writer.write(b"\n").unwrap_err();
+    assert_eq!(writer.pending_bytes(), 5);
+
+    writer.get_mut().remaining = 1;
+    writer.write(b"\n").unwrap_err();
+    assert_eq!(writer.pending_bytes(), 4);
+
+    writer.get_mut().remaining = 100;
+    assert_eq!(writer.write(b"\n").unwrap(), 1);
+    assert_eq!(writer.get_ref().dest, b"  one\n");
+    assert!(!writer.has_pending());
+}
+
+// A writer that follows a repeating script: `None` returns `WouldBlock`, and
+// `Some(n)` accepts up to `n` bytes
+#[derive(Debug)]
+struct Scripted {
+    dest: Vec<u8>,
+    script: &'static [Option<usize>],
+    step: usize,
+}
+
+impl Write for Scripted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let step = self.script[self.step % self.script.len()];
+        self.step += 1;
+
+        match step {
+            None => Err(io::ErrorKind::WouldBlock.into()),
+            Some(n) => {
+                let n = n.min(buf.len());
+                self.dest.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.script[self.step % self.script.len()] {
+            None => {
+                self.step += 1;
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_would_block() {
+    let scripts: &[&'static [Option<usize>]] = &[
+        &[None, Some(1)],
+        &[Some(1), None, Some(2), None],
+        &[None, None, Some(3), Some(1), None, Some(usize::MAX)],
+    ];
+
+    let input: &[u8] = b"one\n\n\xF0\x9F\x98\x80 two\xF0\x9F\nthree\r\n\xFF\n";
+
+    for &script in scripts {
+        let configs: [fn(IndentWriter<Scripted>) -> IndentWriter<Scripted>; 4] = [
+            |writer| writer,
+            |writer| writer.lossy_utf8(true),
+            |writer| writer.line_ending(LineEnding::CrLf),
+            |writer| writer.line_buffered(8),
+        ];
+
+        for (i, config) in configs.iter().enumerate() {
+            let expected: Vec<u8> = {
+                let mut writer = config(IndentWriter::new(
+                    "    ",
+                    Scripted {
+                        dest: Vec::new(),
+                        script: &[Some(usize::MAX)],
+                        step: 0,
+                    },
+                ));
+                writer.write_all(input).unwrap();
+                writer.into_inner().unwrap().dest
+            };
+
+            let mut writer = config(IndentWriter::new(
+                "    ",
+                Scripted {
+                    dest: Vec::new(),
+                    script,
+                    step: 0,
+                },
+            ));
+
+            // Retry with the same buffer after each error, split across
+            // line and code point boundaries
+            for chunk in input.chunks(3) {
+                let mut chunk = chunk;
+                while !chunk.is_empty() {
+                    match writer.write(chunk) {
+                        Ok(n) => chunk = &chunk[n..],
+                        Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+                    }
+                }
+            }
+
+            while writer.flush().is_err() {}
+            let output = writer.into_inner().unwrap().dest;
+            assert_eq!(output, expected, "config {}, script {:?}", i, script);
+        }
+    }
+}
+
+// A writer that writes one byte at a time, and returns `Ok(0)` once it has
+// written `remaining` bytes
+#[derive(Debug)]
+struct ZeroAfter {
+    dest: Vec<u8>,
+    remaining: usize,
+}
+
+impl Write for ZeroAfter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match (self.remaining, buf) {
+            (0, _) | (_, []) => Ok(0),
+            (_, [b, ..]) => {
+                self.remaining -= 1;
+                self.dest.push(*b);
+                Ok(1)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_zero() {
+    // Mid-indent, mid-line, and mid-code point
+    for (remaining, lossy) in [(2, false), (6, false), (6, true), (12, true)] {
+        let mut writer = IndentWriter::new(
+            "    ",
+            ZeroAfter {
+                dest: Vec::new(),
+                remaining,
+            },
+        )
+        .lossy_utf8(lossy);
+
+        let mut buf: &[u8] = b"ab\xF0\x9F\x98\x80\ncd";
+        let err = loop {
+            match writer.write(buf) {
+                Ok(n) => {
+                    assert!(n > 0);
+                    buf = &buf[n..];
+                }
+                Err(err) => break err,
+            }
+        };
+
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero, "{}", remaining);
+        assert_eq!(writer.get_ref().dest.len(), remaining);
+        assert_eq!(
+            writer
+                .write_vectored(&[io::IoSlice::new(buf)])
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::WriteZero
+        );
+    }
+
+    // Empty writes are still fine
+    let mut writer = IndentWriter::new(
+        "    ",
+        ZeroAfter {
+            dest: Vec::new(),
+            remaining: 0,
+        },
+    );
+    assert_eq!(writer.write(b"").unwrap(), 0);
+}
+
+#[test]
+fn test_reset() {
+    let failing = |remaining| FailAfter {
+        dest: Vec::new(),
+        remaining,
+    };
+
+    // After an error, reset and continue with a new writer, like a fresh
+    // writer
+    let content: &[u8] = b"one\n\ntwo\xF0\x9F\n";
+    let mut fresh = IndentWriter::with_first_line_prefix("- ", "  ", Vec::new()).lossy_utf8(true);
+    fresh.write_all(content).unwrap();
+    let expected = fresh.into_inner().unwrap();
+
+    for remaining in 0..expected.len() {
+        let mut writer =
+            IndentWriter::with_first_line_prefix("- ", "  ", failing(remaining)).lossy_utf8(true);
+        writer.start_record();
+        let _ = writer.write_all(content);
+        writer.write_all(b"\xF0\x9F").ok();
+
+        writer.reset();
+        writer.replace_writer(failing(100));
+        writer.write_all(content).unwrap();
+        let output = writer.into_inner().unwrap().dest;
+        assert_eq!(output, expected, "remaining: {}", remaining);
+    }
+
+    // The cleared output can be resent
+    for remaining in 0..expected.len() {
+        let mut writer =
+            IndentWriter::with_first_line_prefix("- ", "  ", failing(remaining)).lossy_utf8(true);
+        writer.start_record();
+        let mut buf = content;
+        while !buf.is_empty() {
+            match writer.write(buf) {
+                Ok(n) => buf = &buf[n..],
+                Err(_) => break,
+            }
+        }
+
+        let pending = writer.clear_pending();
+        assert!(!writer.has_pending());
+        writer.get_mut().remaining = 100;
+        writer.get_mut().dest.extend_from_slice(&pending);
+        writer.write_all(buf).unwrap();
+
+        let output = writer.into_inner().unwrap().dest;
+        assert_eq!(output, expected, "remaining: {}", remaining);
+    }
+}
+
+fn indent_error(err: &io::Error) -> Option<&IndentError> {
+    err.get_ref()?.downcast_ref()
+}
+
+#[test]
+fn test_error_context() {
+    let content = b"one\n\ntwo \xF0\x9F\x98\x80\nthree";
+
+    // The position in the error is the position in the output, including
+    // indents, with every writer configuration
+    for config in 0..4 {
+        for remaining in 0..32 {
+            let writer = IndentWriter::new(
+                "  ",
+                FailAfter {
+                    dest: Vec::new(),
+                    remaining,
+                },
+            );
+            let mut writer = match config {
+                0 => writer,
+                1 => writer.line_buffered(8),
+                2 => writer.line_ending(LineEnding::CrLf),
+                _ => writer.lossy_utf8(true),
+            }
+            .error_context(true);
+
+            let err = match writer.write_all(content).and_then(|()| writer.flush()) {
+                Ok(()) => continue,
+                Err(err) => err,
+            };
+
+            let dest = &writer.get_ref().dest;
+            let line = dest.iter().filter(|&&b| b == b'\n').count();
+            let column = dest.len() - dest.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            let context = indent_error(&err).expect("no context");
+
+            assert_eq!(err.kind(), io::ErrorKind::Other);
+            assert_eq!(
+                (context.line(), context.column()),
+                (line, column),
+                "config: {}, remaining: {}",
+                config,
+                remaining,
+            );
+
+            // The original error is the source, and isn't wrapped twice
+            assert!(indent_error(context.error()).is_none());
+            let source = std::error::Error::source(context).unwrap();
+            assert_eq!(source.to_string(), "out of space");
+        }
+    }
+
+    // Errors from flush have context, too
+    let failing = FailFlush {
+        dest: Vec::new(),
+        failures: 1,
+        flushed: 0,
+    };
+    let mut writer = IndentWriter::new("  ", failing).error_context(true);
+    write!(writer, "one\ntwo").unwrap();
+    let err = writer.flush().unwrap_err();
+    let context = indent_error(&err).unwrap();
+    assert_eq!((context.line(), context.column()), (1, 5));
+    let context = err.into_inner().unwrap().downcast::<IndentError>().unwrap();
+    assert_eq!(context.into_error().to_string(), "flush failed");
+
+    // It's disabled by default
+    let mut writer = IndentWriter::new(
+        "  ",
+        FailAfter {
+            dest: Vec::new(),
+            remaining: 4,
+        },
+    );
+    let err = write!(writer, "one\ntwo").unwrap_err();
+    assert!(indent_error(&err).is_none());
+    assert_eq!(err.to_string(), "out of space");
+
+    // Resetting the writer resets the position
+    writer.reset();
+    assert_eq!((writer.line(), writer.column()), (0, 0));
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "alloc")]
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use indent_write::fmt::IndentWriter;
+use indent_write::iter::indent_lines;
+
+const LINES: &[&str] = &["", "😀", " ", "\t😀 😀"];
+
+// Every sequence of up to 4 lines from LINES
+fn line_sequences() -> impl Iterator<Item = Vec<&'static str>> {
+    (0..=4u32).flat_map(|len| {
+        (0..LINES.len().pow(len)).map(move |mut n| {
+            (0..len)
+                .map(|_| {
+                    let line = LINES[n % LINES.len()];
+                    n /= LINES.len();
+                    line
+                })
+                .collect()
+        })
+    })
+}
+
+fn indent_with_writer(text: &str) -> String {
+    let mut writer = IndentWriter::new("\t", String::new());
+    writer.write_str(text).unwrap();
+    writer.into_inner()
+}
+
+#[test]
+fn test_indent_lines() {
+    let lines: Vec<_> = indent_lines(vec!["😀", "", "😀"], "\t").collect();
+    assert_eq!(lines, ["\t😀", "", "\t😀"]);
+    assert!(matches!(lines[1], Cow::Borrowed(_)));
+
+    let lines: Vec<_> = indent_lines(vec![String::new(), String::from("😀")], "\t")
+        .rev()
+        .collect();
+    assert_eq!(lines, ["\t😀", ""]);
+}
+
+#[test]
+fn test_indent_lines_matches_writer() {
+    for lines in line_sequences() {
+        let joined = lines.join("\n");
+        let indented: Vec<_> = indent_lines(lines.iter().copied(), "\t").collect();
+        assert_eq!(
+            indented.join("\n"),
+            indent_with_writer(&joined),
+            "{:?}",
+            lines
+        );
+    }
+}
+
+#[test]
+fn test_indent_lines_with_newlines_matches_writer() {
+    for lines in line_sequences() {
+        let joined: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let indented: String = indent_lines(lines.iter().copied(), "\t")
+            .with_newlines()
+            .collect();
+        assert_eq!(indented, indent_with_writer(&joined), "{:?}", lines);
+    }
+}
@@ -0,0 +1,60 @@
+//! Check that the `fmt` and `indentable` modules can be used from a `no_std`
+//! crate, with only `core::fmt::Write` destinations.
+
+#![no_std]
+
+use core::fmt::{self, Write};
+
+use indent_write::fmt::IndentWriter;
+use indent_write::indentable::Indentable;
+
+/// A fixed-capacity buffer, standing in for something like a serial port.
+struct Buffer {
+    data: [u8; 64],
+    len: usize,
+}
+
+impl Buffer {
+    fn new() -> Self {
+        Self {
+            data: [0; 64],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.data[..self.len]).unwrap()
+    }
+}
+
+impl Write for Buffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let dest = self
+            .data
+            .get_mut(self.len..self.len + s.len())
+            .ok_or(fmt::Error)?;
+        dest.copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_indent_writer() {
+    let mut writer = IndentWriter::new("  ", Buffer::new());
+    write!(writer, "a\n\n{}\n", 1).unwrap();
+    assert_eq!(writer.get_ref().as_str(), "  a\n\n  1\n");
+}
+
+#[test]
+fn test_indentable() {
+    let mut buffer = Buffer::new();
+    write!(buffer, "{}", "a\nb".indented("> ")).unwrap();
+    assert_eq!(buffer.as_str(), "> a\n> b");
+}
+
+#[test]
+fn test_buffer_full() {
+    let mut writer = IndentWriter::new("    ", Buffer::new());
+    assert!(write!(writer, "{:70}", "").is_err());
+}